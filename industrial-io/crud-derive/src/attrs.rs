@@ -0,0 +1,103 @@
+//! Attributes
+//!
+//! Helpers for collecting `#[crud(...)]` sub-attributes and reporting misuse as
+//! `syn::Error`s, so that mistakes show up as compile errors pointing at the offending
+//! tokens instead of panics inside the macro.
+
+use syn::{spanned::Spanned, Attribute, Meta, NestedMeta};
+
+use crate::TAG;
+
+/// collect all sub-attributes of `#[crud(...)]`, rejecting any keyword that is not in `known`
+///
+/// ```rust,ignore
+/// #[crud(id)]
+/// #[crud(single_index = "unique,asc")]
+/// ```
+///
+/// yields `[id, single_index = "unique,asc"]`
+pub fn crud_metas(attrs: &[Attribute], known: &[&str]) -> syn::Result<Vec<NestedMeta>> {
+    let mut result = vec![];
+
+    for attr in attrs.iter().filter(|a| a.path.is_ident(TAG)) {
+        let meta_list = match attr.parse_meta()? {
+            Meta::List(meta_list) => meta_list,
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected `#[crud(...)]`, e.g. `#[crud(id)]`",
+                ))
+            }
+        };
+
+        for nested_meta in meta_list.nested.into_iter() {
+            let path = match &nested_meta {
+                NestedMeta::Meta(meta) => meta.path(),
+                NestedMeta::Lit(lit) => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "unexpected literal in `#[crud(...)]`, expected a keyword such as `id`",
+                    ))
+                }
+            };
+
+            let ident = path
+                .get_ident()
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            if !known.contains(&ident.as_str()) {
+                return Err(unknown_keyword(path, "attribute", &ident, known));
+            }
+
+            result.push(nested_meta);
+        }
+    }
+
+    Ok(result)
+}
+
+/// error for an unknown keyword, with a "did you mean" hint when a close match exists
+pub fn unknown_keyword<T: Spanned>(
+    tokens: T,
+    kind: &str,
+    found: &str,
+    expected: &[&str],
+) -> syn::Error {
+    let expected_list = expected
+        .iter()
+        .map(|k| format!("`{k}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut message = format!("unknown crud {kind} `{found}`, expected one of: {expected_list}");
+    if let Some(suggestion) = did_you_mean(found, expected) {
+        message.push_str(&format!("\nhelp: did you mean `{suggestion}`?"));
+    }
+
+    syn::Error::new(tokens.span(), message)
+}
+
+/// closest candidate by edit distance, if any is close enough to be a likely typo
+fn did_you_mean<'a>(found: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|c| (levenshtein(found, c), *c))
+        .filter(|(d, c)| *d <= c.len().max(found.len()) / 3 + 1)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+
+    prev[b.len()]
+}
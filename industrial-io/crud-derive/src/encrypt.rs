@@ -0,0 +1,143 @@
+//! Encrypt
+//!
+//! Client-side encrypted fields, generating `BaseCRUD::encrypted_fields`:
+//!
+//! ```rust,ignore
+//! struct TestCrud {
+//!     #[crud(encrypt)]
+//!     data: Option<JsonValue>,              // random, cannot be queried
+//!     #[crud(encrypt(deterministic), single_index = "unique")]
+//!     code: String,                         // equality queries and unique indexes
+//!     ...
+//! }
+//! ```
+
+use quote::quote;
+use syn::{Field, Meta, NestedMeta};
+
+use crate::attrs::{crud_metas, unknown_keyword};
+use crate::indexes::TEXT;
+use crate::rename::{bson_name, RenameRule};
+use crate::{NamedFields, COMPOUND_INDEX, SINGLE_INDEX};
+
+pub const ENCRYPT: &str = "encrypt";
+const DETERMINISTIC: &str = "deterministic";
+
+/// how a field is encrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptMode {
+    Random,
+    Deterministic,
+}
+
+impl quote::ToTokens for EncryptMode {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            EncryptMode::Random => quote! { _crud::EncryptMode::Random },
+            EncryptMode::Deterministic => quote! { _crud::EncryptMode::Deterministic },
+        });
+    }
+}
+
+/// `#[crud(encrypt)]` or `#[crud(encrypt(deterministic))]` of a field
+pub fn encrypt_mode(field: &Field, known: &[&str]) -> syn::Result<Option<EncryptMode>> {
+    let mut result = None;
+    let mut indexed = None;
+
+    for nested_meta in crud_metas(&field.attrs, known)? {
+        let meta = match &nested_meta {
+            NestedMeta::Meta(meta) => meta,
+            NestedMeta::Lit(_) => continue,
+        };
+        if [SINGLE_INDEX, COMPOUND_INDEX, TEXT]
+            .iter()
+            .any(|name| meta.path().is_ident(name))
+        {
+            indexed = Some(meta.clone());
+        }
+        if !meta.path().is_ident(ENCRYPT) {
+            continue;
+        }
+
+        let mode = match meta {
+            Meta::Path(_) => EncryptMode::Random,
+            Meta::List(ml) => match ml.nested.iter().collect::<Vec<_>>().as_slice() {
+                [NestedMeta::Meta(Meta::Path(p))] if p.is_ident(DETERMINISTIC) => {
+                    EncryptMode::Deterministic
+                }
+                [NestedMeta::Meta(m)] => {
+                    let ident = m.path().get_ident().map(ToString::to_string);
+                    return Err(unknown_keyword(
+                        m.path(),
+                        "encrypt mode",
+                        &ident.unwrap_or_default(),
+                        &[DETERMINISTIC],
+                    ));
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        ml,
+                        "expected `encrypt` or `encrypt(deterministic)`",
+                    ))
+                }
+            },
+            Meta::NameValue(mnv) => {
+                return Err(syn::Error::new_spanned(
+                    mnv,
+                    "expected `encrypt` or `encrypt(deterministic)`",
+                ))
+            }
+        };
+        if result.is_some() {
+            return Err(syn::Error::new_spanned(meta, "duplicated `encrypt`"));
+        }
+        result = Some(mode);
+    }
+
+    match (result, indexed) {
+        (Some(_), Some(meta)) if meta.path().is_ident(TEXT) => Err(syn::Error::new_spanned(
+            meta,
+            "an encrypted field cannot be searched by the text index",
+        )),
+        (Some(EncryptMode::Random), Some(meta)) => Err(syn::Error::new_spanned(
+            meta,
+            "a randomly encrypted field cannot be indexed\n\
+             help: use `encrypt(deterministic)`, whose equal values are equal ciphertexts",
+        )),
+        (result, _) => Ok(result),
+    }
+}
+
+/// the encrypted fields
+pub fn encrypted_fields<'a>(
+    named_fields: &'a NamedFields,
+    known: &[&str],
+) -> syn::Result<Vec<(&'a Field, EncryptMode)>> {
+    let mut result = vec![];
+
+    for field in named_fields.iter() {
+        if let Some(mode) = encrypt_mode(field, known)? {
+            result.push((field, mode));
+        }
+    }
+
+    Ok(result)
+}
+
+/// `encrypted_fields` of `BaseCRUD`
+pub fn encrypt_tokens(
+    fields: &[(&Field, EncryptMode)],
+    rename_all: Option<RenameRule>,
+) -> proc_macro2::TokenStream {
+    if fields.is_empty() {
+        return quote! {};
+    }
+    let keys = fields.iter().map(|(f, _)| bson_name(f, rename_all));
+    let modes = fields.iter().map(|(_, mode)| mode);
+
+    quote! {
+        fn encrypted_fields() -> &'static [_crud::EncryptedField] {
+            &[#(_crud::EncryptedField { key: #keys, mode: #modes }),*]
+        }
+    }
+}
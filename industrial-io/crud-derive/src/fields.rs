@@ -0,0 +1,111 @@
+//! Fields
+//!
+//! Generates `<Name>Fields`, the typed paths of the struct's fields used by `crud::Filter`:
+//!
+//! - `T` becomes a method returning `FieldPath<Root, T>`, keyed by the stored name of the field
+//! - `Option<T>` becomes a method returning `FieldPath<Root, T>` as well, `null` is matched by
+//!   `FieldPath::is_null`
+//! - `#[crud(nested)] T` becomes a method returning `<T as Fields>::Paths<Root>`, whose paths are
+//!   prefixed, e.g. `option.position`
+//!
+//! `Root` is the CRUD struct the filter is built for, so paths of a nested type can only be
+//! used in filters on the struct it is nested in.
+
+use quote::{format_ident, quote};
+use syn::{parse_quote, DeriveInput, GenericParam, Path};
+
+use crate::patch::is_nested;
+use crate::rename::{bson_name, rename_all};
+use crate::{option_inner, NamedFields};
+
+/// `<Name>Fields` and the impl of `Fields`
+pub fn impl_fields(
+    input: &DeriveInput,
+    named_fields: &NamedFields,
+    known: &[&str],
+    krate: &Path,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let fields_name = format_ident!("{}Fields", name);
+    let rename_all = rename_all(&input.attrs);
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+    // the struct's generics, followed by `__Root`
+    let mut generics = input.generics.clone();
+    generics.params.push(parse_quote!(__Root));
+
+    let mut methods = vec![];
+    for field in named_fields.iter() {
+        let ident = field.ident.as_ref().unwrap();
+        let key = bson_name(field, rename_all);
+        let field_vis = &field.vis;
+        let ty = &field.ty;
+
+        if is_nested(field, known)? {
+            generics
+                .make_where_clause()
+                .predicates
+                .push(parse_quote! { #ty: #krate::Fields });
+            methods.push(quote! {
+                #field_vis fn #ident(&self) -> <#ty as _crud::Fields>::Paths<__Root> {
+                    <#ty as _crud::Fields>::paths(::std::format!("{}{}.", self.prefix, #key))
+                }
+            });
+        } else {
+            let ty = option_inner(ty).unwrap_or(ty);
+            methods.push(quote! {
+                #field_vis fn #ident(&self) -> _crud::FieldPath<__Root, #ty> {
+                    _crud::FieldPath::new(::std::format!("{}{}", self.prefix, #key))
+                }
+            });
+        }
+    }
+
+    let (fields_impl_generics, fields_ty_generics, where_clause) = generics.split_for_impl();
+    // `Fields::Paths` is generic over `Root` only
+    let params = input.generics.params.iter().map(|p| match p {
+        GenericParam::Type(t) => {
+            let ident = &t.ident;
+            quote!(#ident)
+        }
+        GenericParam::Lifetime(l) => {
+            let lifetime = &l.lifetime;
+            quote!(#lifetime)
+        }
+        GenericParam::Const(c) => {
+            let ident = &c.ident;
+            quote!(#ident)
+        }
+    });
+
+    let doc = format!("Typed paths of [`{name}`]'s fields, in filters on `__Root`.");
+
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #fields_name #fields_impl_generics #where_clause {
+            prefix: ::std::string::String,
+            marker: ::std::marker::PhantomData<fn() -> (__Root, #name #ty_generics)>,
+        }
+
+        #[doc(hidden)]
+        const _: () = {
+            use #krate as _crud;
+
+            impl #fields_impl_generics #fields_name #fields_ty_generics #where_clause {
+                #(#methods)*
+            }
+
+            impl #impl_generics _crud::Fields for #name #ty_generics #where_clause {
+                type Paths<__Root> = #fields_name<#(#params,)* __Root>;
+
+                fn paths<__Root>(prefix: ::std::string::String) -> Self::Paths<__Root> {
+                    #fields_name {
+                        prefix,
+                        marker: ::std::marker::PhantomData,
+                    }
+                }
+            }
+        };
+    })
+}
@@ -4,23 +4,68 @@
 
 use std::str::FromStr;
 
+use proc_macro2::Span;
 use quote::{quote, ToTokens};
+use syn::{spanned::Spanned, Lit, LitStr, Meta, MetaList, NestedMeta};
+
+use crate::attrs::unknown_keyword;
 
 pub const ASC: &str = "asc";
 pub const DESC: &str = "desc";
+pub const HASHED: &str = "hashed";
+pub const SPHERE_2D: &str = "2dsphere";
 pub const UNIQUE: &str = "unique";
 pub const TEXT: &str = "text";
+pub const SPARSE: &str = "sparse";
 
-/// Index direction
-#[derive(Debug, Clone)]
+pub const NAME: &str = "name";
+pub const ORDER: &str = "order";
+pub const DIR: &str = "dir";
+pub const EXPIRE_AFTER: &str = "expire_after";
+pub const PARTIAL: &str = "partial";
+pub const PATH: &str = "path";
+
+/// all keywords accepted by an index option string, e.g. `"unique,desc"`
+pub const INDEX_KEYWORDS: &[&str] = &[ASC, DESC, HASHED, SPHERE_2D, UNIQUE, TEXT, SPARSE];
+
+/// all keywords accepted by `single_index(...)`
+pub const SINGLE_INDEX_KEYWORDS: &[&str] = &[
+    PATH,
+    DIR,
+    UNIQUE,
+    TEXT,
+    SPARSE,
+    HASHED,
+    EXPIRE_AFTER,
+    PARTIAL,
+];
+
+/// all keywords accepted by `compound_index(...)`
+pub const COMPOUND_INDEX_KEYWORDS: &[&str] = &[
+    NAME, PATH, ORDER, DIR, UNIQUE, TEXT, SPARSE, HASHED, PARTIAL,
+];
+
+/// Index direction, or the kind of a special index key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub enum Dir {
+    #[default]
     Asc,
     Desc,
+    Hashed,
+    Sphere2d,
 }
 
-impl Default for Dir {
-    fn default() -> Self {
-        Dir::Asc
+impl FromStr for Dir {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            ASC => Ok(Dir::Asc),
+            DESC => Ok(Dir::Desc),
+            HASHED => Ok(Dir::Hashed),
+            SPHERE_2D => Ok(Dir::Sphere2d),
+            _ => Err(()),
+        }
     }
 }
 
@@ -30,8 +75,10 @@ impl ToTokens for Dir {
     // we need to convert it to a public API (defined in `crud` crate).
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         tokens.extend(match self {
-            Dir::Asc => quote! { crud::Dir::Asc },
-            Dir::Desc => quote! { crud::Dir::Desc },
+            Dir::Asc => quote! { _crud::Dir::Asc },
+            Dir::Desc => quote! { _crud::Dir::Desc },
+            Dir::Hashed => quote! { _crud::Dir::Hashed },
+            Dir::Sphere2d => quote! { _crud::Dir::Sphere2d },
         })
     }
 }
@@ -52,28 +99,237 @@ impl ToTokens for KeyPair {
     }
 }
 
+/// `partialFilterExpression` written as JSON, checked at compile time
+#[derive(Debug, Clone)]
+pub struct PartialFilter(pub serde_json::Map<String, serde_json::Value>);
+
+impl PartialFilter {
+    pub fn from_lit(lit: &LitStr) -> syn::Result<Self> {
+        match serde_json::from_str(&lit.value()) {
+            Ok(serde_json::Value::Object(map)) => Ok(PartialFilter(map)),
+            Ok(_) => Err(syn::Error::new_spanned(
+                lit,
+                "`partial` must be a JSON object, e.g. `partial = \"{\\\"code\\\": {\\\"$exists\\\": true}}\"`",
+            )),
+            Err(e) => Err(syn::Error::new_spanned(
+                lit,
+                format!("invalid JSON in `partial`: {e}"),
+            )),
+        }
+    }
+}
+
+/// JSON value -> `bson::Bson` expression
+fn json_to_bson_tokens(value: &serde_json::Value) -> proc_macro2::TokenStream {
+    match value {
+        serde_json::Value::Null => quote! { _crud::__private::bson::Bson::Null },
+        serde_json::Value::Bool(b) => quote! { _crud::__private::bson::Bson::Boolean(#b) },
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => match i32::try_from(i) {
+                Ok(i) => quote! { _crud::__private::bson::Bson::Int32(#i) },
+                Err(_) => quote! { _crud::__private::bson::Bson::Int64(#i) },
+            },
+            (None, Some(f)) => quote! { _crud::__private::bson::Bson::Double(#f) },
+            _ => unreachable!("a JSON number is either an integer or a float"),
+        },
+        serde_json::Value::String(s) => {
+            quote! { _crud::__private::bson::Bson::String(#s.to_owned()) }
+        }
+        serde_json::Value::Array(a) => {
+            let items = a.iter().map(json_to_bson_tokens);
+            quote! { _crud::__private::bson::Bson::Array(vec![#(#items),*]) }
+        }
+        serde_json::Value::Object(o) => {
+            let doc = json_object_to_tokens(o);
+            quote! { _crud::__private::bson::Bson::Document(#doc) }
+        }
+    }
+}
+
+/// JSON object -> `bson::Document` expression
+pub fn json_object_to_tokens(
+    map: &serde_json::Map<String, serde_json::Value>,
+) -> proc_macro2::TokenStream {
+    let keys = map.keys();
+    let values = map.values().map(json_to_bson_tokens);
+    quote! {
+        {
+            let mut doc = _crud::__private::bson::Document::new();
+            #( doc.insert(#keys, #values); )*
+            doc
+        }
+    }
+}
+
+impl ToTokens for PartialFilter {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(json_object_to_tokens(&self.0))
+    }
+}
+
+fn partial_tokens(partial: &Option<PartialFilter>) -> proc_macro2::TokenStream {
+    match partial {
+        Some(p) => quote! { Some(#p) },
+        None => quote! { None },
+    }
+}
+
+/// a dotted path of a (nested) field, e.g. `"option.position"`
+pub fn check_path(lit: &LitStr) -> syn::Result<String> {
+    let path = lit.value();
+    if path.split('.').any(|p| p.is_empty() || p.starts_with('$')) {
+        return Err(syn::Error::new_spanned(
+            lit,
+            "invalid path, expected dotted field names, e.g. `path = \"option.position\"`",
+        ));
+    }
+    Ok(path)
+}
+
+/// `"7d"` -> seconds, units are `s`, `m`, `h`, `d` and `w`; a bare number means seconds
+fn parse_duration(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let number = number.parse::<u64>().ok()?;
+    let unit = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.checked_mul(unit)
+}
+
+/// Options shared by index attributes, parsed either from a string such as `"unique,desc"`,
+/// or from a list such as `single_index(unique, dir = "desc", expire_after = "7d")`.
 #[derive(Debug, Clone, Default)]
 pub struct CommonOption {
+    pub name: Option<String>,
+    /// explicit key, overrides the field's name
+    pub path: Option<String>,
+    pub order: Option<(u32, Span)>,
     pub dir: Dir,
     pub unique: bool,
     pub text: bool,
+    pub sparse: bool,
+    pub expire_after: Option<u64>,
+    pub partial: Option<PartialFilter>,
 }
 
 impl FromStr for CommonOption {
-    type Err = ();
+    /// the first keyword that does not match the `IndexOptions` fields' format
+    type Err = String;
 
-    // ignore any string who does not match the `IndexOptions` fields' format
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut options = CommonOption::default();
-        s.split(',').for_each(|i| match i {
-            ASC => options.dir = Dir::Asc,
-            DESC => options.dir = Dir::Desc,
-            UNIQUE => options.unique = true,
-            TEXT => options.text = true,
-            _ => {}
-        });
+        for i in s.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            match i {
+                UNIQUE => options.unique = true,
+                TEXT => options.text = true,
+                SPARSE => options.sparse = true,
+                dir => options.dir = dir.parse().map_err(|_| dir.to_owned())?,
+            }
+        }
+        Ok(options)
+    }
+}
+
+impl CommonOption {
+    /// parse an attribute's string literal, pointing at the literal if a keyword is unknown
+    pub fn from_lit(lit: &LitStr) -> syn::Result<Self> {
+        let options: Self = lit.value().parse().map_err(|unknown: String| {
+            unknown_keyword(lit, "index option", &unknown, INDEX_KEYWORDS)
+        })?;
+        options.check(lit.span())?;
         Ok(options)
     }
+
+    /// parse `single_index(...)` or `compound_index(...)`, accepting only keywords in `known`
+    pub fn from_meta_list(meta_list: &MetaList, kind: &str, known: &[&str]) -> syn::Result<Self> {
+        let mut options = CommonOption::default();
+
+        for nested_meta in meta_list.nested.iter() {
+            let meta = match nested_meta {
+                NestedMeta::Meta(meta) => meta,
+                NestedMeta::Lit(lit) => return Err(expected(lit, "unique")),
+            };
+            let keyword = meta.path().get_ident().map(ToString::to_string);
+            match keyword.as_deref() {
+                Some(k) if known.contains(&k) => {}
+                _ => return Err(unknown_option(meta, kind, known)),
+            }
+
+            match meta {
+                Meta::NameValue(mnv) if mnv.path.is_ident(NAME) => match &mnv.lit {
+                    Lit::Str(s) if !s.value().is_empty() => options.name = Some(s.value()),
+                    other => return Err(expected(other, "name = \"by_source_target\"")),
+                },
+                Meta::NameValue(mnv) if mnv.path.is_ident(PATH) => match &mnv.lit {
+                    Lit::Str(s) => options.path = Some(check_path(s)?),
+                    other => return Err(expected(other, "path = \"option.position\"")),
+                },
+                Meta::NameValue(mnv) if mnv.path.is_ident(ORDER) => match &mnv.lit {
+                    Lit::Int(i) => options.order = Some((i.base10_parse::<u32>()?, i.span())),
+                    other => return Err(expected(other, "order = 1")),
+                },
+                Meta::NameValue(mnv) if mnv.path.is_ident(DIR) => match &mnv.lit {
+                    Lit::Str(s) if s.value().parse::<Dir>().is_ok() => {
+                        options.dir = s.value().parse().unwrap()
+                    }
+                    other => {
+                        return Err(expected(
+                            other,
+                            "dir = \"asc\"`, `\"desc\"`, `\"hashed\"` or `\"2dsphere\"",
+                        ))
+                    }
+                },
+                Meta::NameValue(mnv) if mnv.path.is_ident(EXPIRE_AFTER) => match &mnv.lit {
+                    Lit::Str(s) => match parse_duration(&s.value()) {
+                        Some(secs) => options.expire_after = Some(secs),
+                        None => return Err(expected(s, "expire_after = \"7d\"")),
+                    },
+                    Lit::Int(i) => options.expire_after = Some(i.base10_parse::<u64>()?),
+                    other => return Err(expected(other, "expire_after = \"7d\"")),
+                },
+                Meta::NameValue(mnv) if mnv.path.is_ident(PARTIAL) => match &mnv.lit {
+                    Lit::Str(s) => options.partial = Some(PartialFilter::from_lit(s)?),
+                    other => return Err(expected(other, "partial = \"{...}\"")),
+                },
+                Meta::Path(p) if p.is_ident(UNIQUE) => options.unique = true,
+                Meta::Path(p) if p.is_ident(TEXT) => options.text = true,
+                Meta::Path(p) if p.is_ident(SPARSE) => options.sparse = true,
+                Meta::Path(p) if p.is_ident(HASHED) => options.dir = Dir::Hashed,
+                other => return Err(unknown_option(other, kind, known)),
+            }
+        }
+
+        options.check(meta_list.span())?;
+        Ok(options)
+    }
+
+    /// reject combinations MongoDB would refuse when creating the index
+    fn check(&self, span: Span) -> syn::Result<()> {
+        if self.unique && self.dir == Dir::Hashed {
+            return Err(syn::Error::new(
+                span,
+                "hashed indexes cannot be `unique`\n\
+                 help: use an `asc` or `desc` index for uniqueness",
+            ));
+        }
+        if self.sparse && self.partial.is_some() {
+            return Err(syn::Error::new(
+                span,
+                "`sparse` and `partial` cannot be used together\n\
+                 help: `partial = \"{\\\"field\\\": {\\\"$exists\\\": true}}\"` covers `sparse`",
+            ));
+        }
+        Ok(())
+    }
 }
 
 /// Index options (MongoDB)
@@ -82,100 +338,429 @@ pub struct SingleIndex {
     pub key: KeyPair,
     pub unique: bool,
     pub text: bool,
+    pub sparse: bool,
+    /// TTL in seconds
+    pub expire_after: Option<u64>,
+    pub partial: Option<PartialFilter>,
 }
 
 impl SingleIndex {
-    pub fn new_from_str(name: String, s: &str) -> Self {
-        // parse from string
-        let common_option = s.parse::<CommonOption>().unwrap();
+    fn new(name: String, common_option: CommonOption) -> Self {
         SingleIndex {
-            key: KeyPair(name, common_option.dir),
+            key: KeyPair(common_option.path.unwrap_or(name), common_option.dir),
             unique: common_option.unique,
             text: common_option.text,
+            sparse: common_option.sparse,
+            expire_after: common_option.expire_after,
+            partial: common_option.partial,
         }
     }
-}
 
-/// new type for `Vec<crud_derive::IndexOptions>`
-/// we need it because `syn::ToTokens` cannot be implemented for `Vec<_>`
-#[derive(Debug, Clone)]
-pub struct SingleIndexOptions(pub Vec<SingleIndex>);
+    /// `#[crud(single_index = "...")]`
+    pub fn new_from_lit(name: String, lit: &LitStr) -> syn::Result<Self> {
+        // parse from string
+        let common_option = CommonOption::from_lit(lit)?;
+        Ok(SingleIndex::new(name, common_option))
+    }
 
-/// `crud_derive::IndexOptions` -> `crud::IndexOptions`
+    /// `#[crud(single_index(...))]`
+    pub fn new_from_meta_list(name: String, meta_list: &MetaList) -> syn::Result<Self> {
+        let common_option =
+            CommonOption::from_meta_list(meta_list, "single index option", SINGLE_INDEX_KEYWORDS)?;
+        Ok(SingleIndex::new(name, common_option))
+    }
+}
+
+/// `crud_derive::SingleIndex` -> `crud::SingleIndex`
 impl ToTokens for SingleIndex {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let name = &self.key.0;
-        let dir = &self.key.1;
+        let key = &self.key;
         let unique = &self.unique;
         let text = &self.text;
+        let sparse = &self.sparse;
+        let expire_after = match self.expire_after {
+            Some(secs) => quote! { Some(::std::time::Duration::from_secs(#secs)) },
+            None => quote! { None },
+        };
+        let partial = partial_tokens(&self.partial);
         tokens.extend(quote! {
-            crud::SingleIndex::new((#name.to_string(), #dir), #unique, #text)
+            _crud::SingleIndex {
+                key: #key,
+                unique: #unique,
+                text: #text,
+                sparse: #sparse,
+                expire_after: #expire_after,
+                partial: #partial,
+            }
         })
     }
 }
 
-/// `crud_derive::IndexOptionsCollection` -> `Vec<crud::IndexOptions>`
-impl ToTokens for SingleIndexOptions {
-    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let d = &self.0;
-        tokens.extend(quote! {
-            crud::IndexOptions::Single(crud::SingleIndexOptions(vec![#(#d),*]))
-        })
-    }
+/// A key of a compound index, with its optional position in the index
+#[derive(Debug, Clone)]
+pub struct CompoundKey {
+    pub key: KeyPair,
+    /// `order = N` and where it is declared
+    pub order: Option<(u32, Span)>,
+    /// where the key is declared, used for error reporting
+    pub span: Span,
 }
 
 /// Compound index options
+///
+/// Unnamed compound index collects keys from `#[crud(compound_index)]`, and named ones from
+/// `#[crud(compound_index(name = "..."))]`.
 #[derive(Debug, Clone, Default)]
 pub struct CompoundIndexOptions {
-    pub keys: Vec<KeyPair>,
+    pub name: Option<String>,
+    pub keys: Vec<CompoundKey>,
     pub unique: bool,
     pub text: bool,
+    pub sparse: bool,
+    pub partial: Option<PartialFilter>,
 }
 
 impl CompoundIndexOptions {
-    pub fn update_from_str(&mut self, name: String, s: &str) {
+    pub fn new(name: Option<String>) -> Self {
+        CompoundIndexOptions {
+            name,
+            ..Default::default()
+        }
+    }
+
+    pub fn update_from_lit(&mut self, name: String, lit: &LitStr) -> syn::Result<()> {
         // parse from string
-        let common_option = s.parse::<CommonOption>().unwrap();
-        let kp = KeyPair(name, common_option.dir);
+        let common_option = CommonOption::from_lit(lit)?;
+        self.update(name, common_option, lit.span())
+    }
+
+    pub fn add_keys(&mut self, name: String, span: Span) {
+        self.keys.push(CompoundKey {
+            key: KeyPair(name, Dir::default()),
+            order: None,
+            span,
+        });
+    }
+
+    pub fn add_grouped_key(&mut self, gk: GroupedKey) -> syn::Result<()> {
+        self.update(gk.field, gk.options, gk.span)
+    }
+
+    fn update(&mut self, name: String, common_option: CommonOption, span: Span) -> syn::Result<()> {
+        if let Some(partial) = common_option.partial {
+            if self.partial.as_ref().is_some_and(|p| p.0 != partial.0) {
+                return Err(syn::Error::new(
+                    span,
+                    "conflicting `partial` filters in the same compound index",
+                ));
+            }
+            self.partial = Some(partial);
+        }
+
+        self.keys.push(CompoundKey {
+            key: KeyPair(common_option.path.unwrap_or(name), common_option.dir),
+            order: common_option.order,
+            span,
+        });
+        self.unique |= common_option.unique;
+        self.text |= common_option.text;
+        self.sparse |= common_option.sparse;
+        Ok(())
+    }
+
+    /// sort keys by their `order`, either every key of an index has an `order` or none has
+    pub fn sort_keys(&mut self) -> syn::Result<()> {
+        let ordered = self.keys.iter().filter(|k| k.order.is_some()).count();
+        if ordered == 0 {
+            return Ok(());
+        }
+        if let Some(k) = self.keys.iter().find(|k| k.order.is_none()) {
+            return Err(syn::Error::new(
+                k.span,
+                format!(
+                    "field `{}` has no `order` in compound index `{}`\n\
+                     help: either give every key of the index an `order`, or none of them",
+                    k.key.0,
+                    self.name.as_deref().unwrap_or_default()
+                ),
+            ));
+        }
+
+        let mut orders = vec![];
+        for k in self.keys.iter() {
+            let (value, span) = k.order.unwrap();
+            if orders.contains(&value) {
+                return Err(syn::Error::new(
+                    span,
+                    format!("duplicated `order = {value}` in compound index"),
+                ));
+            }
+            orders.push(value);
+        }
 
-        self.keys.push(kp);
-        self.unique = common_option.unique;
-        self.text = common_option.text;
+        let mut keys = orders
+            .into_iter()
+            .zip(self.keys.drain(..))
+            .collect::<Vec<_>>();
+        keys.sort_by_key(|(o, _)| *o);
+        self.keys = keys.into_iter().map(|(_, k)| k).collect();
+
+        Ok(())
     }
+}
+
+/// A key declared by `#[crud(compound_index(name = "...", order = 1, dir = "desc", unique))]`
+#[derive(Debug, Clone)]
+pub struct GroupedKey {
+    pub name: Option<String>,
+    pub field: String,
+    pub options: CommonOption,
+    pub span: Span,
+}
 
-    pub fn add_keys(&mut self, name: String) {
-        self.keys.push(KeyPair(name, Dir::default()));
+impl GroupedKey {
+    pub fn from_meta_list(field: String, meta_list: &MetaList) -> syn::Result<Self> {
+        let ttl = meta_list.nested.iter().find(|nm| match nm {
+            NestedMeta::Meta(meta) => meta.path().is_ident(EXPIRE_AFTER),
+            _ => false,
+        });
+        if let Some(ttl) = ttl {
+            return Err(syn::Error::new_spanned(
+                ttl,
+                "`expire_after` is not supported by compound indexes\n\
+                 help: TTL indexes must be `single_index(expire_after = \"...\")`",
+            ));
+        }
+
+        let options = CommonOption::from_meta_list(
+            meta_list,
+            "compound index option",
+            COMPOUND_INDEX_KEYWORDS,
+        )?;
+
+        if options.name.is_none() && options.order.is_some() {
+            return Err(syn::Error::new_spanned(
+                meta_list,
+                "`order` requires a named compound index\n\
+                 help: add `name = \"...\"` to every key of this compound index",
+            ));
+        }
+
+        Ok(GroupedKey {
+            name: options.name.clone(),
+            field,
+            options,
+            span: meta_list.span(),
+        })
     }
 }
 
+fn expected<T: ToTokens>(tokens: T, example: &str) -> syn::Error {
+    syn::Error::new_spanned(tokens, format!("expected `{example}`"))
+}
+
+/// a known keyword in a wrong shape is malformed, otherwise it is unknown
+fn unknown_option(meta: &Meta, kind: &str, known: &[&str]) -> syn::Error {
+    match meta.path().get_ident().map(ToString::to_string) {
+        Some(f) if known.contains(&f.as_str()) => {
+            syn::Error::new_spanned(meta, format!("malformed `{f}` in {kind}s"))
+        }
+        f => unknown_keyword(meta.path(), kind, &f.unwrap_or_default(), known),
+    }
+}
+
+/// `crud_derive::CompoundIndexOptions` -> `crud::CompoundIndexOptions`
 impl ToTokens for CompoundIndexOptions {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let keys = &self.keys;
+        let name = match &self.name {
+            Some(n) => quote! { Some(#n.to_owned()) },
+            None => quote! { None },
+        };
+        let keys = self.keys.iter().map(|k| &k.key);
         let unique = &self.unique;
         let text = &self.text;
+        let sparse = &self.sparse;
+        let partial = partial_tokens(&self.partial);
         tokens.extend(quote! {
-            crud::IndexOptions::Compound(crud::CompoundIndexOptions {
+            _crud::CompoundIndexOptions {
+                name: #name,
                 keys: vec![#(#keys),*],
                 unique: #unique,
                 text: #text,
-            })
+                sparse: #sparse,
+                partial: #partial,
+            }
+        })
+    }
+}
+
+pub const WEIGHT: &str = "weight";
+pub const DEFAULT_LANGUAGE: &str = "default_language";
+pub const LANGUAGE_OVERRIDE: &str = "language_override";
+
+/// all keywords accepted by `text(...)`
+pub const TEXT_KEYWORDS: &[&str] = &[WEIGHT];
+
+/// all keywords accepted by `text_index(...)`
+pub const TEXT_INDEX_KEYWORDS: &[&str] = &[NAME, DEFAULT_LANGUAGE, LANGUAGE_OVERRIDE];
+
+/// Text index options
+///
+/// A collection can only have one text index, so fields marked by `#[crud(text)]` are all
+/// gathered here, and the struct level `#[crud(text_index(...))]` sets the index options.
+#[derive(Debug, Clone, Default)]
+pub struct TextIndex {
+    pub name: Option<String>,
+    pub fields: Vec<(String, Option<i32>)>,
+    pub default_language: Option<String>,
+    pub language_override: Option<String>,
+}
+
+impl TextIndex {
+    /// `#[crud(text)]` or `#[crud(text(weight = 10))]`
+    pub fn add_field_from_meta(&mut self, field: String, meta: &Meta) -> syn::Result<()> {
+        let weight = match meta {
+            Meta::Path(_) => None,
+            Meta::List(ml) => {
+                let mut weight = None;
+                for nested_meta in ml.nested.iter() {
+                    match nested_meta {
+                        NestedMeta::Meta(Meta::NameValue(mnv)) if mnv.path.is_ident(WEIGHT) => {
+                            match &mnv.lit {
+                                Lit::Int(i) => match i.base10_parse::<i32>() {
+                                    Ok(w) if (1..=99999).contains(&w) => weight = Some(w),
+                                    _ => {
+                                        return Err(syn::Error::new_spanned(
+                                            i,
+                                            "text index weight must be between 1 and 99999",
+                                        ))
+                                    }
+                                },
+                                other => return Err(expected(other, "weight = 10")),
+                            }
+                        }
+                        NestedMeta::Meta(meta) => {
+                            return Err(unknown_option(meta, "text option", TEXT_KEYWORDS))
+                        }
+                        NestedMeta::Lit(lit) => return Err(expected(lit, "weight = 10")),
+                    }
+                }
+                weight
+            }
+            Meta::NameValue(mnv) => return Err(expected(mnv, "text(weight = 10)")),
+        };
+
+        self.fields.push((field, weight));
+        Ok(())
+    }
+
+    /// `#[crud(text_index(name = "...", default_language = "...", language_override = "..."))]`
+    pub fn update_from_meta(&mut self, meta: &Meta) -> syn::Result<()> {
+        let ml = match meta {
+            Meta::List(ml) => ml,
+            other => {
+                return Err(expected(
+                    other,
+                    "text_index(default_language = \"english\")",
+                ))
+            }
+        };
+
+        for nested_meta in ml.nested.iter() {
+            let (key, value) = match nested_meta {
+                NestedMeta::Meta(Meta::NameValue(mnv)) => match &mnv.lit {
+                    Lit::Str(s) => (&mnv.path, s.value()),
+                    other => return Err(expected(other, "default_language = \"english\"")),
+                },
+                NestedMeta::Meta(meta) => {
+                    return Err(unknown_option(
+                        meta,
+                        "text index option",
+                        TEXT_INDEX_KEYWORDS,
+                    ))
+                }
+                NestedMeta::Lit(lit) => {
+                    return Err(expected(lit, "default_language = \"english\""))
+                }
+            };
+            if key.is_ident(NAME) {
+                self.name = Some(value);
+            } else if key.is_ident(DEFAULT_LANGUAGE) {
+                self.default_language = Some(value);
+            } else if key.is_ident(LANGUAGE_OVERRIDE) {
+                self.language_override = Some(value);
+            } else {
+                let found = key.get_ident().map(ToString::to_string).unwrap_or_default();
+                return Err(unknown_keyword(
+                    key,
+                    "text index option",
+                    &found,
+                    TEXT_INDEX_KEYWORDS,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn option_tokens(value: &Option<String>) -> proc_macro2::TokenStream {
+    match value {
+        Some(v) => quote! { Some(#v.to_owned()) },
+        None => quote! { None },
+    }
+}
+
+/// `crud_derive::TextIndex` -> `crud::TextIndex`
+impl ToTokens for TextIndex {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let name = option_tokens(&self.name);
+        let fields = self.fields.iter().map(|(f, w)| match w {
+            Some(w) => quote! { (#f.to_owned(), Some(#w)) },
+            None => quote! { (#f.to_owned(), None) },
+        });
+        let default_language = option_tokens(&self.default_language);
+        let language_override = option_tokens(&self.language_override);
+        tokens.extend(quote! {
+            _crud::TextIndex {
+                name: #name,
+                fields: vec![#(#fields),*],
+                default_language: #default_language,
+                language_override: #language_override,
+            }
         })
     }
 }
 
+/// A single index definition
 #[derive(Debug, Clone)]
-pub enum IndexOptions {
-    Single(SingleIndexOptions),
+pub enum Index {
+    Single(SingleIndex),
     Compound(CompoundIndexOptions),
-    None,
+    Text(TextIndex),
 }
 
+impl ToTokens for Index {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            Index::Single(v) => quote! { _crud::Index::Single(#v) },
+            Index::Compound(v) => quote! { _crud::Index::Compound(#v) },
+            Index::Text(v) => quote! { _crud::Index::Text(#v) },
+        })
+    }
+}
+
+/// new type for `Vec<crud_derive::Index>`
+/// we need it because `syn::ToTokens` cannot be implemented for `Vec<_>`
+#[derive(Debug, Clone, Default)]
+pub struct IndexOptions(pub Vec<Index>);
+
+/// `crud_derive::IndexOptions` -> `crud::IndexOptions`
 impl ToTokens for IndexOptions {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        match self {
-            IndexOptions::Single(v) => v.to_tokens(tokens),
-            IndexOptions::Compound(v) => v.to_tokens(tokens),
-            IndexOptions::None => tokens.extend(quote! { crud::IndexOptions::None }),
-        }
+        let d = &self.0;
+        tokens.extend(quote! {
+            _crud::IndexOptions(vec![#(#d),*])
+        })
     }
 }
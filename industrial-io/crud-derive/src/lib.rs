@@ -5,27 +5,129 @@
 //! Featured functions catalogue:
 //! - `get_field_id`
 //! - `get_attr_id`
+//! - `id_type`
+//! - `id_generator`
 //! - `single_index_format`
 //! - `compound_index_format`
+//! - `text_index_format`
+//! - `route_format`
+//! - `discriminator_format`
+//! - `crate_path`
 //!
 //! These functions are used to find out tagged fields or attributes in the compiling time,
 //! and the results of these functions are used in proc-macro's token streams (see function
 //! `impl_crud`).
-
+//!
+//! The generated impls live in a `const _: () = { use ::crud as _crud; ... };` block, and only
+//! refer to `_crud::...`: `bson`, `anyhow`, `async_trait` and `serde` are reached through
+//! `crud::__private`, so a crate deriving `CRUD` needs no other dependency nor import. A renamed
+//! or re-exported `crud` is declared by `#[crud(crate = "...")]`.
+
+mod attrs;
+mod encrypt;
+mod fields;
 mod indexes;
+mod patch;
+mod projection;
+mod rename;
+mod schema;
+mod timestamps;
+mod validate;
+mod version;
 
 use quote::quote;
 use syn::{
-    parse_macro_input, punctuated::Punctuated, token::Comma, Attribute, Data, DeriveInput, Field,
-    Fields, Ident, Lit, Meta, NestedMeta,
+    parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned, token::Comma, Data,
+    DeriveInput, Field, Fields, GenericArgument, Lit, Meta, NestedMeta, PathArguments, Type,
 };
 
+use attrs::*;
+use encrypt::*;
+use fields::*;
 use indexes::*;
+use patch::*;
+use projection::*;
+use rename::*;
+use schema::*;
+use timestamps::*;
+use validate::*;
+use version::*;
 
 const TAG: &str = "crud";
 const ID: &str = "id";
 const SINGLE_INDEX: &str = "single_index";
 const COMPOUND_INDEX: &str = "compound_index";
+const TEXT_INDEX: &str = "text_index";
+const GENERATE: &str = "generate";
+const COLLECTION: &str = "collection";
+const DATABASE: &str = "database";
+const DISCRIMINATOR: &str = "discriminator";
+const VALUE: &str = "value";
+const CRATE: &str = "crate";
+const HOOKS: &str = "hooks";
+
+/// client-side id generators accepted by `#[crud(id(generate = "..."))]`
+const ID_GENERATORS: &[&str] = &["uuid_v4", "uuid_v7"];
+
+/// client-side id generator
+enum IdGenerator {
+    UuidV4,
+    UuidV7,
+}
+
+impl IdGenerator {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "uuid_v4" => Some(IdGenerator::UuidV4),
+            "uuid_v7" => Some(IdGenerator::UuidV7),
+            _ => None,
+        }
+    }
+}
+
+impl quote::ToTokens for IdGenerator {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        tokens.extend(match self {
+            IdGenerator::UuidV4 => quote! { _crud::id::uuid_v4() },
+            IdGenerator::UuidV7 => quote! { _crud::id::uuid_v7() },
+        });
+    }
+}
+
+/// all sub-attributes accepted by `#[crud(...)]` on a field
+const FIELD_ATTRS: &[&str] = &[
+    ID,
+    PATH,
+    SINGLE_INDEX,
+    COMPOUND_INDEX,
+    TEXT,
+    NESTED,
+    CREATED_AT,
+    UPDATED_AT,
+    SOFT_DELETE,
+    VERSION,
+    VALIDATE,
+    SCHEMA,
+    ENCRYPT,
+];
+
+/// all sub-attributes accepted by `#[crud(...)]` on a field of `#[derive(Patchable)]`
+const PATCHABLE_FIELD_ATTRS: &[&str] = &[NESTED, SCHEMA];
+
+/// all sub-attributes accepted by `#[crud(...)]` on a struct
+const STRUCT_ATTRS: &[&str] = &[
+    TEXT_INDEX,
+    COLLECTION,
+    DATABASE,
+    DISCRIMINATOR,
+    VALUE,
+    CRATE,
+    HOOKS,
+    PROJECTION,
+];
+
+/// all sub-attributes accepted by `#[crud(...)]` on a struct of `#[derive(Patchable)]`
+const PATCHABLE_STRUCT_ATTRS: &[&str] = &[CRATE];
 
 /// macro for CRUD derive
 #[proc_macro_derive(CRUD, attributes(crud))]
@@ -33,7 +135,8 @@ pub fn derive_crud(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // Parse the input tokens into a syntax tree
     let input = parse_macro_input!(input as DeriveInput);
 
-    let stream = impl_crud(&input);
+    // any misuse of the attributes is reported as a compile error
+    let stream = impl_crud(&input).unwrap_or_else(syn::Error::into_compile_error);
 
     // Debug use:
     // println!("{}", &stream);
@@ -41,22 +144,53 @@ pub fn derive_crud(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     proc_macro::TokenStream::from(stream)
 }
 
+/// macro for Patchable derive, used by types nested in a CRUD struct by `#[crud(nested)]`,
+/// generates `<Name>Patch`, `<Name>Fields` and the impl of `BsonSchema`
+#[proc_macro_derive(Patchable, attributes(crud))]
+pub fn derive_patchable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let stream = crate_path(&input, PATCHABLE_STRUCT_ATTRS)
+        .and_then(|krate| {
+            let named_fields = named_fields(&input)?;
+            let patch = impl_patch(&input, &named_fields, &[], PATCHABLE_FIELD_ATTRS, &krate)?;
+            let fields = impl_fields(&input, &named_fields, PATCHABLE_FIELD_ATTRS, &krate)?;
+            let schema = impl_bson_schema(&input, &named_fields, PATCHABLE_FIELD_ATTRS, &krate)?;
+            Ok(quote! { #patch #fields #schema })
+        })
+        .unwrap_or_else(syn::Error::into_compile_error);
+
+    proc_macro::TokenStream::from(stream)
+}
+
 type NamedFields = Punctuated<Field, Comma>;
 
 /// turn ast into `Punctuated<Field, Comma>`, and filter out any type that is not a Rust struct
-fn named_fields(ast: &DeriveInput) -> NamedFields {
+fn named_fields(ast: &DeriveInput) -> syn::Result<NamedFields> {
     match &ast.data {
-        Data::Struct(s) => {
-            if let Fields::Named(ref named_fields) = s.fields {
-                named_fields.named.clone()
-            } else {
-                unimplemented!("derive(Builder) only supports named fields")
-            }
-        }
-        other => unimplemented!(
-            "CRUD only supports Struct and is not implemented for {:?}",
-            other
-        ),
+        Data::Struct(s) => match s.fields {
+            Fields::Named(ref named_fields) => Ok(named_fields.named.clone()),
+            Fields::Unnamed(ref fields) => Err(syn::Error::new_spanned(
+                fields,
+                "CRUD cannot be derived for tuple structs\n\
+                 help: use named fields, e.g. `struct Foo { id: Option<ObjectId>, .. }`",
+            )),
+            Fields::Unit => Err(syn::Error::new_spanned(
+                &ast.ident,
+                "CRUD cannot be derived for unit structs\n\
+                 help: add named fields, including an `id`",
+            )),
+        },
+        Data::Enum(e) => Err(syn::Error::new(
+            e.enum_token.span(),
+            "CRUD cannot be derived for enums\n\
+             help: CRUD only supports structs with named fields",
+        )),
+        Data::Union(u) => Err(syn::Error::new(
+            u.union_token.span(),
+            "CRUD cannot be derived for unions\n\
+             help: CRUD only supports structs with named fields",
+        )),
     }
 }
 
@@ -68,13 +202,10 @@ fn named_fields(ast: &DeriveInput) -> NamedFields {
 ///     ...
 /// }
 /// ```
-fn get_field_id(named_fields: &NamedFields) -> Option<Ident> {
-    for field in named_fields.iter() {
-        if field.ident.as_ref().unwrap() == ID {
-            return Some(field.ident.as_ref().unwrap().clone());
-        }
-    }
-    None
+fn get_field_id(named_fields: &NamedFields) -> Option<&Field> {
+    named_fields
+        .iter()
+        .find(|field| matches!(&field.ident, Some(ident) if ident == ID))
 }
 
 /// find out a field whose attribute is `id`
@@ -87,56 +218,147 @@ fn get_field_id(named_fields: &NamedFields) -> Option<Ident> {
 /// }
 /// ```
 ///
-/// same as:
+/// only one field can be tagged as `id`.
+fn get_attr_id(named_fields: &NamedFields) -> syn::Result<Option<&Field>> {
+    let mut result: Option<&Field> = None;
+
+    for field in named_fields.iter() {
+        for nested_meta in crud_metas(&field.attrs, FIELD_ATTRS)? {
+            if !nested_meta_is(&nested_meta, ID) {
+                continue;
+            }
+            if let NestedMeta::Meta(Meta::NameValue(mnv)) = nested_meta {
+                return Err(syn::Error::new_spanned(
+                    mnv,
+                    "expected `#[crud(id)]` or `#[crud(id(generate = \"...\"))]`",
+                ));
+            }
+            if result.is_some() {
+                return Err(syn::Error::new_spanned(
+                    nested_meta,
+                    "duplicated `#[crud(id)]`, only one field can be the id",
+                ));
+            }
+            result = Some(field);
+        }
+    }
+
+    Ok(result)
+}
+
+/// `T` of `Option<T>`
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let seg = match ty {
+        Type::Path(tp) => tp.path.segments.last()?,
+        _ => return None,
+    };
+    match &seg.arguments {
+        PathArguments::AngleBracketed(args) if seg.ident == "Option" && args.args.len() == 1 => {
+            match args.args.first() {
+                Some(GenericArgument::Type(ty)) => Some(ty),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// the id type `ID` of a field `Option<ID>`
+fn id_type(field: &Field) -> syn::Result<&Type> {
+    option_inner(&field.ty).ok_or_else(|| {
+        syn::Error::new_spanned(
+            &field.ty,
+            "the id field must be an `Option`\n\
+             help: use e.g. `Option<ObjectId>`, `Option<String>` or `Option<bson::Uuid>`, \
+             `None` before the document is created",
+        )
+    })
+}
+
+/// client-side id generation of the id field
 ///
 /// ```rust,ignore
-/// fn get_attr_id(named_fields: &NamedFields) -> Option<Ident> {
-///     for field in named_fields.iter() {
-///         for attr in field.attrs.iter() {
-///             if let Ok(Meta::List(meta_list)) = attr.parse_meta() {
-///                 if meta_list.path.is_ident(TAG) {
-///                     for nested_meta in meta_list.nested.iter() {
-///                         if let NestedMeta::Meta(Meta::Path(path)) = nested_meta {
-///                             if path.is_ident(ID) {
-///                                 return Some(field.ident.as_ref().unwrap().clone());
-///                             }
-///                         }
-///                     }
-///                 }
-///             }
-///         }
-///     }
-///     None
+/// struct TestCrud {
+///     #[crud(id(generate = "uuid_v7"))]
+///     id: Option<bson::Uuid>,
+///     ...
 /// }
 /// ```
-fn get_attr_id(named_fields: &NamedFields) -> Option<Ident> {
-    // get the `id` sub-attribute field as an `Ident`
-    let nested_meta_find_map = |field: &Field, nested_meta: &NestedMeta| match nested_meta {
-        NestedMeta::Meta(Meta::Path(path)) if path.is_ident(ID) => {
-            Some(field.ident.as_ref().unwrap().clone())
+fn id_generator(field: &Field) -> syn::Result<Option<IdGenerator>> {
+    let mut result = None;
+
+    for nested_meta in crud_metas(&field.attrs, FIELD_ATTRS)? {
+        let ml = match nested_meta {
+            NestedMeta::Meta(Meta::List(ml)) if ml.path.is_ident(ID) => ml,
+            _ => continue,
+        };
+
+        for nm in ml.nested.iter() {
+            if !nested_meta_is(nm, GENERATE) {
+                let found = match nm {
+                    NestedMeta::Meta(meta) => meta.path().get_ident().map(ToString::to_string),
+                    NestedMeta::Lit(_) => None,
+                };
+                return Err(unknown_keyword(
+                    nm,
+                    "id option",
+                    &found.unwrap_or_default(),
+                    &[GENERATE],
+                ));
+            }
+            let lit = expect_lit_str(nm, GENERATE)?;
+            let generator = IdGenerator::from_str(&lit.value())
+                .ok_or_else(|| unknown_keyword(lit, "id generator", &lit.value(), ID_GENERATORS))?;
+            result = Some(generator);
         }
-        _ => None,
-    };
+    }
 
-    // iterate an attribute until we find the `id` sub-attribute
-    let attrs_find_map = |field: &Field, attr: &Attribute| match attr.parse_meta() {
-        Ok(Meta::List(meta_list)) if meta_list.path.is_ident(TAG) => meta_list
-            .nested
-            .iter()
-            .find_map(|nested_meta| nested_meta_find_map(field, nested_meta)),
-        _ => None,
-    };
+    Ok(result)
+}
 
-    // iterate all attributes until we find one that contains the `id` sub-attribute
-    let field_find_map = |field: &Field| {
-        field
-            .attrs
-            .iter()
-            .find_map(|attr| attrs_find_map(field, attr))
-    };
+/// whether a sub-attribute's keyword is `name`
+fn nested_meta_is(nested_meta: &NestedMeta, name: &str) -> bool {
+    matches!(nested_meta, NestedMeta::Meta(meta) if meta.path().is_ident(name))
+}
+
+/// the string literal of `#[crud(name = "...")]`
+fn expect_lit_str<'a>(nested_meta: &'a NestedMeta, name: &str) -> syn::Result<&'a syn::LitStr> {
+    match nested_meta {
+        NestedMeta::Meta(Meta::NameValue(mnv)) => match &mnv.lit {
+            Lit::Str(s) => Ok(s),
+            other => Err(syn::Error::new_spanned(
+                other,
+                format!("`{name}` expects a string, e.g. `{name} = \"...\"`"),
+            )),
+        },
+        other => Err(syn::Error::new_spanned(
+            other,
+            format!("expected `{name} = \"...\"`"),
+        )),
+    }
+}
+
+/// the key of a field used by indexes
+///
+/// It is the field's name in the stored BSON document, following serde's `rename` and
+/// `rename_all`, unless an explicit path is given:
+///
+/// ```rust,ignore
+/// struct TestCrud {
+///     id: Option<ID>,
+///     #[crud(path = "option.position", single_index = "asc")]
+///     option: VertexOption,
+/// }
+/// ```
+fn field_key(field: &Field, rename_all: Option<RenameRule>) -> syn::Result<String> {
+    for nested_meta in crud_metas(&field.attrs, FIELD_ATTRS)? {
+        if nested_meta_is(&nested_meta, PATH) {
+            let lit = expect_lit_str(&nested_meta, PATH)?;
+            return check_path(lit);
+        }
+    }
 
-    // iterate all fields until we find a field whose attribute is `crud(id)`
-    named_fields.iter().find_map(field_find_map)
+    Ok(bson_name(field, rename_all))
 }
 
 /// find out fields whose attribute is `single_index`
@@ -148,246 +370,556 @@ fn get_attr_id(named_fields: &NamedFields) -> Option<Ident> {
 ///     name: String,
 ///     #[crud(single_index = "unique,desc,text")]
 ///     tag: String,
+///     #[crud(single_index(expire_after = "7d"))]
+///     created_at: DateTime,
+///     #[crud(single_index(unique, partial = "{\"code\": {\"$exists\": true}}"))]
+///     code: Option<String>,
 /// }
 /// ```
 ///
-/// same as:
-///
-/// ```rust,ignore
-/// fn index_format(named_fields: &NamedFields) -> Vec<IndexOptions> {
-///     let mut result = Vec::<SingleIndex>::default();
-///     for field in named_fields.iter() {
-///         for attr in field.attrs.iter() {
-///             if let Ok(Meta::List(meta_list)) = attr.parse_meta() {
-///                 if meta_list.path.is_ident(TAG) {
-///                     for nm in meta_list.nested.iter() {
-///                         if let NestedMeta::Meta(Meta::NameValue(mnv)) = nm {
-///                             if mnv.path.is_ident(INDEX) {
-///                                 if let Lit::Str(ref s) = mnv.lit {
-///                                     result.push(SingleIndex::new_from_str(
-///                                         field.ident.as_ref().unwrap().to_string(),
-///                                         &s.value(),
-///                                     ));
-///                                 }
-///                             }
-///                         }
-///                     }
-///                 }
-///             }
-///         }
-///     }
-///     result
-/// }
-/// ```
-fn single_index_format(named_fields: &NamedFields) -> Option<SingleIndexOptions> {
-    // parse single_index attribute
-    let nested_meta_filter_map = |field: &Field, nested_meta: &NestedMeta| match nested_meta {
-        NestedMeta::Meta(Meta::NameValue(mnv)) if mnv.path.is_ident(SINGLE_INDEX) => {
-            match mnv.lit {
-                Lit::Str(ref s) => Some(SingleIndex::new_from_str(
-                    field.ident.as_ref().unwrap().to_string(),
-                    &s.value(),
-                )),
-                _ => None,
+/// index kinds: `asc`, `desc`, `hashed` and `2dsphere`; options: `unique`, `text`, `sparse`,
+/// `expire_after` (TTL, e.g. `"30s"`, `"12h"`, `"7d"`) and `partial` (JSON filter).
+fn single_index_format(
+    named_fields: &NamedFields,
+    rename_all: Option<RenameRule>,
+) -> syn::Result<Vec<SingleIndex>> {
+    let mut single_index = Vec::<SingleIndex>::new();
+
+    // iterate through all fields and their `crud(single_index = "...")` attributes
+    for field in named_fields.iter() {
+        for nested_meta in crud_metas(&field.attrs, FIELD_ATTRS)? {
+            if !nested_meta_is(&nested_meta, SINGLE_INDEX) {
+                continue;
+            }
+            let name = field_key(field, rename_all)?;
+            match nested_meta {
+                // - `crud(single_index(unique, dir = "desc", expire_after = "7d"))`
+                NestedMeta::Meta(Meta::List(ref ml)) => {
+                    single_index.push(SingleIndex::new_from_meta_list(name, ml)?)
+                }
+                // - `crud(single_index = "...")`
+                ref other => {
+                    let lit = expect_lit_str(other, SINGLE_INDEX)?;
+                    single_index.push(SingleIndex::new_from_lit(name, lit)?);
+                }
             }
         }
-        _ => None,
-    };
-
-    // iterate an attribute and filter out `crud(single_index = "...")`
-    let attrs_fmap = |field: &Field, attr: &Attribute| match attr.parse_meta() {
-        Ok(Meta::List(meta_list)) if meta_list.path.is_ident(TAG) => meta_list
-            .nested
-            .iter()
-            .filter_map(|nested_meta| nested_meta_filter_map(field, nested_meta))
-            .collect::<Vec<SingleIndex>>(),
-        _ => vec![],
-    };
-
-    // iterate through all attributes in a field
-    let field_fmap = |field: &Field| {
-        field
-            .attrs
-            .iter()
-            .flat_map(|attr| attrs_fmap(field, attr))
-            .collect::<Vec<SingleIndex>>()
-    };
-
-    // iterate through all fields
-    let single_index = named_fields
-        .iter()
-        .flat_map(field_fmap)
-        .collect::<Vec<SingleIndex>>();
-
-    if single_index.is_empty() {
-        None
-    } else {
-        Some(SingleIndexOptions(single_index))
     }
+
+    Ok(single_index)
 }
 
 /// find out fields whose attribute is `compound_index`
 ///
+/// Fields tagged by `compound_index` or `compound_index = "..."` belong to the unnamed compound
+/// index, and fields tagged by `compound_index(name = "...", ...)` belong to the named one.
+/// A field can join several compound indexes.
+///
 /// ```rust,ignore
 /// struct TestCrud {
 ///     id: Option<ID>,
 ///     #[crud(compound_index = "unique,asc")]
 ///     name: String,
 ///     #[crud(compound_index)]
+///     #[crud(compound_index(name = "by_tag_name", order = 1, dir = "desc", unique))]
 ///     tag: String,
+///     #[crud(compound_index(name = "by_tag_name", order = 2))]
+///     name: String,
 /// }
 /// ```
-///
-/// same as:
+fn compound_index_format(
+    named_fields: &NamedFields,
+    rename_all: Option<RenameRule>,
+) -> syn::Result<Vec<CompoundIndexOptions>> {
+    // keep the order of first appearance, the unnamed index is keyed by `None`
+    let mut compound_index = Vec::<CompoundIndexOptions>::new();
+
+    // iterate through all fields and their `crud(compound_index...)` attributes
+    for field in named_fields.iter() {
+        for nested_meta in crud_metas(&field.attrs, FIELD_ATTRS)? {
+            if !nested_meta_is(&nested_meta, COMPOUND_INDEX) {
+                continue;
+            }
+            let name = field_key(field, rename_all)?;
+            match nested_meta {
+                // - `crud(compound_index)`
+                NestedMeta::Meta(Meta::Path(ref p)) => {
+                    compound_index_group(&mut compound_index, None).add_keys(name, p.span())
+                }
+                // - `crud(compound_index(name = "...", order = 1, dir = "desc", unique))`
+                NestedMeta::Meta(Meta::List(ref ml)) => {
+                    let gk = GroupedKey::from_meta_list(name, ml)?;
+                    compound_index_group(&mut compound_index, gk.name.clone())
+                        .add_grouped_key(gk)?;
+                }
+                // - `crud(compound_index = "...")`
+                ref other => {
+                    let lit = expect_lit_str(other, COMPOUND_INDEX)?;
+                    compound_index_group(&mut compound_index, None).update_from_lit(name, lit)?;
+                }
+            }
+        }
+    }
+
+    for ci in compound_index.iter_mut() {
+        ci.sort_keys()?;
+    }
+
+    Ok(compound_index)
+}
+
+/// find the compound index by its name, or start a new one
+fn compound_index_group(
+    compound_index: &mut Vec<CompoundIndexOptions>,
+    name: Option<String>,
+) -> &mut CompoundIndexOptions {
+    match compound_index.iter().position(|ci| ci.name == name) {
+        Some(i) => &mut compound_index[i],
+        None => {
+            compound_index.push(CompoundIndexOptions::new(name));
+            compound_index.last_mut().unwrap()
+        }
+    }
+}
+
+/// find out fields whose attribute is `text`, and the struct's `text_index` options
 ///
 /// ```rust,ignore
-/// fn compound_index_format(named_fields: &NamedFields) -> Option<CompoundIndexOptions> {
-///     let mut result = CompoundIndexOptions::default();
-///     for field in named_fields.iter() {
-///         for attr in field.attrs.iter() {
-///             if let Ok(Meta::List(meta_list)) = attr.parse_meta() {
-///                 if meta_list.path.is_ident(TAG) {
-///                     for nested_meta in meta_list.nested.iter() {
-///                         match nested_meta {
-///                             NestedMeta::Meta(Meta::NameValue(mnv))
-///                                 if mnv.path.is_ident(COMPOUND_INDEX) =>
-///                             {
-///                                 if let Lit::Str(ref s) = mnv.lit {
-///                                     result.update_from_str(
-///                                         field.ident.as_ref().unwrap().to_string(),
-///                                         &s.value(),
-///                                     );
-///                                 }
-///                             }
-///                             NestedMeta::Meta(Meta::Path(mp)) if mp.is_ident(COMPOUND_INDEX) => {
-///                                 result.add_keys(field.ident.as_ref().unwrap().to_string());
-///                             }
-///                             _ => {}
-///                         }
-///                     }
-///                 }
-///             }
-///         }
-///     }
-///
-///     if result.keys.is_empty() {
-///         None
-///     } else {
-///         Some(result)
-///     }
+/// #[crud(text_index(default_language = "english", language_override = "lang"))]
+/// struct TestCrud {
+///     id: Option<ID>,
+///     #[crud(text(weight = 10))]
+///     name: String,
+///     #[crud(text)]
+///     description: String,
 /// }
 /// ```
-fn compound_index_format(named_fields: &NamedFields) -> Option<CompoundIndexOptions> {
-    // parse compound_index attribute
-    let nested_meta_fold = |mut result: CompoundIndexOptions,
-                            field: &Field,
-                            nested_meta: &NestedMeta| {
-        match nested_meta {
-            // - `crud(compound_index = "...")`
-            NestedMeta::Meta(Meta::NameValue(mnv)) if mnv.path.is_ident(COMPOUND_INDEX) => {
-                if let Lit::Str(ref s) = mnv.lit {
-                    result.update_from_str(field.ident.as_ref().unwrap().to_string(), &s.value());
+fn text_index_format(
+    input: &DeriveInput,
+    named_fields: &NamedFields,
+    rename_all: Option<RenameRule>,
+) -> syn::Result<Option<TextIndex>> {
+    let mut text_index = TextIndex::default();
+
+    for field in named_fields.iter() {
+        for nested_meta in crud_metas(&field.attrs, FIELD_ATTRS)? {
+            if let NestedMeta::Meta(ref meta) = nested_meta {
+                if meta.path().is_ident(TEXT) {
+                    let name = field_key(field, rename_all)?;
+                    text_index.add_field_from_meta(name, meta)?;
                 }
             }
-            // - `crud(compound_index)`
-            NestedMeta::Meta(Meta::Path(mp)) if mp.is_ident(COMPOUND_INDEX) => {
-                result.add_keys(field.ident.as_ref().unwrap().to_string());
+        }
+    }
+
+    let mut options = None;
+    for nested_meta in crud_metas(&input.attrs, STRUCT_ATTRS)? {
+        if let NestedMeta::Meta(ref meta) = nested_meta {
+            if meta.path().is_ident(TEXT_INDEX) {
+                text_index.update_from_meta(meta)?;
+                options = Some(nested_meta.span());
             }
-            _ => {}
         }
+    }
 
-        result
-    };
+    match (text_index.fields.is_empty(), options) {
+        (false, _) => Ok(Some(text_index)),
+        (true, Some(span)) => Err(syn::Error::new(
+            span,
+            "`text_index` is declared but no field is marked as `text`\n\
+             help: add `#[crud(text)]` to the fields to be searched",
+        )),
+        (true, None) => Ok(None),
+    }
+}
+
+/// get `IndexOptions` from a struct
+fn index_format(input: &DeriveInput, named_fields: &NamedFields) -> syn::Result<IndexOptions> {
+    let rename_all = rename_all(&input.attrs);
+    let single_index = single_index_format(named_fields, rename_all)?;
+    let compound_index = compound_index_format(named_fields, rename_all)?;
+    let text_index = text_index_format(input, named_fields, rename_all)?;
+
+    let indexes = single_index
+        .into_iter()
+        .map(Index::Single)
+        .chain(compound_index.into_iter().map(Index::Compound))
+        .chain(text_index.into_iter().map(Index::Text))
+        .collect();
+
+    Ok(IndexOptions(indexes))
+}
 
-    // iterate an attribute and filter out `crud(compound_index = "...")`
-    let attrs_fold = |result: CompoundIndexOptions, field: &Field, attr: &Attribute| {
-        if let Ok(Meta::List(meta_list)) = attr.parse_meta() {
-            if meta_list.path.is_ident(TAG) {
-                return meta_list
-                    .nested
-                    .iter()
-                    .fold(result, |cio, nm| nested_meta_fold(cio, field, nm));
+/// database and collection where a struct is stored, `None` falls back to `MongoClient`'s
+///
+/// ```rust,ignore
+/// #[crud(collection = "companies", database = "iio")]
+/// struct Company { ... }
+/// ```
+fn route_format(input: &DeriveInput) -> syn::Result<(Option<String>, Option<String>)> {
+    let mut database = None;
+    let mut collection = None;
+
+    for nested_meta in crud_metas(&input.attrs, STRUCT_ATTRS)? {
+        let (target, name, forbidden) = match nested_meta {
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(DATABASE) => (
+                &mut database,
+                DATABASE,
+                &['/', '\\', '.', ' ', '"', '$'][..],
+            ),
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(COLLECTION) => {
+                (&mut collection, COLLECTION, &['$'][..])
             }
+            _ => continue,
+        };
+
+        let value = expect_lit_str(&nested_meta, name)?.value();
+        if target.is_some() {
+            return Err(syn::Error::new_spanned(
+                nested_meta,
+                format!("duplicated `{name}`, a struct is stored in only one {name}"),
+            ));
+        }
+        if value.is_empty() || value.contains(forbidden) {
+            return Err(syn::Error::new_spanned(
+                nested_meta,
+                format!("invalid {name} name `{value}`"),
+            ));
         }
+        *target = Some(value);
+    }
 
-        result
-    };
+    Ok((database, collection))
+}
 
-    // iterate through all attributes in a field
-    let field_fold = |result: CompoundIndexOptions, field: &Field| {
-        field
-            .attrs
-            .iter()
-            .fold(result, |cio, attr| attrs_fold(cio, field, attr))
-    };
+/// path of the `crud` crate used by the generated code, `::crud` unless it is renamed
+///
+/// ```rust,ignore
+/// #[crud(crate = "my_crud")]
+/// struct Company { ... }
+/// ```
+fn crate_path(input: &DeriveInput, known: &[&str]) -> syn::Result<syn::Path> {
+    let mut result = None;
 
-    // iterate through all fields
-    let compound_index = named_fields
-        .iter()
-        .fold(CompoundIndexOptions::default(), field_fold);
+    for nested_meta in crud_metas(&input.attrs, known)? {
+        match nested_meta {
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(CRATE) => {}
+            _ => continue,
+        }
 
-    if compound_index.keys.is_empty() {
-        None
-    } else {
-        Some(compound_index)
+        let lit = expect_lit_str(&nested_meta, CRATE)?;
+        if result.is_some() {
+            return Err(syn::Error::new_spanned(
+                nested_meta,
+                format!("duplicated `{CRATE}`"),
+            ));
+        }
+        let path = lit.parse::<syn::Path>().map_err(|_| {
+            syn::Error::new_spanned(lit, format!("invalid crate path `{}`", lit.value()))
+        })?;
+        result = Some(path);
     }
+
+    Ok(result.unwrap_or_else(|| parse_quote!(::crud)))
 }
 
-/// get `IndexOptions` from a struct
-fn index_format(named_fields: &NamedFields) -> IndexOptions {
-    let single_index = single_index_format(named_fields);
-    let compound_index = compound_index_format(named_fields);
-
-    match (single_index, compound_index) {
-        (Some(si), None) => IndexOptions::Single(si),
-        (None, Some(ci)) => IndexOptions::Compound(ci),
-        (Some(_), Some(_)) => {
-            panic!("single_index & compound_index are currently not allowed at the same time")
+/// whether `crud::Hooks` is implemented by hand, `#[crud(hooks)]`
+fn has_hooks(input: &DeriveInput) -> syn::Result<bool> {
+    let mut result = false;
+
+    for nested_meta in crud_metas(&input.attrs, STRUCT_ATTRS)? {
+        match nested_meta {
+            NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident(HOOKS) => result = true,
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(HOOKS) => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "`hooks` takes no value, write `#[crud(hooks)]` and implement `crud::Hooks`",
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(result)
+}
+
+/// discriminator key and value of a struct sharing its collection with other structs
+///
+/// ```rust,ignore
+/// #[crud(collection = "graph", discriminator = "etype", value = "Company")]
+/// struct Company { ... }
+/// ```
+fn discriminator_format(input: &DeriveInput) -> syn::Result<Option<(String, String)>> {
+    let mut key = None;
+    let mut value = None;
+    let mut span = None;
+
+    for nested_meta in crud_metas(&input.attrs, STRUCT_ATTRS)? {
+        let (target, name) = match nested_meta {
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(DISCRIMINATOR) => {
+                (&mut key, DISCRIMINATOR)
+            }
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(VALUE) => (&mut value, VALUE),
+            _ => continue,
+        };
+
+        let lit = expect_lit_str(&nested_meta, name)?.value();
+        if target.is_some() {
+            return Err(syn::Error::new_spanned(
+                nested_meta,
+                format!("duplicated `{name}`"),
+            ));
+        }
+        if lit.is_empty() || (name == DISCRIMINATOR && (lit.starts_with('$') || lit == "_id")) {
+            return Err(syn::Error::new_spanned(
+                nested_meta,
+                format!("invalid {name} `{lit}`"),
+            ));
         }
-        _ => IndexOptions::None,
+        *target = Some(lit);
+        span = Some(nested_meta.span());
+    }
+
+    match (key, value, span) {
+        (Some(key), Some(value), _) => Ok(Some((key, value))),
+        (None, None, _) => Ok(None),
+        (_, _, span) => Err(syn::Error::new(
+            span.unwrap(),
+            "`discriminator` and `value` must be declared together\n\
+             help: e.g. `#[crud(discriminator = \"etype\", value = \"Company\")]`",
+        )),
     }
 }
 
 /// main process of handling derive stream
-fn impl_crud(input: &DeriveInput) -> proc_macro2::TokenStream {
+fn impl_crud(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     // name of the struct
     let name = input.ident.clone();
-    let named_fields = named_fields(input);
+    let named_fields = named_fields(input)?;
+    // path of the `crud` crate
+    let krate = crate_path(input, STRUCT_ATTRS)?;
     // index options of the struct
-    let io = index_format(&named_fields);
+    let io = index_format(input, &named_fields)?;
+    // database and collection of the struct
+    let (database, collection) = route_format(input)?;
+    let database = option_str(database);
+    let collection = option_str(collection);
+    // the struct's documents in a shared collection
+    let discriminator_format = discriminator_format(input)?;
+    let discriminator = match &discriminator_format {
+        Some((key, value)) => quote! { ::std::option::Option::Some((#key, #value)) },
+        None => quote! { ::std::option::Option::None },
+    };
 
     // get ID either from field `id` or field whose attribute is `id`
-    let id = match (get_field_id(&named_fields), get_attr_id(&named_fields)) {
+    let id_field = match (get_field_id(&named_fields), get_attr_id(&named_fields)?) {
+        (Some(id), Some(oid)) if id.ident != oid.ident => {
+            return Err(syn::Error::new_spanned(
+                &oid.ident,
+                "a field named `id` is the id already, `#[crud(id)]` would be ignored\n\
+                 help: remove `#[crud(id)]`, or move it to the field `id`",
+            ))
+        }
         (Some(id), _) => id,
         (None, Some(oid)) => oid,
-        _ => panic!("No `id` field nor `oid` attribute were found!"),
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "no id found for CRUD\n\
+                 help: add a field named `id`, or mark the id field with `#[crud(id)]`",
+            ))
+        }
     };
 
-    let expanded = quote! {
-        // impl `BaseCRUD`
-        impl BaseCRUD for #name {
-            fn get_id(&self) -> ::std::option::Option<bson::oid::ObjectId> {
-                self.#id
+    let id = id_field.ident.clone();
+    let id_ty = id_type(id_field)?;
+    // `BaseCRUD::new_id` is only overridden by a client-side generator
+    let new_id = id_generator(id_field)?.map(|generator| {
+        quote! {
+            fn new_id() -> ::std::option::Option<Self::Id> {
+                ::std::option::Option::Some(::std::convert::From::from(#generator))
             }
+        }
+    });
+
+    // `created_at`, `updated_at` and `soft_delete` stamped by `MongoCRUD`
+    let timestamps = Timestamps::from_fields(&named_fields, FIELD_ATTRS)?;
+    let stamps = timestamps.to_tokens(rename_all(&input.attrs));
+
+    // `version` checked and incremented by `MongoCRUD::update`
+    let version = version_field(&named_fields, FIELD_ATTRS)?;
+    if let Some(v) = version.filter(|v| timestamps.fields().any(|f| f.ident == v.ident)) {
+        return Err(syn::Error::new_spanned(
+            &v.ident,
+            "the version field cannot be a timestamp as well",
+        ));
+    }
+    let versioning = version_tokens(version, rename_all(&input.attrs));
+
+    // `validate` called by `MongoCRUD::create` and `MongoCRUD::update`
+    let validation = validate_tokens(&named_fields, FIELD_ATTRS, rename_all(&input.attrs))?;
+
+    // `encrypted_fields` encrypted by `MongoCRUD`, written by the client and the server only
+    let encrypted = encrypted_fields(&named_fields, FIELD_ATTRS)?;
+    for (field, _) in encrypted.iter() {
+        let managed = std::iter::once(id_field)
+            .chain(timestamps.fields())
+            .chain(version)
+            .any(|f| f.ident == field.ident);
+        if managed || is_nested(field, FIELD_ATTRS)? {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                "the id, timestamps, version and nested fields cannot be encrypted",
+            ));
+        }
+    }
+    let encryption = encrypt_tokens(&encrypted, rename_all(&input.attrs));
 
-            fn remove_id(&mut self) {
-                self.#id = None;
-            }
+    // `json_schema` of the stored documents
+    let (schema, schema_bounds) = schema_tokens(input, &named_fields, FIELD_ATTRS, &krate)?;
+
+    // `<Name>Patch`, neither the id, the timestamps, the version nor the discriminator are
+    // patched, a patch cannot turn a document into another type of its collection
+    let discriminator_field = discriminator_format.as_ref().and_then(|(key, _)| {
+        named_fields
+            .iter()
+            .find(|f| bson_name(f, rename_all(&input.attrs)) == *key)
+    });
+    let skip = std::iter::once(id_field)
+        .chain(timestamps.fields())
+        .chain(version)
+        .chain(discriminator_field)
+        .collect::<Vec<_>>();
+    let patch = impl_patch(input, &named_fields, &skip, FIELD_ATTRS, &krate)?;
+
+    // `<Name>Fields`, typed paths of all the fields
+    let fields = impl_fields(input, &named_fields, FIELD_ATTRS, &krate)?;
+
+    // structs of `#[crud(projection(...))]`
+    let projections = impl_projections(input, &named_fields, id_field, STRUCT_ATTRS, &krate)?;
+
+    // generics of the struct, `BaseCRUD::Id` and `MongoCRUD` add their bounds to the where clause
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+    let mut base_generics = input.generics.clone();
+    base_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! {
+            #id_ty: ::std::marker::Send
+                + ::std::marker::Sync
+                + ::std::clone::Clone
+                + _crud::__private::serde::Serialize
+                + _crud::__private::serde::de::DeserializeOwned
+                + ::std::marker::Unpin
+        });
+    base_generics
+        .make_where_clause()
+        .predicates
+        .extend(schema_bounds);
+    let mut crud_generics = base_generics.clone();
+    crud_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! {
+            #name #ty_generics: ::std::marker::Send
+                + ::std::marker::Sync
+                + ::std::clone::Clone
+                + _crud::__private::serde::Serialize
+                + _crud::__private::serde::de::DeserializeOwned
+                + ::std::marker::Unpin
+        });
+    let base_where_clause = &base_generics.where_clause;
+    let crud_where_clause = &crud_generics.where_clause;
+
+    // no-op `Hooks`, unless they are implemented by hand
+    let hooks = match has_hooks(input)? {
+        true => quote! {},
+        false => quote! {
+            impl #impl_generics _crud::Hooks for #name #ty_generics #crud_where_clause {}
+        },
+    };
+
+    let expanded = quote! {
+        #[doc(hidden)]
+        const _: () = {
+            use #krate as _crud;
+
+            // impl `BaseCRUD`
+            impl #impl_generics _crud::BaseCRUD for #name #ty_generics #base_where_clause {
+                type Id = #id_ty;
+
+                fn get_id(&self) -> ::std::option::Option<Self::Id> {
+                    ::std::clone::Clone::clone(&self.#id)
+                }
 
-            fn mutate_id(&mut self, oid: bson::oid::ObjectId) -> anyhow::Result<()> {
-                self.#id = Some(oid);
-                Ok(())
+                fn remove_id(&mut self) {
+                    self.#id = ::std::option::Option::None;
+                }
+
+                fn mutate_id(&mut self, id: Self::Id) -> _crud::__private::anyhow::Result<()> {
+                    self.#id = ::std::option::Option::Some(id);
+                    ::std::result::Result::Ok(())
+                }
+
+                #new_id
+
+                fn show_indexes() -> _crud::IndexOptions {
+                    #io
+                }
+
+                fn collection_name() -> ::std::option::Option<&'static str> {
+                    #collection
+                }
+
+                fn database_name() -> ::std::option::Option<&'static str> {
+                    #database
+                }
+
+                fn discriminator() -> ::std::option::Option<(&'static str, &'static str)> {
+                    #discriminator
+                }
+
+                #stamps
+
+                #versioning
+
+                #validation
+
+                fn json_schema() -> _crud::__private::bson::Document {
+                    #schema
+                }
+
+                #encryption
             }
 
-            fn show_indexes() -> crud::IndexOptions {
-                #io
+            // impl `Hooks`
+            #hooks
+
+            // impl `MongoCRUD`
+            #[_crud::__private::async_trait::async_trait]
+            impl #impl_generics _crud::MongoCRUD<#name #ty_generics> for _crud::MongoClient
+                #crud_where_clause
+            {
             }
-        }
+        };
+
+        // `<Name>Patch` and impl `Patchable`
+        #patch
+
+        // `<Name>Fields` and impl `Fields`
+        #fields
 
-        // impl `MongoCRUD`
-        #[async_trait::async_trait]
-        impl MongoCRUD<#name> for crud::MongoClient {}
+        // projections and impl `Projection`
+        #projections
     };
 
-    expanded
+    Ok(expanded)
+}
+
+/// `Option<String>` into `Some("...")` or `None` tokens
+fn option_str(value: Option<String>) -> proc_macro2::TokenStream {
+    match value {
+        Some(v) => quote! { ::std::option::Option::Some(#v) },
+        None => quote! { ::std::option::Option::None },
+    }
 }
@@ -0,0 +1,231 @@
+//! Patch
+//!
+//! Generates `<Name>Patch`, a copy of the struct in which every field is optional:
+//!
+//! - `T` becomes `Option<T>`, `Some` is `$set`
+//! - `Option<T>` becomes `Option<Option<T>>`, `Some(Some(_))` is `$set` and `Some(None)` is `$unset`
+//! - `#[crud(nested)] T` becomes `Option<<T as Patchable>::Patch>`, whose fields are `$set`
+//!   one by one, e.g. `option.position`
+//!
+//! The id field, the timestamps, the version and the discriminator field are never patched.
+//! The present fields are checked by their `#[crud(validate(...))]`, as `BaseCRUD::validate`
+//! does.
+//!
+//! A generic struct gets a generic patch, e.g. `Versioned<T>` gets `VersionedPatch<T>`, which
+//! also carries a `__marker` field, so `..Default::default()` is needed to build it.
+
+use quote::{format_ident, quote};
+use syn::{parse_quote, DeriveInput, Field, Meta, NestedMeta, Path, WherePredicate};
+
+use crate::attrs::crud_metas;
+use crate::rename::{bson_name, rename_all};
+use crate::validate::field_checks;
+use crate::{option_inner, NamedFields};
+
+pub const NESTED: &str = "nested";
+
+/// whether a field is marked by `#[crud(nested)]`
+pub fn is_nested(field: &Field, known: &[&str]) -> syn::Result<bool> {
+    let mut result = false;
+
+    for nested_meta in crud_metas(&field.attrs, known)? {
+        match nested_meta {
+            NestedMeta::Meta(Meta::Path(ref path)) if path.is_ident(NESTED) => result = true,
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(NESTED) => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "`nested` takes no value, write `#[crud(nested)]`",
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    if result && option_inner(&field.ty).is_some() {
+        return Err(syn::Error::new_spanned(
+            &field.ty,
+            "`#[crud(nested)]` cannot be used on an `Option`\n\
+             help: remove `nested`, the whole value is then set or unset",
+        ));
+    }
+
+    Ok(result)
+}
+
+/// `<Name>Patch` and the impls of `Patch` and `Patchable`, fields in `skip` are not patched
+pub fn impl_patch(
+    input: &DeriveInput,
+    named_fields: &NamedFields,
+    skip: &[&Field],
+    known: &[&str],
+    krate: &Path,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let patch_name = format_ident!("{}Patch", name);
+    let rename_all = rename_all(&input.attrs);
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    // bounds of the patched field types, needed when they are generic: nested patches are part
+    // of the struct, the values are only serialized when writing the update
+    let mut nested_bounds: Vec<WherePredicate> = vec![];
+    let mut value_bounds: Vec<WherePredicate> = vec![];
+
+    let mut fields = vec![];
+    let mut idents = vec![];
+    let mut writes = vec![];
+    let mut checks = vec![];
+
+    for field in named_fields.iter() {
+        if skip.iter().any(|f| f.ident == field.ident) {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().unwrap();
+        let key = bson_name(field, rename_all);
+        let field_vis = &field.vis;
+        let ty = &field.ty;
+
+        idents.push(ident);
+        let check = field_checks(field, known, quote!(&format!("{}{}", prefix, #key)))?;
+
+        if is_nested(field, known)? {
+            nested_bounds.push(parse_quote! { #ty: #krate::Patchable });
+            fields.push(quote! {
+                #field_vis #ident: ::std::option::Option<<#ty as #krate::Patchable>::Patch>
+            });
+            writes.push(quote! {
+                if let ::std::option::Option::Some(v) = &self.#ident {
+                    _crud::Patch::write_update(v, &_crud::PatchUpdate::nested(prefix, #key), update)?;
+                }
+            });
+            checks.push(quote! {
+                if let ::std::option::Option::Some(v) = &self.#ident {
+                    _crud::Patch::validate(v, &_crud::PatchUpdate::nested(prefix, #key), errors);
+                }
+            });
+        } else if option_inner(ty).is_some() {
+            value_bounds.push(parse_quote! { #ty: _crud::__private::serde::Serialize });
+            fields.push(quote! {
+                #field_vis #ident: ::std::option::Option<#ty>
+            });
+            writes.push(quote! {
+                match &self.#ident {
+                    ::std::option::Option::Some(::std::option::Option::Some(v)) => {
+                        update.set(prefix, #key, v)?
+                    }
+                    ::std::option::Option::Some(::std::option::Option::None) => {
+                        update.unset(prefix, #key)
+                    }
+                    ::std::option::Option::None => {}
+                }
+            });
+            checks.extend(check.map(|check| {
+                quote! {
+                    if let ::std::option::Option::Some(::std::option::Option::Some(value)) = &self.#ident {
+                        #check
+                    }
+                }
+            }));
+        } else {
+            value_bounds.push(parse_quote! { #ty: _crud::__private::serde::Serialize });
+            fields.push(quote! {
+                #field_vis #ident: ::std::option::Option<#ty>
+            });
+            writes.push(quote! {
+                if let ::std::option::Option::Some(v) = &self.#ident {
+                    update.set(prefix, #key, v)?;
+                }
+            });
+            checks.extend(check.map(|check| {
+                quote! {
+                    if let ::std::option::Option::Some(value) = &self.#ident {
+                        #check
+                    }
+                }
+            }));
+        }
+    }
+
+    // the type parameters might all be in skipped fields
+    let marker = (!input.generics.params.is_empty()).then(|| {
+        quote! {
+            #[doc(hidden)]
+            #vis __marker: ::std::marker::PhantomData<fn() -> #name #ty_generics>,
+        }
+    });
+    let marker_default = marker
+        .as_ref()
+        .map(|_| quote! { __marker: ::std::marker::PhantomData, });
+
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(nested_bounds);
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+    let mut write_generics = generics.clone();
+    write_generics
+        .make_where_clause()
+        .predicates
+        .extend(value_bounds);
+    let write_where_clause = &write_generics.where_clause;
+
+    let mut patchable_generics = write_generics.clone();
+    patchable_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! {
+            #patch_name #ty_generics: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync
+        });
+    let patchable_where_clause = &patchable_generics.where_clause;
+
+    let doc = format!("Partial update of [`{name}`], only the present fields are written.");
+
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Clone)]
+        #vis struct #patch_name #impl_generics #where_clause {
+            #(#fields,)*
+            #marker
+        }
+
+        impl #impl_generics ::std::default::Default for #patch_name #ty_generics #where_clause {
+            fn default() -> Self {
+                #patch_name {
+                    #(#idents: ::std::option::Option::None,)*
+                    #marker_default
+                }
+            }
+        }
+
+        #[doc(hidden)]
+        const _: () = {
+            use #krate as _crud;
+
+            impl #impl_generics _crud::Patch for #patch_name #ty_generics #write_where_clause {
+                type Target = #name #ty_generics;
+
+                #[allow(unused_variables)]
+                fn write_update(
+                    &self,
+                    prefix: &str,
+                    update: &mut _crud::PatchUpdate,
+                ) -> _crud::__private::anyhow::Result<()> {
+                    #(#writes)*
+                    ::std::result::Result::Ok(())
+                }
+
+                #[allow(unused_variables)]
+                fn validate(&self, prefix: &str, errors: &mut _crud::ValidationErrors) {
+                    #(#checks)*
+                }
+            }
+
+            impl #impl_generics _crud::Patchable for #name #ty_generics #patchable_where_clause {
+                type Patch = #patch_name #ty_generics;
+            }
+        };
+    })
+}
@@ -0,0 +1,318 @@
+//! Projection
+//!
+//! Lightweight structs of some of the fields, generating the structs and their impls of
+//! `Projection`:
+//!
+//! ```rust,ignore
+//! #[crud(projection(name = "CompanySummary", fields("name", "category"), derive(Debug)))]
+//! struct Company {
+//!     id: Option<ID>,
+//!     name: String,
+//!     category: Industry,
+//!     data: Option<JsonValue>,
+//!     ...
+//! }
+//!
+//! // generates
+//! struct CompanySummary {
+//!     id: Option<ID>,
+//!     name: String,
+//!     category: Industry,
+//! }
+//! ```
+//!
+//! The id is always projected. Fields keep their serde attributes, and the struct keeps
+//! `rename_all`, so that the stored keys are the same.
+
+use quote::quote;
+use syn::{
+    ext::IdentExt, Attribute, DeriveInput, Field, Ident, Lit, LitStr, Meta, MetaList, NestedMeta,
+    Path,
+};
+
+use crate::attrs::{crud_metas, unknown_keyword};
+use crate::rename::{bson_name, rename_all};
+use crate::{NamedFields, TAG};
+
+pub const PROJECTION: &str = "projection";
+const NAME: &str = "name";
+const FIELDS: &str = "fields";
+const DERIVE: &str = "derive";
+const SERDE: &str = "serde";
+const RENAME_ALL: &str = "rename_all";
+
+/// options of `projection(...)`
+const PROJECTION_KEYWORDS: &[&str] = &[NAME, FIELDS, DERIVE];
+
+/// a `#[crud(projection(...))]`
+struct Projection<'a> {
+    name: Ident,
+    fields: Vec<&'a Field>,
+    derives: Vec<Path>,
+}
+
+impl<'a> Projection<'a> {
+    fn from_meta_list(ml: &MetaList, named_fields: &'a NamedFields) -> syn::Result<Self> {
+        let mut name = None;
+        let mut fields = None;
+        let mut derives = vec![];
+
+        for nested_meta in ml.nested.iter() {
+            let meta = match nested_meta {
+                NestedMeta::Meta(meta) => meta,
+                NestedMeta::Lit(lit) => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "expected `name = \"...\"`, `fields(...)` or `derive(...)`",
+                    ))
+                }
+            };
+            match meta {
+                Meta::NameValue(mnv) if mnv.path.is_ident(NAME) => match &mnv.lit {
+                    Lit::Str(s) if name.is_none() => name = Some(projection_name(s)?),
+                    Lit::Str(_) => {
+                        return Err(syn::Error::new_spanned(mnv, "duplicated `name`"));
+                    }
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "`name` expects a string, e.g. `name = \"CompanySummary\"`",
+                        ))
+                    }
+                },
+                Meta::List(list) if list.path.is_ident(FIELDS) => {
+                    if fields.is_some() {
+                        return Err(syn::Error::new_spanned(list, "duplicated `fields`"));
+                    }
+                    fields = Some(projected_fields(list, named_fields)?);
+                }
+                Meta::List(list) if list.path.is_ident(DERIVE) => {
+                    for nm in list.nested.iter() {
+                        match nm {
+                            NestedMeta::Meta(Meta::Path(path)) => derives.push(path.clone()),
+                            other => {
+                                return Err(syn::Error::new_spanned(
+                                    other,
+                                    "expected a trait, e.g. `derive(Debug, PartialEq)`",
+                                ))
+                            }
+                        }
+                    }
+                }
+                other => {
+                    let ident = other.path().get_ident().map(ToString::to_string);
+                    if PROJECTION_KEYWORDS.iter().any(|k| other.path().is_ident(k)) {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "expected `name = \"...\"`, `fields(\"...\", ..)` or `derive(...)`",
+                        ));
+                    }
+                    return Err(unknown_keyword(
+                        other.path(),
+                        "projection option",
+                        &ident.unwrap_or_default(),
+                        PROJECTION_KEYWORDS,
+                    ));
+                }
+            }
+        }
+
+        match (name, fields) {
+            (Some(name), Some(fields)) => Ok(Projection {
+                name,
+                fields,
+                derives,
+            }),
+            _ => Err(syn::Error::new_spanned(
+                ml,
+                "a projection needs a `name` and its `fields`\n\
+                 help: e.g. `projection(name = \"CompanySummary\", fields(\"name\", \"category\"))`",
+            )),
+        }
+    }
+}
+
+/// the name of the generated struct
+fn projection_name(lit: &LitStr) -> syn::Result<Ident> {
+    lit.parse::<Ident>()
+        .map_err(|_| syn::Error::new_spanned(lit, format!("invalid struct name `{}`", lit.value())))
+}
+
+/// the fields of `fields("name", "category")`, by their Rust names
+fn projected_fields<'a>(
+    list: &MetaList,
+    named_fields: &'a NamedFields,
+) -> syn::Result<Vec<&'a Field>> {
+    let names = named_fields
+        .iter()
+        .map(|f| f.ident.as_ref().unwrap().unraw().to_string())
+        .collect::<Vec<_>>();
+    let mut result: Vec<&Field> = vec![];
+
+    for nested_meta in list.nested.iter() {
+        let lit = match nested_meta {
+            NestedMeta::Lit(Lit::Str(s)) => s,
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected field names, e.g. `fields(\"name\", \"category\")`",
+                ))
+            }
+        };
+        let value = lit.value();
+        let field = match names.iter().position(|n| *n == value) {
+            Some(i) => &named_fields[i],
+            None => {
+                let expected = names.iter().map(String::as_str).collect::<Vec<_>>();
+                return Err(unknown_keyword(lit, "field", &value, &expected));
+            }
+        };
+        if result.iter().any(|f| f.ident == field.ident) {
+            return Err(syn::Error::new_spanned(
+                lit,
+                format!("duplicated field `{value}`"),
+            ));
+        }
+        result.push(field);
+    }
+
+    if result.is_empty() {
+        return Err(syn::Error::new_spanned(
+            list,
+            "a projection needs at least one field",
+        ));
+    }
+
+    Ok(result)
+}
+
+/// the struct's `#[serde(rename_all = "...")]`, the other struct attributes are not copied
+fn rename_all_attr(attrs: &[Attribute]) -> Option<proc_macro2::TokenStream> {
+    let metas = attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(SERDE))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(ml)) => Some(ml.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter(|nm| matches!(nm, NestedMeta::Meta(meta) if meta.path().is_ident(RENAME_ALL)))
+        .collect::<Vec<_>>();
+
+    match metas.is_empty() {
+        true => None,
+        false => Some(quote! { #[serde(#(#metas),*)] }),
+    }
+}
+
+/// the generated structs and their impls of `Projection`
+pub fn impl_projections(
+    input: &DeriveInput,
+    named_fields: &NamedFields,
+    id_field: &Field,
+    known: &[&str],
+    krate: &Path,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut projections: Vec<Projection> = vec![];
+    for nested_meta in crud_metas(&input.attrs, known)? {
+        let ml = match nested_meta {
+            NestedMeta::Meta(Meta::List(ref ml)) if ml.path.is_ident(PROJECTION) => ml,
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(PROJECTION) => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected `projection(name = \"...\", fields(\"...\", ..))`",
+                ))
+            }
+            _ => continue,
+        };
+
+        let projection = Projection::from_meta_list(ml, named_fields)?;
+        if projection.name == input.ident || projections.iter().any(|p| p.name == projection.name) {
+            return Err(syn::Error::new_spanned(
+                ml,
+                format!("duplicated struct name `{}`", projection.name),
+            ));
+        }
+        projections.push(projection);
+    }
+
+    if projections.is_empty() {
+        return Ok(quote! {});
+    }
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "projections of a generic struct are not supported",
+        ));
+    }
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let rename = rename_all(&input.attrs);
+    let rename_attr = rename_all_attr(&input.attrs);
+    let serde_path = format!("{}::__private::serde", quote!(#krate)).replace(' ', "");
+
+    let structs = projections.iter().map(|p| {
+        let projection_name = &p.name;
+        let derives = &p.derives;
+        let fields = std::iter::once(id_field)
+            .chain(
+                p.fields
+                    .iter()
+                    .copied()
+                    .filter(|f| f.ident != id_field.ident),
+            )
+            .collect::<Vec<_>>();
+        let keys = p.fields.iter().map(|f| bson_name(f, rename));
+        // the fields as declared, but `#[crud(...)]`
+        let declared = fields.iter().map(|f| {
+            let attrs = f.attrs.iter().filter(|a| !a.path.is_ident(TAG));
+            let field_vis = &f.vis;
+            let ident = &f.ident;
+            let ty = &f.ty;
+            quote! {
+                #(#attrs)*
+                #field_vis #ident: #ty
+            }
+        });
+        let listed = p
+            .fields
+            .iter()
+            .map(|f| format!("`{}`", f.ident.as_ref().unwrap().unraw()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let doc = format!("Projection of [`{name}`] on its id and {listed}.");
+
+        quote! {
+            #[doc = #doc]
+            #[derive(
+                ::std::clone::Clone,
+                #krate::__private::serde::Serialize,
+                #krate::__private::serde::Deserialize,
+                #(#derives),*
+            )]
+            #[serde(crate = #serde_path)]
+            #rename_attr
+            #vis struct #projection_name {
+                #(#declared),*
+            }
+
+            #[doc(hidden)]
+            const _: () = {
+                use #krate as _crud;
+
+                impl _crud::Projection for #projection_name {
+                    type Of = #name;
+
+                    fn projection() -> _crud::__private::bson::Document {
+                        let mut doc = _crud::__private::bson::Document::new();
+                        #( doc.insert(#keys, 1_i32); )*
+                        doc
+                    }
+                }
+            };
+        }
+    });
+
+    Ok(quote! { #(#structs)* })
+}
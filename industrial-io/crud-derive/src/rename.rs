@@ -0,0 +1,154 @@
+//! Rename
+//!
+//! MongoDB stores what serde serializes, so the keys used by indexes must follow the same
+//! renaming rules as serde: `#[serde(rename = "...")]` on a field, and
+//! `#[serde(rename_all = "...")]` on a struct.
+
+use syn::{ext::IdentExt, Attribute, Field, Lit, Meta, NestedMeta};
+
+const SERDE: &str = "serde";
+const RENAME: &str = "rename";
+const RENAME_ALL: &str = "rename_all";
+const SERIALIZE: &str = "serialize";
+const SKIP_SERIALIZING_IF: &str = "skip_serializing_if";
+
+/// serde's `rename_all` rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "lowercase" => Some(RenameRule::Lower),
+            "UPPERCASE" => Some(RenameRule::Upper),
+            "PascalCase" => Some(RenameRule::Pascal),
+            "camelCase" => Some(RenameRule::Camel),
+            "snake_case" => Some(RenameRule::Snake),
+            "SCREAMING_SNAKE_CASE" => Some(RenameRule::ScreamingSnake),
+            "kebab-case" => Some(RenameRule::Kebab),
+            "SCREAMING-KEBAB-CASE" => Some(RenameRule::ScreamingKebab),
+            _ => None,
+        }
+    }
+
+    /// same as serde's `RenameRule::apply_to_field`, Rust field names are `snake_case`
+    pub fn apply_to_field(&self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_owned(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply_to_field(field);
+                pascal[..1].to_ascii_lowercase() + &pascal[1..]
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => RenameRule::ScreamingSnake
+                .apply_to_field(field)
+                .replace('_', "-"),
+        }
+    }
+}
+
+/// the serialize side of `rename = "..."` or `rename(serialize = "...")`
+fn serialize_name(meta: &Meta) -> Option<String> {
+    match meta {
+        Meta::NameValue(mnv) => match &mnv.lit {
+            Lit::Str(s) => Some(s.value()),
+            _ => None,
+        },
+        Meta::List(ml) => ml.nested.iter().find_map(|nm| match nm {
+            NestedMeta::Meta(Meta::NameValue(mnv)) if mnv.path.is_ident(SERIALIZE) => {
+                match &mnv.lit {
+                    Lit::Str(s) => Some(s.value()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }),
+        Meta::Path(_) => None,
+    }
+}
+
+/// find the serialize side of a `#[serde(...)]` sub-attribute, e.g. `rename` or `rename_all`
+fn serde_value(attrs: &[Attribute], name: &str) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(SERDE))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(ml)) => Some(ml.nested),
+            _ => None,
+        })
+        .flatten()
+        .find_map(|nm| match nm {
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(name) => serialize_name(meta),
+            _ => None,
+        })
+}
+
+/// the struct's `#[serde(rename_all = "...")]`, unknown rules are left for serde to report
+pub fn rename_all(attrs: &[Attribute]) -> Option<RenameRule> {
+    serde_value(attrs, RENAME_ALL).and_then(|s| RenameRule::from_str(&s))
+}
+
+/// whether `#[serde(...)]` declares `name`, in any form, e.g. `default` or `default = "..."`
+pub fn has_serde(attrs: &[Attribute], name: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident(SERDE))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(ml)) => Some(ml.nested),
+            _ => None,
+        })
+        .flatten()
+        .any(|nm| matches!(nm, NestedMeta::Meta(ref meta) if meta.path().is_ident(name)))
+}
+
+/// whether a field has `#[serde(skip_serializing_if = "...")]`
+pub fn skips_serializing_if(field: &Field) -> bool {
+    serde_value(&field.attrs, SKIP_SERIALIZING_IF).is_some()
+}
+
+/// the key of a field in the stored BSON document
+///
+/// ```rust,ignore
+/// #[serde(rename_all = "camelCase")]
+/// struct TestCrud {
+///     #[serde(rename = "_id")]
+///     id: Option<ID>,      // "_id"
+///     full_name: String,   // "fullName"
+/// }
+/// ```
+pub fn bson_name(field: &Field, rename_all: Option<RenameRule>) -> String {
+    if let Some(name) = serde_value(&field.attrs, RENAME) {
+        return name;
+    }
+
+    let name = field.ident.as_ref().unwrap().unraw().to_string();
+    match rename_all {
+        Some(rule) => rule.apply_to_field(&name),
+        None => name,
+    }
+}
@@ -0,0 +1,308 @@
+//! Schema
+//!
+//! `$jsonSchema` of a struct, generating `BaseCRUD::json_schema`, or `BsonSchema::bson_schema`
+//! of a type nested by `#[crud(nested)]`:
+//!
+//! ```rust,ignore
+//! struct TestCrud {
+//!     name: String,                             // { bsonType: "string" }, required
+//!     tags: Vec<String>,                        // { bsonType: "array", items: { ... } }
+//!     weight: Option<f64>,                      // `null` accepted, not required
+//!     #[serde(default)]
+//!     version: i64,                             // not required
+//!     #[crud(nested)]
+//!     option: VertexOption,                     // `VertexOption::bson_schema()`
+//!     #[crud(schema = "{\"enum\": [\"Left\", \"Right\"]}")]
+//!     position: Position,                       // declared, as the enum is unknown
+//!     ...
+//! }
+//! ```
+//!
+//! An encrypted field is a `binData`, see `crate::encrypt`.
+//!
+//! Types are known by their names, so a type alias or a type of another crate is not checked
+//! unless its schema is declared.
+
+use quote::quote;
+use syn::{
+    parse_quote, DeriveInput, Field, GenericArgument, Lit, Meta, NestedMeta, Path, PathArguments,
+    Type,
+};
+
+use crate::attrs::crud_metas;
+use crate::encrypt::encrypt_mode;
+use crate::indexes::json_object_to_tokens;
+use crate::patch::is_nested;
+use crate::rename::{bson_name, has_serde, rename_all, skips_serializing_if};
+use crate::{option_inner, NamedFields};
+
+pub const SCHEMA: &str = "schema";
+const DEFAULT: &str = "default";
+const SKIP: &str = "skip";
+const SKIP_SERIALIZING: &str = "skip_serializing";
+const FLATTEN: &str = "flatten";
+
+/// `{ bsonType: ... }`
+fn bson_type(types: &[&str]) -> proc_macro2::TokenStream {
+    let value = match types {
+        [t] => quote!(#t),
+        _ => quote!(vec![#(#types),*]),
+    };
+    quote! {
+        {
+            let mut doc = _crud::__private::bson::Document::new();
+            doc.insert("bsonType", #value);
+            doc
+        }
+    }
+}
+
+/// `{ bsonType: "array", items: ... }`
+fn array(items: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let array = bson_type(&["array"]);
+    quote! {
+        {
+            let mut doc = #array;
+            doc.insert("items", #items);
+            doc
+        }
+    }
+}
+
+/// no constraint
+fn any() -> proc_macro2::TokenStream {
+    quote! { _crud::__private::bson::Document::new() }
+}
+
+/// the generic arguments of `T<A, B>`
+fn type_args(args: &PathArguments) -> Vec<&Type> {
+    match args {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// schema of a type, by its name
+fn type_schema(ty: &Type) -> proc_macro2::TokenStream {
+    let seg = match ty {
+        Type::Path(tp) if tp.qself.is_none() => match tp.path.segments.last() {
+            Some(seg) => seg,
+            None => return any(),
+        },
+        Type::Reference(r) => return type_schema(&r.elem),
+        Type::Paren(p) => return type_schema(&p.elem),
+        Type::Group(g) => return type_schema(&g.elem),
+        Type::Slice(s) => return array(type_schema(&s.elem)),
+        Type::Array(a) => return array(type_schema(&a.elem)),
+        Type::Tuple(t) if !t.elems.is_empty() => {
+            let len = t.elems.len() as i64;
+            let items = t.elems.iter().map(type_schema);
+            let array = bson_type(&["array"]);
+            return quote! {
+                {
+                    let mut doc = #array;
+                    doc.insert("minItems", #len);
+                    doc.insert("maxItems", #len);
+                    doc.insert("items", vec![#(#items),*]);
+                    doc
+                }
+            };
+        }
+        _ => return any(),
+    };
+
+    let args = type_args(&seg.arguments);
+    match (seg.ident.to_string().as_str(), args.as_slice()) {
+        ("Option", [inner]) => {
+            let inner = type_schema(inner);
+            quote! { _crud::schema::nullable(#inner) }
+        }
+        ("Box" | "Arc" | "Rc", [inner]) => type_schema(inner),
+        ("Vec" | "VecDeque" | "LinkedList" | "HashSet" | "BTreeSet", [inner]) => {
+            array(type_schema(inner))
+        }
+        ("HashMap" | "BTreeMap", [_, value]) => {
+            let object = bson_type(&["object"]);
+            let value = type_schema(value);
+            quote! {
+                {
+                    let mut doc = #object;
+                    doc.insert("additionalProperties", #value);
+                    doc
+                }
+            }
+        }
+        (_, [_, ..]) => any(),
+        ("String" | "str" | "char", []) => bson_type(&["string"]),
+        ("bool", []) => bson_type(&["bool"]),
+        ("i8" | "i16" | "i32" | "u8" | "u16", []) => bson_type(&["int"]),
+        // a missing field incremented by `$inc: 1` is an `int`
+        ("i64" | "u32" | "u64" | "isize" | "usize", []) => bson_type(&["int", "long"]),
+        ("f32" | "f64", []) => bson_type(&["double", "int", "long"]),
+        ("ObjectId", []) => bson_type(&["objectId"]),
+        ("DateTime", []) => bson_type(&["date"]),
+        ("Uuid" | "Binary", []) => bson_type(&["binData"]),
+        ("Document", []) => bson_type(&["object"]),
+        ("Decimal128", []) => bson_type(&["decimal"]),
+        ("Timestamp", []) => bson_type(&["timestamp"]),
+        _ => any(),
+    }
+}
+
+/// `#[crud(schema = "{...}")]`, a JSON object checked at compile time
+fn declared_schema(field: &Field, known: &[&str]) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let mut result = None;
+
+    for nested_meta in crud_metas(&field.attrs, known)? {
+        let lit = match nested_meta {
+            NestedMeta::Meta(Meta::NameValue(ref mnv)) if mnv.path.is_ident(SCHEMA) => {
+                match &mnv.lit {
+                    Lit::Str(s) => s.clone(),
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "`schema` expects a string, e.g. `schema = \"{\\\"enum\\\": [\\\"A\\\"]}\"`",
+                        ))
+                    }
+                }
+            }
+            NestedMeta::Meta(ref meta) if meta.path().is_ident(SCHEMA) => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected `schema = \"{...}\"`, a JSON object",
+                ))
+            }
+            _ => continue,
+        };
+
+        if result.is_some() {
+            return Err(syn::Error::new_spanned(lit, "duplicated `schema`"));
+        }
+        let map = match serde_json::from_str(&lit.value()) {
+            Ok(serde_json::Value::Object(map)) => map,
+            Ok(_) => return Err(syn::Error::new_spanned(
+                lit,
+                "`schema` must be a JSON object, e.g. `schema = \"{\\\"enum\\\": [\\\"A\\\"]}\"`",
+            )),
+            Err(e) => {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    format!("invalid JSON in `schema`: {e}"),
+                ))
+            }
+        };
+        result = Some(json_object_to_tokens(&map));
+    }
+
+    Ok(result)
+}
+
+/// the schema of the struct as a `bson::Document` expression, and the bounds of the nested
+/// types it requires
+pub fn schema_tokens(
+    input: &DeriveInput,
+    named_fields: &NamedFields,
+    known: &[&str],
+    krate: &Path,
+) -> syn::Result<(proc_macro2::TokenStream, Vec<syn::WherePredicate>)> {
+    let rename_all = rename_all(&input.attrs);
+    // `#[serde(default)]` on the struct, every field may be missing
+    let struct_default = has_serde(&input.attrs, DEFAULT);
+
+    let mut keys = vec![];
+    let mut schemas = vec![];
+    let mut required = vec![];
+    let mut bounds = vec![];
+
+    for field in named_fields.iter() {
+        // not stored as a key of its own
+        if [SKIP, SKIP_SERIALIZING, FLATTEN]
+            .iter()
+            .any(|name| has_serde(&field.attrs, name))
+        {
+            continue;
+        }
+
+        let ty = &field.ty;
+        let optional = option_inner(ty).is_some();
+        let schema = match declared_schema(field, known)? {
+            Some(schema) if optional => quote! { _crud::schema::nullable(#schema) },
+            Some(schema) => schema,
+            None if encrypt_mode(field, known)?.is_some() => match optional {
+                true => bson_type(&["binData", "null"]),
+                false => bson_type(&["binData"]),
+            },
+            None if is_nested(field, known)? => {
+                bounds.push(parse_quote! { #ty: #krate::BsonSchema });
+                quote! { <#ty as _crud::BsonSchema>::bson_schema() }
+            }
+            None => type_schema(ty),
+        };
+
+        let key = bson_name(field, rename_all);
+        if !(optional
+            || struct_default
+            || has_serde(&field.attrs, DEFAULT)
+            || skips_serializing_if(field))
+        {
+            required.push(key.clone());
+        }
+        keys.push(key);
+        schemas.push(schema);
+    }
+
+    // `required` cannot be empty
+    let required = match required.is_empty() {
+        true => quote! {},
+        false => quote! { doc.insert("required", vec![#(#required),*]); },
+    };
+
+    let schema = quote! {
+        {
+            let mut properties = _crud::__private::bson::Document::new();
+            #( properties.insert(#keys, #schemas); )*
+
+            let mut doc = _crud::__private::bson::Document::new();
+            doc.insert("bsonType", "object");
+            #required
+            doc.insert("properties", properties);
+            doc
+        }
+    };
+
+    Ok((schema, bounds))
+}
+
+/// impl `BsonSchema` of a type nested by `#[crud(nested)]`
+pub fn impl_bson_schema(
+    input: &DeriveInput,
+    named_fields: &NamedFields,
+    known: &[&str],
+    krate: &Path,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let (schema, bounds) = schema_tokens(input, named_fields, known, krate)?;
+    let mut generics = input.generics.clone();
+    generics.make_where_clause().predicates.extend(bounds);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    Ok(quote! {
+        #[doc(hidden)]
+        const _: () = {
+            use #krate as _crud;
+
+            impl #impl_generics _crud::BsonSchema for #name #ty_generics #where_clause {
+                fn bson_schema() -> _crud::__private::bson::Document {
+                    #schema
+                }
+            }
+        };
+    })
+}
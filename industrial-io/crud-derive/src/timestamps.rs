@@ -0,0 +1,162 @@
+//! Timestamps
+//!
+//! Fields stamped by `MongoCRUD` with the client's clock:
+//!
+//! ```rust,ignore
+//! struct TestCrud {
+//!     #[crud(created_at)]
+//!     created_at: Option<DateTime>,   // set by `create`
+//!     #[crud(updated_at)]
+//!     updated_at: DateTime,           // set by `create`, `update` and `patch`
+//!     #[crud(soft_delete)]
+//!     deleted_at: Option<DateTime>,   // set by `delete`, `null` while the document is live
+//!     ...
+//! }
+//! ```
+
+use quote::quote;
+use syn::{Field, Meta, NestedMeta};
+
+use crate::attrs::crud_metas;
+use crate::rename::{bson_name, skips_serializing_if, RenameRule};
+use crate::{option_inner, NamedFields};
+
+pub const CREATED_AT: &str = "created_at";
+pub const UPDATED_AT: &str = "updated_at";
+pub const SOFT_DELETE: &str = "soft_delete";
+
+/// the `#[crud(created_at)]`, `#[crud(updated_at)]` and `#[crud(soft_delete)]` fields
+pub struct Timestamps<'a> {
+    pub created_at: Option<&'a Field>,
+    pub updated_at: Option<&'a Field>,
+    pub deleted_at: Option<&'a Field>,
+}
+
+impl<'a> Timestamps<'a> {
+    pub fn from_fields(named_fields: &'a NamedFields, known: &[&str]) -> syn::Result<Self> {
+        let mut result = Timestamps {
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        };
+
+        for field in named_fields.iter() {
+            for nested_meta in crud_metas(&field.attrs, known)? {
+                let (target, name) = match &nested_meta {
+                    NestedMeta::Meta(meta) if meta.path().is_ident(CREATED_AT) => {
+                        (&mut result.created_at, CREATED_AT)
+                    }
+                    NestedMeta::Meta(meta) if meta.path().is_ident(UPDATED_AT) => {
+                        (&mut result.updated_at, UPDATED_AT)
+                    }
+                    NestedMeta::Meta(meta) if meta.path().is_ident(SOFT_DELETE) => {
+                        (&mut result.deleted_at, SOFT_DELETE)
+                    }
+                    _ => continue,
+                };
+
+                if !matches!(nested_meta, NestedMeta::Meta(Meta::Path(_))) {
+                    return Err(syn::Error::new_spanned(
+                        nested_meta,
+                        format!("`{name}` takes no value, write `#[crud({name})]`"),
+                    ));
+                }
+                if target.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        nested_meta,
+                        format!("duplicated `#[crud({name})]`, only one field can be `{name}`"),
+                    ));
+                }
+                *target = Some(field);
+            }
+        }
+
+        let fields = result.fields().collect::<Vec<_>>();
+        for (i, f) in fields.iter().enumerate() {
+            if fields[..i].iter().any(|other| other.ident == f.ident) {
+                return Err(syn::Error::new_spanned(
+                    &f.ident,
+                    "a field can only be one of `created_at`, `updated_at` and `soft_delete`",
+                ));
+            }
+        }
+
+        if let Some(field) = result.deleted_at {
+            if option_inner(&field.ty).is_none() {
+                return Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "the soft delete field must be an `Option`\n\
+                     help: use `Option<DateTime>`, `None` while the document is live",
+                ));
+            }
+            if skips_serializing_if(field) {
+                return Err(syn::Error::new_spanned(
+                    &field.ident,
+                    "the soft delete field must be stored as `null` while the document is live\n\
+                     help: remove `skip_serializing_if`, unique indexes only cover `null`",
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// the fields, which are skipped by `<Name>Patch`
+    pub fn fields(&self) -> impl Iterator<Item = &'a Field> {
+        self.created_at
+            .into_iter()
+            .chain(self.updated_at)
+            .chain(self.deleted_at)
+    }
+
+    /// `stamp_created`, `stamp_updated`, `created_at_key`, `updated_at_key` and
+    /// `soft_delete_key` of `BaseCRUD`
+    pub fn to_tokens(&self, rename_all: Option<RenameRule>) -> proc_macro2::TokenStream {
+        let mut tokens = quote! {};
+
+        if let Some(field) = self.deleted_at {
+            let name = bson_name(field, rename_all);
+            tokens.extend(quote! {
+                fn soft_delete_key() -> ::std::option::Option<&'static str> {
+                    ::std::option::Option::Some(#name)
+                }
+            });
+        }
+
+        for (field, stamp, key) in [
+            (
+                self.created_at,
+                quote!(stamp_created),
+                quote!(created_at_key),
+            ),
+            (
+                self.updated_at,
+                quote!(stamp_updated),
+                quote!(updated_at_key),
+            ),
+        ] {
+            let field = match field {
+                Some(f) => f,
+                None => continue,
+            };
+            let ident = &field.ident;
+            let value = match option_inner(&field.ty) {
+                Some(_) => quote! { ::std::option::Option::Some(now) },
+                None => quote! { now },
+            };
+            let name = bson_name(field, rename_all);
+
+            tokens.extend(quote! {
+                fn #stamp(&mut self, now: _crud::__private::bson::DateTime) {
+                    self.#ident = #value;
+                }
+
+                fn #key() -> ::std::option::Option<&'static str> {
+                    ::std::option::Option::Some(#name)
+                }
+            });
+        }
+
+        tokens
+    }
+}
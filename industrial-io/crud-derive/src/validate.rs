@@ -0,0 +1,304 @@
+//! Validate
+//!
+//! Field validation, generating `BaseCRUD::validate`:
+//!
+//! ```rust,ignore
+//! struct TestCrud {
+//!     #[crud(validate(length(min = 1, max = 200)))]
+//!     name: String,
+//!     #[crud(validate(range(min = 0.0, max = 1.0)))]
+//!     weight: Option<f64>,                      // `None` is not checked
+//!     #[crud(validate(range(min = "-90", max = 90)))]
+//!     latitude: i32,                            // a negative bound is written as a string
+//!     #[crud(validate(regex = "^[A-Z]{2}$", custom = "check_country"))]
+//!     country: String,
+//!     ...
+//! }
+//! ```
+
+use quote::quote;
+use syn::{Expr, Field, Lit, LitStr, Meta, NestedMeta, Path};
+
+use crate::attrs::{crud_metas, unknown_keyword};
+use crate::rename::{bson_name, RenameRule};
+use crate::{option_inner, NamedFields};
+
+pub const VALIDATE: &str = "validate";
+const LENGTH: &str = "length";
+const RANGE: &str = "range";
+const REGEX: &str = "regex";
+const CUSTOM: &str = "custom";
+const MIN: &str = "min";
+const MAX: &str = "max";
+
+/// validators accepted by `#[crud(validate(...))]`
+const VALIDATORS: &[&str] = &[LENGTH, RANGE, REGEX, CUSTOM];
+
+/// a check of a field
+enum Validator {
+    /// `length(min = 1, max = 200)`
+    Length(Option<usize>, Option<usize>),
+    /// `range(min = 0.0, max = 1.0)`
+    Range(Option<Box<Expr>>, Option<Box<Expr>>),
+    /// `regex = "^[A-Z]{2}$"`
+    Regex(LitStr),
+    /// `custom = "check_country"`, a `fn(&T) -> Result<(), String>`
+    Custom(Path),
+}
+
+impl Validator {
+    fn to_tokens(&self) -> proc_macro2::TokenStream {
+        match self {
+            Validator::Length(min, max) => {
+                let min = option_tokens(min.map(|m| quote!(#m)));
+                let max = option_tokens(max.map(|m| quote!(#m)));
+                quote! { _crud::validate::length(value, #min, #max) }
+            }
+            Validator::Range(min, max) => {
+                let min = option_tokens(min.as_ref().map(|m| quote!(#m)));
+                let max = option_tokens(max.as_ref().map(|m| quote!(#m)));
+                quote! { _crud::validate::range(value, #min, #max) }
+            }
+            Validator::Regex(pattern) => quote! {
+                {
+                    static PATTERN: _crud::validate::Pattern = _crud::validate::Pattern::new(#pattern);
+                    PATTERN.check(value)
+                }
+            },
+            Validator::Custom(path) => quote! { #path(value) },
+        }
+    }
+}
+
+fn option_tokens(value: Option<proc_macro2::TokenStream>) -> proc_macro2::TokenStream {
+    match value {
+        Some(v) => quote! { ::std::option::Option::Some(#v) },
+        None => quote! { ::std::option::Option::None },
+    }
+}
+
+/// `min = ..` and `max = ..` of `length(...)` and `range(...)`
+fn bounds<'a>(meta: &'a Meta, name: &str) -> syn::Result<(Option<&'a Lit>, Option<&'a Lit>)> {
+    let list = match meta {
+        Meta::List(list) if !list.nested.is_empty() => list,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                meta,
+                format!("expected `{name}(min = .., max = ..)`"),
+            ))
+        }
+    };
+
+    let (mut min, mut max) = (None, None);
+    for nested_meta in list.nested.iter() {
+        let (target, mnv) = match nested_meta {
+            NestedMeta::Meta(Meta::NameValue(mnv)) if mnv.path.is_ident(MIN) => (&mut min, mnv),
+            NestedMeta::Meta(Meta::NameValue(mnv)) if mnv.path.is_ident(MAX) => (&mut max, mnv),
+            NestedMeta::Meta(meta) => {
+                let ident = meta.path().get_ident().map(ToString::to_string);
+                return Err(unknown_keyword(
+                    meta.path(),
+                    &format!("`{name}` bound"),
+                    &ident.unwrap_or_default(),
+                    &[MIN, MAX],
+                ));
+            }
+            NestedMeta::Lit(lit) => {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    format!("expected `{name}(min = .., max = ..)`"),
+                ))
+            }
+        };
+        if target.is_some() {
+            return Err(syn::Error::new_spanned(nested_meta, "duplicated bound"));
+        }
+        *target = Some(&mnv.lit);
+    }
+
+    Ok((min, max))
+}
+
+fn length(meta: &Meta) -> syn::Result<Validator> {
+    let (min, max) = bounds(meta, LENGTH)?;
+    let parse = |lit: Option<&Lit>| match lit {
+        Some(Lit::Int(i)) => i.base10_parse::<usize>().map(Some),
+        Some(other) => Err(syn::Error::new_spanned(
+            other,
+            "a length bound must be a non-negative integer",
+        )),
+        None => Ok(None),
+    };
+    let (min, max) = (parse(min)?, parse(max)?);
+
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            return Err(syn::Error::new_spanned(
+                meta,
+                format!("`min` ({min}) is greater than `max` ({max})"),
+            ));
+        }
+    }
+
+    Ok(Validator::Length(min, max))
+}
+
+fn range(meta: &Meta) -> syn::Result<Validator> {
+    let (min, max) = bounds(meta, RANGE)?;
+    let parse = |lit: Option<&Lit>| match lit {
+        Some(lit @ (Lit::Int(_) | Lit::Float(_))) => Ok(Some(Box::new(syn::parse_quote!(#lit)))),
+        Some(Lit::Str(s)) => s.parse::<Expr>().map(|e| Some(Box::new(e))),
+        Some(other) => Err(syn::Error::new_spanned(
+            other,
+            "a range bound must be a number, e.g. `min = 0` or `min = \"-1.5\"`",
+        )),
+        None => Ok(None),
+    };
+
+    Ok(Validator::Range(parse(min)?, parse(max)?))
+}
+
+/// `name = "..."`
+fn lit_str<'a>(meta: &'a Meta, name: &str) -> syn::Result<&'a LitStr> {
+    match meta {
+        Meta::NameValue(mnv) => match &mnv.lit {
+            Lit::Str(s) => Ok(s),
+            other => Err(syn::Error::new_spanned(
+                other,
+                format!("expected `{name} = \"...\"`"),
+            )),
+        },
+        other => Err(syn::Error::new_spanned(
+            other,
+            format!("expected `{name} = \"...\"`"),
+        )),
+    }
+}
+
+fn regex(meta: &Meta) -> syn::Result<Validator> {
+    let lit = lit_str(meta, REGEX)?;
+    if let Err(e) = regex::Regex::new(&lit.value()) {
+        return Err(syn::Error::new_spanned(lit, format!("invalid regex: {e}")));
+    }
+
+    Ok(Validator::Regex(lit.clone()))
+}
+
+fn custom(meta: &Meta) -> syn::Result<Validator> {
+    let lit = lit_str(meta, CUSTOM)?;
+    let path = lit.parse::<Path>().map_err(|_| {
+        syn::Error::new_spanned(lit, format!("invalid function path `{}`", lit.value()))
+    })?;
+
+    Ok(Validator::Custom(path))
+}
+
+/// validators of a field
+fn field_validators(field: &Field, known: &[&str]) -> syn::Result<Vec<Validator>> {
+    let mut result = vec![];
+
+    for nested_meta in crud_metas(&field.attrs, known)? {
+        let list = match &nested_meta {
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident(VALIDATE) => list,
+            NestedMeta::Meta(meta) if meta.path().is_ident(VALIDATE) => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected `validate(...)`, e.g. `#[crud(validate(length(min = 1)))]`",
+                ))
+            }
+            _ => continue,
+        };
+
+        for v in list.nested.iter() {
+            let meta = match v {
+                NestedMeta::Meta(meta) => meta,
+                NestedMeta::Lit(lit) => {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        "expected a validator such as `length(min = 1)`",
+                    ))
+                }
+            };
+            let validator = match meta.path().get_ident().map(ToString::to_string) {
+                Some(v) if v == LENGTH => length(meta)?,
+                Some(v) if v == RANGE => range(meta)?,
+                Some(v) if v == REGEX => regex(meta)?,
+                Some(v) if v == CUSTOM => custom(meta)?,
+                other => {
+                    return Err(unknown_keyword(
+                        meta.path(),
+                        "validator",
+                        &other.unwrap_or_default(),
+                        VALIDATORS,
+                    ))
+                }
+            };
+            result.push(validator);
+        }
+    }
+
+    Ok(result)
+}
+
+/// The checks of a field's `value` into `errors`, recorded under `key`. `None` if the field
+/// is not validated.
+pub fn field_checks(
+    field: &Field,
+    known: &[&str],
+    key: proc_macro2::TokenStream,
+) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let validators = field_validators(field, known)?;
+    if validators.is_empty() {
+        return Ok(None);
+    }
+
+    let validators = validators.iter().map(Validator::to_tokens);
+    Ok(Some(quote! {
+        #( errors.check(#key, #validators); )*
+    }))
+}
+
+/// `validate` of `BaseCRUD`, nothing if no field is validated
+pub fn validate_tokens(
+    named_fields: &NamedFields,
+    known: &[&str],
+    rename_all: Option<RenameRule>,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut checks = vec![];
+
+    for field in named_fields.iter() {
+        let key = bson_name(field, rename_all);
+        let check = match field_checks(field, known, quote!(#key))? {
+            Some(check) => check,
+            None => continue,
+        };
+
+        let ident = &field.ident;
+
+        checks.push(match option_inner(&field.ty) {
+            Some(_) => quote! {
+                if let ::std::option::Option::Some(value) = &self.#ident {
+                    #check
+                }
+            },
+            None => quote! {
+                {
+                    let value = &self.#ident;
+                    #check
+                }
+            },
+        });
+    }
+
+    if checks.is_empty() {
+        return Ok(quote! {});
+    }
+
+    Ok(quote! {
+        fn validate(&self) -> ::std::result::Result<(), _crud::ValidationErrors> {
+            let mut errors = _crud::ValidationErrors::default();
+            #(#checks)*
+            errors.into_result()
+        }
+    })
+}
@@ -0,0 +1,100 @@
+//! Version
+//!
+//! Optimistic concurrency control, `MongoCRUD::update` only writes a document that is still at
+//! the version carried by the value, and increments it:
+//!
+//! ```rust,ignore
+//! struct TestCrud {
+//!     #[crud(version)]
+//!     version: i32,
+//!     ...
+//! }
+//! ```
+
+use quote::quote;
+use syn::{Field, Meta, NestedMeta, Type};
+
+use crate::attrs::crud_metas;
+use crate::rename::{bson_name, RenameRule};
+use crate::NamedFields;
+
+pub const VERSION: &str = "version";
+
+/// integer types accepted by a version field
+const VERSION_TYPES: &[&str] = &["i32", "i64", "u32", "u64"];
+
+/// the `#[crud(version)]` field
+pub fn version_field<'a>(
+    named_fields: &'a NamedFields,
+    known: &[&str],
+) -> syn::Result<Option<&'a Field>> {
+    let mut result: Option<&Field> = None;
+
+    for field in named_fields.iter() {
+        for nested_meta in crud_metas(&field.attrs, known)? {
+            match &nested_meta {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident(VERSION) => {}
+                NestedMeta::Meta(meta) if meta.path().is_ident(VERSION) => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "`version` takes no value, write `#[crud(version)]`",
+                    ))
+                }
+                _ => continue,
+            }
+
+            if result.is_some() {
+                return Err(syn::Error::new_spanned(
+                    nested_meta,
+                    "duplicated `#[crud(version)]`, only one field can be the version",
+                ));
+            }
+            let is_integer = matches!(
+                &field.ty,
+                Type::Path(tp) if VERSION_TYPES.iter().any(|t| tp.path.is_ident(t))
+            );
+            if !is_integer {
+                return Err(syn::Error::new_spanned(
+                    &field.ty,
+                    "the version field must be an integer\n\
+                     help: use one of `i32`, `i64`, `u32` or `u64`",
+                ));
+            }
+            result = Some(field);
+        }
+    }
+
+    Ok(result)
+}
+
+/// `version_key`, `get_version` and `set_version` of `BaseCRUD`
+pub fn version_tokens(
+    field: Option<&Field>,
+    rename_all: Option<RenameRule>,
+) -> proc_macro2::TokenStream {
+    let field = match field {
+        Some(f) => f,
+        None => return quote! {},
+    };
+    let ident = &field.ident;
+    let ty = &field.ty;
+    let name = bson_name(field, rename_all);
+
+    quote! {
+        fn version_key() -> ::std::option::Option<&'static str> {
+            ::std::option::Option::Some(#name)
+        }
+
+        fn get_version(&self) -> ::std::option::Option<i64> {
+            <i64 as ::std::convert::TryFrom<#ty>>::try_from(self.#ident).ok()
+        }
+
+        fn set_version(&mut self, version: i64) {
+            if let ::std::result::Result::Ok(v) =
+                <#ty as ::std::convert::TryFrom<i64>>::try_from(version)
+            {
+                self.#ident = v;
+            }
+        }
+    }
+}
@@ -0,0 +1,577 @@
+//! Bulk
+//!
+//! Many writes by a few round trips, for imports and batch jobs:
+//!
+//! ```rust,ignore
+//! let result = client.create_many(companies, false).await?;
+//! println!("{} created, {} failed", result.inserted_ids.len(), result.errors.len());
+//!
+//! let result = client
+//!     .bulk_write(
+//!         vec![
+//!             WriteModel::upsert(company, &Company::fields().name()),
+//!             WriteModel::update_many(fields.group().eq("A"), CompanyPatch { .. })?,
+//!             WriteModel::Delete(id),
+//!         ],
+//!         true,
+//!     )
+//!     .await?;
+//! ```
+//!
+//! Consecutive operations of the same kind are sent as one `insert`, `update` or `delete`
+//! command. Ordered operations stop at the first failure, unordered ones go on with the
+//! others. An operation failing before it is sent, e.g. by its hook or its validation, or
+//! failing in MongoDB, e.g. by a duplicated key, is reported by `BulkResult::errors`. An error
+//! of a whole command is returned as it is.
+//!
+//! Inserts, updates and deletes call the hooks of `create`, `update` and `delete`. An
+//! `Update` is sent alone, so that it fails with `CrudError::Conflict` or `CrudError::NotFound`
+//! as `update` does, when it matches nothing. The documents to be deleted are read first, so
+//! that `after_delete` is called with each of them. An upsert reads the document of its
+//! natural key first, and calls the hooks of `update` if there is one, those of `create`
+//! otherwise. `UpdateMany` calls no hook.
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use bson::{doc, Bson, Document};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio_stream::StreamExt;
+
+use crate::encrypt::EncryptMode;
+use crate::errors::CrudError;
+use crate::filter::{FieldPath, Filter};
+use crate::hooks::Hooks;
+use crate::page::value_at;
+use crate::patch::{validated_update, Patch, PatchUpdate};
+use crate::persistence::{
+    created_document, decode, encode, id_filter, patched_update, scoped_filter,
+    soft_deleted_update, type_filter, unmatched_update, updated_statement, Scope,
+};
+use crate::{BaseCRUD, MongoClient, MongoClientAbstraction};
+
+/// statements of a command, at most
+const MAX_STATEMENTS: usize = 1000;
+/// bytes of the statements of a command, at most, below the 16MB of a BSON document
+const MAX_BYTES: usize = 8 * 1024 * 1024;
+
+/// A write operation of `MongoCRUD::bulk_write`
+pub enum WriteModel<T: BaseCRUD> {
+    /// `MongoCRUD::create`
+    Insert(T),
+    /// `MongoCRUD::update`, by the value's id and at its version
+    Update(T),
+    /// update the document whose `key` is the value's, or insert the value, see
+    /// `WriteModel::upsert`
+    Upsert { value: T, key: String },
+    /// update the fields of a patch in every document matching the filter, see
+    /// `WriteModel::update_many`
+    UpdateMany {
+        filter: Filter<T>,
+        update: PatchUpdate,
+    },
+    /// `MongoCRUD::delete`
+    Delete(T::Id),
+    /// `MongoCRUD::delete` of every document matching the filter
+    DeleteMany(Filter<T>),
+}
+
+impl<T: BaseCRUD> WriteModel<T> {
+    /// Upsert by a natural key, e.g. `&Company::fields().name()`. The id, `#[crud(created_at)]`
+    /// and the soft delete field are only written by an insert. `#[crud(version)]` is
+    /// incremented by an update, an inserted document is at the value's version, as `create`
+    /// does.
+    pub fn upsert<V>(value: T, key: &FieldPath<T, V>) -> Self {
+        WriteModel::Upsert {
+            value,
+            key: key.path().to_owned(),
+        }
+    }
+
+    /// Update the fields present in `patch`, in every document matching the filter
+    pub fn update_many<P: Patch<Target = T>>(filter: Filter<T>, patch: P) -> Result<Self> {
+        let update = validated_update(&patch)?;
+
+        Ok(WriteModel::UpdateMany { filter, update })
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            WriteModel::Insert(_) => Kind::Insert,
+            WriteModel::Update(_) => Kind::UpdateOne,
+            WriteModel::Upsert { .. } | WriteModel::UpdateMany { .. } => Kind::Update,
+            WriteModel::Delete(_) | WriteModel::DeleteMany(_) => match T::soft_delete_key() {
+                Some(_) => Kind::SoftDelete,
+                None => Kind::Delete,
+            },
+        }
+    }
+}
+
+/// An operation that failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkError {
+    /// index of the operation
+    pub index: usize,
+    /// MongoDB's error code, e.g. `11000` of a duplicated key, `None` if the operation was not
+    /// sent, or if an update by id matched nothing
+    pub code: Option<i32>,
+    pub message: String,
+}
+
+/// What a bulk write did
+#[derive(Debug, Clone, PartialEq)]
+pub struct BulkResult<Id> {
+    /// ids of the inserted documents, by the index of their operations
+    pub inserted_ids: BTreeMap<usize, Id>,
+    /// ids of the documents inserted by upserts, by the index of their operations
+    pub upserted_ids: BTreeMap<usize, Id>,
+    /// documents matched by updates and upserts
+    pub matched: u64,
+    /// documents actually changed by updates and upserts
+    pub modified: u64,
+    /// documents deleted, or soft-deleted
+    pub deleted: u64,
+    /// the operations that failed
+    pub errors: Vec<BulkError>,
+}
+
+impl<Id> Default for BulkResult<Id> {
+    fn default() -> Self {
+        BulkResult {
+            inserted_ids: BTreeMap::new(),
+            upserted_ids: BTreeMap::new(),
+            matched: 0,
+            modified: 0,
+            deleted: 0,
+            errors: vec![],
+        }
+    }
+}
+
+/// the command of a statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Insert,
+    /// an update by id, whose match is checked
+    UpdateOne,
+    Update,
+    SoftDelete,
+    Delete,
+}
+
+impl Kind {
+    /// name of the command, and key of its statements
+    fn command(self) -> (&'static str, &'static str) {
+        match self {
+            Kind::Insert => ("insert", "documents"),
+            Kind::UpdateOne | Kind::Update | Kind::SoftDelete => ("update", "updates"),
+            Kind::Delete => ("delete", "deletes"),
+        }
+    }
+
+    /// statements of a command, at most. An update by id is alone, the count of its command
+    /// tells whether it matched.
+    fn max_statements(self) -> usize {
+        match self {
+            Kind::UpdateOne => 1,
+            _ => MAX_STATEMENTS,
+        }
+    }
+}
+
+/// a statement of a command, and what is done once it is written
+struct Statement<T> {
+    index: usize,
+    document: Document,
+    written: Written<T>,
+}
+
+enum Written<T> {
+    Created(T),
+    /// updated by id, at the version of `version_key`
+    Updated(T, Option<(&'static str, i64)>),
+    /// upserted, created if its index is in `BulkResult::upserted_ids`, updated otherwise
+    Upserted(T),
+    Deleted(Vec<T>),
+    Nothing,
+}
+
+/// `MongoCRUD::bulk_write`
+pub(crate) async fn bulk_write<T>(
+    client: &MongoClient,
+    operations: Vec<WriteModel<T>>,
+    ordered: bool,
+) -> Result<BulkResult<T::Id>>
+where
+    T: Hooks + Clone + Serialize + DeserializeOwned,
+{
+    let mut result = BulkResult::default();
+    let mut batch: Vec<Statement<T>> = vec![];
+    let mut batch_kind = Kind::Insert;
+    let mut batch_bytes = 0;
+
+    for (index, operation) in operations.into_iter().enumerate() {
+        // the previous operations are written before a statement of another kind is prepared,
+        // e.g. the documents to be deleted are read after the inserts before them
+        let kind = operation.kind();
+        if kind != batch_kind && !batch.is_empty() {
+            let statements = std::mem::take(&mut batch);
+            if !execute(client, batch_kind, statements, ordered, &mut result).await? {
+                return Ok(result);
+            }
+            batch_bytes = 0;
+        }
+        batch_kind = kind;
+
+        let statement = match prepare(client, index, operation).await {
+            Ok(statement) => statement,
+            Err(e) => {
+                if ordered {
+                    execute(client, batch_kind, batch, ordered, &mut result).await?;
+                    if result.errors.is_empty() {
+                        result.errors.push(unsent(index, &e));
+                    }
+                    return Ok(result);
+                }
+                result.errors.push(unsent(index, &e));
+                continue;
+            }
+        };
+
+        let bytes = bson::to_vec(&statement.document)?.len();
+        if batch.len() >= batch_kind.max_statements() || batch_bytes + bytes > MAX_BYTES {
+            let statements = std::mem::take(&mut batch);
+            if !execute(client, batch_kind, statements, ordered, &mut result).await? {
+                return Ok(result);
+            }
+            batch_bytes = 0;
+        }
+        batch_bytes += bytes;
+        batch.push(statement);
+    }
+    execute(client, batch_kind, batch, ordered, &mut result).await?;
+
+    Ok(result)
+}
+
+/// an operation failed before it was sent
+fn unsent(index: usize, error: &anyhow::Error) -> BulkError {
+    BulkError {
+        index,
+        code: None,
+        message: error.to_string(),
+    }
+}
+
+/// the statement of an operation
+async fn prepare<T>(
+    client: &MongoClient,
+    index: usize,
+    operation: WriteModel<T>,
+) -> Result<Statement<T>>
+where
+    T: Hooks + Clone + Serialize + DeserializeOwned,
+{
+    let (document, written) = match operation {
+        WriteModel::Insert(mut value) => {
+            let document = created_document(client, &mut value).await?;
+            (document, Written::Created(value))
+        }
+        WriteModel::Update(mut value) => {
+            let (filter, update) = updated_statement(client, &mut value).await?;
+            let document = doc! { "q": filter, "u": update, "multi": false };
+            let version = T::version_key().zip(value.get_version());
+            (document, Written::Updated(value, version))
+        }
+        WriteModel::Upsert { value, key } => upsert_statement(client, value, &key).await?,
+        WriteModel::UpdateMany { filter, update } => {
+            let filter = client.encrypt_filter(T::encrypted_fields(), filter.into_document()?)?;
+            let update = patched_update::<T>(client, update)?;
+            let document = doc! { "q": scoped_filter::<T>(filter), "u": update, "multi": true };
+            (document, Written::Nothing)
+        }
+        WriteModel::Delete(id) => {
+            T::before_delete(&id, client).await?;
+            delete_statement(client, id_filter(&id)?, false).await?
+        }
+        WriteModel::DeleteMany(filter) => {
+            let filter = client.encrypt_filter(T::encrypted_fields(), filter.into_document()?)?;
+            let (document, written) = delete_statement(client, filter, true).await?;
+            if let Written::Deleted(values) = &written {
+                for id in values.iter().filter_map(BaseCRUD::get_id) {
+                    T::before_delete(&id, client).await?;
+                }
+            }
+            (document, written)
+        }
+    };
+
+    Ok(Statement {
+        index,
+        document,
+        written,
+    })
+}
+
+/// `{ q, u, upsert: true }` of a value, by its natural key. The value is hooked as an update if
+/// a document has its key, as a create otherwise.
+async fn upsert_statement<T>(
+    client: &MongoClient,
+    value: T,
+    key: &str,
+) -> Result<(Document, Written<T>)>
+where
+    T: Hooks + Clone + Serialize + DeserializeOwned,
+{
+    let random = T::encrypted_fields()
+        .iter()
+        .any(|f| f.key == key && f.mode == EncryptMode::Random);
+    if random {
+        return Err(anyhow!(
+            "`{}` is randomly encrypted and cannot be a key, use `encrypt(deterministic)`!",
+            key
+        ));
+    }
+
+    // the key as the document would be updated, e.g. trimmed by `before_update`
+    let mut updated = value.clone();
+    updated.before_update(client).await?;
+    let filter = scoped_filter::<T>(doc! { key: natural_key(client, &updated, key)? });
+    let existing = client.session_schema::<T>().find_one(filter, None).await?;
+    let (mut value, exists) = match existing.as_ref().and_then(|d| d.get("_id")) {
+        Some(id) => {
+            updated.mutate_id(bson::from_bson(id.clone())?)?;
+            (updated, true)
+        }
+        None => {
+            let mut value = value;
+            value.before_create(client).await?;
+            (value, false)
+        }
+    };
+    value.validate().map_err(CrudError::Validation)?;
+
+    if value.get_id().is_none() {
+        let id = T::new_id().ok_or_else(|| {
+            anyhow!(
+                "No `id` was given, and `{}` cannot be generated!",
+                std::any::type_name::<T::Id>()
+            )
+        })?;
+        value.mutate_id(id)?;
+    }
+    let now = client.now();
+    if !exists {
+        value.stamp_created(now);
+    }
+    value.stamp_updated(now);
+
+    let mut set = encode(client, &value)?;
+    let natural = value_at(&set, key).cloned().unwrap_or(Bson::Null);
+    let mut insert = doc! { "_id": set.remove("_id").unwrap_or(Bson::Null) };
+    if let Some(key) = T::created_at_key() {
+        insert.insert(key, set.remove(key).unwrap_or(Bson::Null));
+    }
+    if let Some(key) = T::soft_delete_key() {
+        set.remove(key);
+        insert.insert(key, Bson::Null);
+    }
+    if let Some(key) = T::version_key() {
+        let version = set.remove(key).unwrap_or(Bson::Int64(0));
+        if !exists {
+            insert.insert(key, version);
+        }
+    }
+    if let Some((key, discriminator)) = T::discriminator() {
+        set.insert(key, discriminator);
+    }
+    let mut update = doc! { "$set": set, "$setOnInsert": insert };
+    if let (Some(key), true) = (T::version_key(), exists) {
+        update.insert("$inc", doc! { key: 1 });
+    }
+
+    let filter = scoped_filter::<T>(doc! { key: natural });
+    let document = doc! { "q": filter, "u": update, "upsert": true, "multi": false };
+    Ok((document, Written::Upserted(value)))
+}
+
+/// the value of the natural key of a value, as stored
+fn natural_key<T: BaseCRUD + Serialize>(
+    client: &MongoClient,
+    value: &T,
+    key: &str,
+) -> Result<Bson> {
+    let document = encode(client, value)?;
+    Ok(value_at(&document, key).cloned().unwrap_or(Bson::Null))
+}
+
+/// `{ q, limit }` of a delete, or `{ q, u }` of a soft delete, along with the documents to be
+/// deleted
+async fn delete_statement<T>(
+    client: &MongoClient,
+    filter: Document,
+    many: bool,
+) -> Result<(Document, Written<T>)>
+where
+    T: BaseCRUD + DeserializeOwned,
+{
+    let (filter, document) = match T::soft_delete_key() {
+        Some(key) => {
+            let filter = scoped_filter::<T>(filter);
+            let update = soft_deleted_update::<T>(key, client.now());
+            let document = doc! { "q": filter.clone(), "u": update, "multi": many };
+            (filter, document)
+        }
+        None => {
+            let filter = type_filter::<T>(filter);
+            let limit = if many { 0 } else { 1 };
+            let document = doc! { "q": filter.clone(), "limit": limit };
+            (filter, document)
+        }
+    };
+    let deleted = client
+        .session_schema::<T>()
+        .find(filter, None)
+        .await?
+        .map(|v| decode::<T>(client, v?))
+        .collect::<Result<Vec<_>>>()
+        .await?;
+
+    Ok((document, Written::Deleted(deleted)))
+}
+
+/// Send the statements as a command, and add its results. `false` if ordered statements
+/// failed, the following ones are not to be sent.
+async fn execute<T>(
+    client: &MongoClient,
+    kind: Kind,
+    statements: Vec<Statement<T>>,
+    ordered: bool,
+    result: &mut BulkResult<T::Id>,
+) -> Result<bool>
+where
+    T: Hooks + Clone + Serialize + DeserializeOwned,
+{
+    if statements.is_empty() {
+        return Ok(true);
+    }
+    let scope = Scope::of::<T>();
+    let collection = scope.collection.unwrap_or(&client.collection);
+    let (command, key) = kind.command();
+    let documents = statements
+        .iter()
+        .map(|s| s.document.clone())
+        .collect::<Vec<_>>();
+    let reply = client
+        .session_command(
+            client.scoped_database(&scope),
+            doc! { command: collection, key: documents, "ordered": ordered },
+        )
+        .await?;
+
+    if let Ok(error) = reply.get_document("writeConcernError") {
+        return Err(anyhow!(
+            "Write concern error: {}",
+            error.get_str("errmsg").unwrap_or_default()
+        ));
+    }
+    let mut errors = BTreeMap::new();
+    for error in reply.get_array("writeErrors").into_iter().flatten() {
+        if let Bson::Document(error) = error {
+            let i = count(error, "index") as usize;
+            errors.insert(
+                i,
+                BulkError {
+                    index: statements.get(i).map_or(i, |s| s.index),
+                    code: error.get_i32("code").ok(),
+                    message: error.get_str("errmsg").unwrap_or_default().to_owned(),
+                },
+            );
+        }
+    }
+    let mut upserted = 0;
+    for document in reply.get_array("upserted").into_iter().flatten() {
+        if let Bson::Document(document) = document {
+            let i = count(document, "index") as usize;
+            if let (Some(statement), Some(id)) = (statements.get(i), document.get("_id")) {
+                result
+                    .upserted_ids
+                    .insert(statement.index, bson::from_bson(id.clone())?);
+            }
+            upserted += 1;
+        }
+    }
+    let n = count(&reply, "n");
+    match kind {
+        Kind::Insert => {}
+        Kind::UpdateOne | Kind::Update => {
+            result.matched += n.saturating_sub(upserted);
+            result.modified += count(&reply, "nModified");
+        }
+        Kind::SoftDelete | Kind::Delete => result.deleted += n,
+    }
+
+    // ordered statements after the first failure were not written
+    let first_error = errors.keys().next().copied();
+    for (i, statement) in statements.into_iter().enumerate() {
+        if let Some(error) = errors.remove(&i) {
+            result.errors.push(error);
+            continue;
+        }
+        if ordered && first_error.is_some_and(|e| i > e) {
+            break;
+        }
+        match statement.written {
+            Written::Created(value) => {
+                if let Some(id) = value.get_id() {
+                    result.inserted_ids.insert(statement.index, id);
+                }
+                value.after_create(client).await?;
+            }
+            Written::Updated(mut value, version) => {
+                // alone in its command, see `Kind::max_statements`
+                if n == 0 {
+                    let id = value
+                        .get_id()
+                        .ok_or_else(|| anyhow!("No `id` field was found!"))?;
+                    let error = unmatched_update::<T>(client, &id, version).await?;
+                    result.errors.push(BulkError {
+                        index: statement.index,
+                        code: None,
+                        message: error.to_string(),
+                    });
+                    return Ok(!ordered);
+                }
+                if let Some((_, expected)) = version {
+                    value.set_version(expected + 1);
+                }
+                value.after_update(client).await?;
+            }
+            Written::Upserted(value) => match result.upserted_ids.contains_key(&statement.index) {
+                true => value.after_create(client).await?,
+                false => value.after_update(client).await?,
+            },
+            Written::Deleted(values) => {
+                for value in values {
+                    match kind {
+                        Kind::Delete => value.after_purge(client).await?,
+                        _ => value.after_delete(client).await?,
+                    }
+                }
+            }
+            Written::Nothing => {}
+        }
+    }
+
+    Ok(!(ordered && first_error.is_some()))
+}
+
+/// a count of a reply, `0` if missing
+fn count(document: &Document, key: &str) -> u64 {
+    match document.get(key) {
+        Some(Bson::Int32(v)) => *v as u64,
+        Some(Bson::Int64(v)) => *v as u64,
+        _ => 0,
+    }
+}
@@ -0,0 +1,44 @@
+//! Clock
+//!
+//! Source of the time used by `#[crud(created_at)]` and `#[crud(updated_at)]`. `MongoClient`
+//! uses `SystemClock` unless another one is set, tests can use `FixedClock` to be deterministic.
+
+use std::sync::Mutex;
+
+use bson::DateTime;
+
+/// Current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime;
+}
+
+/// The system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime {
+        DateTime::now()
+    }
+}
+
+/// A clock that always returns the same time, until it is set to another one
+#[derive(Debug)]
+pub struct FixedClock(Mutex<DateTime>);
+
+impl FixedClock {
+    pub fn new(now: DateTime) -> Self {
+        FixedClock(Mutex::new(now))
+    }
+
+    /// set the time returned from now on
+    pub fn set(&self, now: DateTime) {
+        *self.0.lock().unwrap() = now;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime {
+        *self.0.lock().unwrap()
+    }
+}
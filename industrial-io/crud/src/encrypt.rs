@@ -0,0 +1,346 @@
+//! Encrypt
+//!
+//! Client-side encryption of fields, by AES-256-GCM. A field marked by `#[crud(encrypt)]` is
+//! encrypted by `MongoCRUD` before it is written, and decrypted after it is read, so MongoDB and
+//! its backups only hold ciphertext:
+//!
+//! ```rust,ignore
+//! struct Company {
+//!     #[crud(encrypt)]
+//!     data: Option<JsonValue>,            // random, every write is a new ciphertext
+//!     #[crud(encrypt(deterministic), single_index = "unique")]
+//!     code: String,                       // deterministic, equal values are equal ciphertexts
+//!     ...
+//! }
+//!
+//! client.set_key_provider(Arc::new(LocalKeyFile::open("crud.key")?));
+//! ```
+//!
+//! A deterministic field can be queried by equality, its values in filters of `read_by_filter`,
+//! `find` and `count_by_filter` are encrypted as well. A random field cannot be queried. `null`
+//! is stored as it is, so is any field not marked.
+//!
+//! A value which is not an encrypted binary is read as it is, e.g. a document written before
+//! its field was marked. It is encrypted by its next write, and until then it is not found by
+//! an equality query.
+//!
+//! Each value is stored as an encrypted binary: the mode, the 12-byte nonce, and the ciphertext
+//! of the value with its tag. The field's key is authenticated along, a value copied to another
+//! field cannot be decrypted. The nonce of a deterministic value is derived from the value by
+//! HMAC-SHA256.
+
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::{AeadCore, Aes256Gcm, Nonce};
+use anyhow::{anyhow, Result};
+use bson::{doc, spec::BinarySubtype, Binary, Bson, Document};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::MongoClient;
+
+const NONCE_LEN: usize = 12;
+/// info of the key deriving the nonces of deterministic values
+const DETERMINISTIC_INFO: &[u8] = b"crud deterministic nonce";
+
+/// How a field is encrypted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptMode {
+    /// a random nonce, equal values are different ciphertexts
+    Random,
+    /// a nonce derived from the value, so that equality queries and unique indexes work.
+    /// Equal values can be told apart from different ones.
+    Deterministic,
+}
+
+impl EncryptMode {
+    fn to_byte(self) -> u8 {
+        match self {
+            EncryptMode::Random => 1,
+            EncryptMode::Deterministic => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(EncryptMode::Random),
+            2 => Some(EncryptMode::Deterministic),
+            _ => None,
+        }
+    }
+}
+
+/// A field marked by `#[crud(encrypt)]`, see `BaseCRUD::encrypted_fields`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncryptedField {
+    /// stored name of the field
+    pub key: &'static str,
+    pub mode: EncryptMode,
+}
+
+/// Where the data key comes from, set by `MongoClient::set_key_provider`
+pub trait KeyProvider: Send + Sync {
+    /// the 256-bit data key
+    fn key(&self) -> Result<[u8; 32]>;
+}
+
+/// A data key kept in a local file, as base64
+pub struct LocalKeyFile {
+    key: [u8; 32],
+}
+
+impl LocalKeyFile {
+    /// Read the key of a file written by `LocalKeyFile::create`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+        let key = base64::decode(text.trim())?
+            .try_into()
+            .map_err(|_| anyhow!("`{}` is not a 256-bit key!", path.as_ref().display()))?;
+
+        Ok(LocalKeyFile { key })
+    }
+
+    /// Generate a new key and write it to a file, which must not exist
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let key: [u8; 32] = Aes256Gcm::generate_key(&mut OsRng).into();
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        std::io::Write::write_all(&mut file, base64::encode(key).as_bytes())?;
+
+        Ok(LocalKeyFile { key })
+    }
+}
+
+impl KeyProvider for LocalKeyFile {
+    fn key(&self) -> Result<[u8; 32]> {
+        Ok(self.key)
+    }
+}
+
+/// AES-256-GCM with the key of a `KeyProvider`
+struct Cipher {
+    aes: Aes256Gcm,
+    key: [u8; 32],
+}
+
+impl Cipher {
+    fn new(provider: &dyn KeyProvider) -> Result<Self> {
+        let key = provider.key()?;
+        Ok(Cipher {
+            aes: Aes256Gcm::new(&key.into()),
+            key,
+        })
+    }
+
+    /// HMAC-SHA256 of the value, by a key derived from the data key
+    fn synthetic_nonce(&self, field: &str, plaintext: &[u8]) -> Result<[u8; NONCE_LEN]> {
+        let mut derive = <Hmac<Sha256> as Mac>::new_from_slice(&self.key)?;
+        derive.update(DETERMINISTIC_INFO);
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&derive.finalize().into_bytes())?;
+        mac.update(field.as_bytes());
+        mac.update(&[0]);
+        mac.update(plaintext);
+
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(&mac.finalize().into_bytes()[..NONCE_LEN]);
+        Ok(nonce)
+    }
+
+    fn encrypt(&self, field: &str, mode: EncryptMode, value: Bson) -> Result<Bson> {
+        let mut plaintext = vec![];
+        doc! { "v": value }.to_writer(&mut plaintext)?;
+        let nonce = match mode {
+            EncryptMode::Random => Aes256Gcm::generate_nonce(&mut OsRng).into(),
+            EncryptMode::Deterministic => self.synthetic_nonce(field, &plaintext)?,
+        };
+        let payload = Payload {
+            msg: &plaintext,
+            aad: field.as_bytes(),
+        };
+        let ciphertext = self
+            .aes
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|_| anyhow!("Failed to encrypt `{}`!", field))?;
+
+        let mut bytes = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        bytes.push(mode.to_byte());
+        bytes.extend_from_slice(&nonce);
+        bytes.extend(ciphertext);
+        Ok(Bson::Binary(Binary {
+            subtype: BinarySubtype::Encrypted,
+            bytes,
+        }))
+    }
+
+    fn decrypt(&self, field: &str, value: &Binary) -> Result<Bson> {
+        let bytes = &value.bytes;
+        if value.subtype != BinarySubtype::Encrypted
+            || bytes.len() <= 1 + NONCE_LEN
+            || EncryptMode::from_byte(bytes[0]).is_none()
+        {
+            return Err(anyhow!("`{}` is not an encrypted value!", field));
+        }
+        let payload = Payload {
+            msg: &bytes[1 + NONCE_LEN..],
+            aad: field.as_bytes(),
+        };
+        let plaintext = self
+            .aes
+            .decrypt(Nonce::from_slice(&bytes[1..1 + NONCE_LEN]), payload)
+            .map_err(|_| {
+                anyhow!(
+                    "Failed to decrypt `{}`, wrong key or tampered value!",
+                    field
+                )
+            })?;
+
+        let mut document = Document::from_reader(plaintext.as_slice())?;
+        document
+            .remove("v")
+            .ok_or_else(|| anyhow!("`{}` is not an encrypted value!", field))
+    }
+}
+
+impl MongoClient {
+    fn cipher(&self, fields: &[EncryptedField]) -> Result<Option<Cipher>> {
+        if fields.is_empty() {
+            return Ok(None);
+        }
+        let provider = self.key_provider().ok_or_else(|| {
+            anyhow!("Encrypted fields need a key provider, see `set_key_provider`!")
+        })?;
+
+        Cipher::new(provider).map(Some)
+    }
+
+    /// Encrypt the fields of a document to be written, or the `$set` of an update
+    pub(crate) fn encrypt_document(
+        &self,
+        fields: &[EncryptedField],
+        document: &mut Document,
+    ) -> Result<()> {
+        let cipher = match self.cipher(fields)? {
+            Some(cipher) => cipher,
+            None => return Ok(()),
+        };
+        for field in fields {
+            if let Some(value) = document.get_mut(field.key) {
+                if *value != Bson::Null {
+                    *value = cipher.encrypt(field.key, field.mode, value.clone())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decrypt the fields of a document read, a value not encrypted is kept as it is
+    pub(crate) fn decrypt_document(
+        &self,
+        fields: &[EncryptedField],
+        document: &mut Document,
+    ) -> Result<()> {
+        let cipher = match self.cipher(fields)? {
+            Some(cipher) => cipher,
+            None => return Ok(()),
+        };
+        for field in fields {
+            if let Some(value) = document.get_mut(field.key) {
+                if let Bson::Binary(binary) = value {
+                    if binary.subtype == BinarySubtype::Encrypted {
+                        *value = cipher.decrypt(field.key, binary)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Encrypt the values compared to the deterministic fields of a filter, by `$eq`, `$ne`,
+    /// `$in` and `$nin`. Any other comparison of an encrypted field is an error.
+    pub(crate) fn encrypt_filter(
+        &self,
+        fields: &[EncryptedField],
+        filter: Document,
+    ) -> Result<Document> {
+        match self.cipher(fields)? {
+            Some(cipher) => encrypt_filter(&cipher, fields, filter),
+            None => Ok(filter),
+        }
+    }
+}
+
+fn encrypt_filter(
+    cipher: &Cipher,
+    fields: &[EncryptedField],
+    filter: Document,
+) -> Result<Document> {
+    let mut result = Document::new();
+
+    for (key, value) in filter {
+        let value = match (key.as_str(), fields.iter().find(|f| f.key == key)) {
+            ("$and" | "$or" | "$nor", _) => match value {
+                Bson::Array(filters) => Bson::Array(
+                    filters
+                        .into_iter()
+                        .map(|f| match f {
+                            Bson::Document(d) => encrypt_filter(cipher, fields, d).map(Bson::from),
+                            other => Ok(other),
+                        })
+                        .collect::<Result<_>>()?,
+                ),
+                other => other,
+            },
+            (_, Some(field)) => encrypt_condition(cipher, field, value)?,
+            _ => value,
+        };
+        result.insert(key, value);
+    }
+
+    Ok(result)
+}
+
+/// `value` or `{ $op: value }` of an encrypted field
+fn encrypt_condition(cipher: &Cipher, field: &EncryptedField, value: Bson) -> Result<Bson> {
+    let encrypt = |value: Bson| match (value, field.mode) {
+        (Bson::Null, _) => Ok(Bson::Null),
+        (value, EncryptMode::Deterministic) => cipher.encrypt(field.key, field.mode, value),
+        (_, EncryptMode::Random) => Err(anyhow!(
+            "`{}` is randomly encrypted and cannot be queried, use `encrypt(deterministic)`!",
+            field.key
+        )),
+    };
+
+    let operators = match value {
+        Bson::Document(d) if d.keys().any(|k| k.starts_with('$')) => d,
+        value => return encrypt(value),
+    };
+    let mut result = Document::new();
+    for (op, value) in operators {
+        let value = match (op.as_str(), value) {
+            ("$eq" | "$ne", value) => encrypt(value)?,
+            ("$in" | "$nin", Bson::Array(values)) => Bson::Array(
+                values
+                    .into_iter()
+                    .map(encrypt)
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            // the stored value is always binary, `$type` would never match
+            ("$exists", value) => value,
+            (op, _) => {
+                return Err(anyhow!(
+                    "`{}` is encrypted, `{}` is not supported on it!",
+                    field.key,
+                    op
+                ))
+            }
+        };
+        result.insert(op, value);
+    }
+
+    Ok(Bson::Document(result))
+}
@@ -0,0 +1,26 @@
+//! Error handling
+//!
+//! `MongoCRUD` returns `anyhow::Result`, errors that callers may want to handle are
+//! `CrudError`s, e.g. `err.downcast_ref::<CrudError>()`.
+
+use thiserror::Error;
+
+use crate::validate::ValidationErrors;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CrudError {
+    #[error("Document not found")]
+    NotFound,
+
+    #[error("Version conflict: expected version {expected}, but the current version is {current}")]
+    Conflict { expected: i64, current: i64 },
+
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(String),
+
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("Validation failed: {0}")]
+    Validation(ValidationErrors),
+}
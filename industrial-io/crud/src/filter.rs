@@ -0,0 +1,241 @@
+//! Filter
+//!
+//! Typed filters. `#[derive(CRUD)]` generates a `<Name>Fields` struct, whose methods are the
+//! paths of the fields, keyed by their stored names:
+//!
+//! ```rust,ignore
+//! let fields = Company::fields();
+//! let filter = fields
+//!     .name()
+//!     .eq("x")
+//!     .and(fields.option().position().ne((0, 0)));  // `option.position`, a `#[crud(nested)]`
+//! let companies = client.find(filter).await?;
+//! ```
+//!
+//! A misspelled field is a compile error, and so is a value of another type.
+
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use bson::{doc, Bson, Document};
+use serde::Serialize;
+
+use crate::errors::CrudError;
+
+/// A type whose fields have typed paths.
+/// Implemented by `#[derive(CRUD)]`, or by `#[derive(Patchable)]` for types nested in a
+/// CRUD struct by `#[crud(nested)]`.
+pub trait Fields {
+    /// paths of the fields, in filters on `Root`
+    type Paths<Root>;
+
+    /// paths of the fields, prefixed by `prefix`
+    fn paths<Root>(prefix: String) -> Self::Paths<Root>;
+
+    /// paths of the fields, in filters on `Self`
+    fn fields() -> Self::Paths<Self>
+    where
+        Self: Sized,
+    {
+        Self::paths(String::new())
+    }
+}
+
+/// The path of a field of type `V`, in filters on `Root`.
+/// The path of an `Option<V>` field is a `FieldPath<Root, V>` as well.
+pub struct FieldPath<Root, V> {
+    path: String,
+    marker: PhantomData<fn() -> (Root, V)>,
+}
+
+impl<Root, V> FieldPath<Root, V> {
+    pub fn new(path: String) -> Self {
+        FieldPath {
+            path,
+            marker: PhantomData,
+        }
+    }
+
+    /// the dotted path, e.g. `option.position`
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// `{ path: { $exists: exists } }`
+    pub fn exists(&self, exists: bool) -> Filter<Root> {
+        Filter::new(doc! { &self.path: { "$exists": exists } })
+    }
+
+    /// `{ path: null }`, a `None` or missing field
+    pub fn is_null(&self) -> Filter<Root> {
+        Filter::new(doc! { &self.path: Bson::Null })
+    }
+}
+
+impl<Root, V: Serialize> FieldPath<Root, V> {
+    fn compare(&self, op: &str, value: V) -> Filter<Root> {
+        Filter::from_result(bson::to_bson(&value).map(|v| doc! { &self.path: { op: v } }))
+    }
+
+    fn compare_many<I>(&self, op: &str, values: I) -> Filter<Root>
+    where
+        I: IntoIterator,
+        I::Item: Into<V>,
+    {
+        let values = values
+            .into_iter()
+            .map(|v| bson::to_bson(&v.into()))
+            .collect::<Result<Vec<_>, _>>();
+        Filter::from_result(values.map(|v| doc! { &self.path: { op: v } }))
+    }
+
+    /// `{ path: { $eq: value } }`
+    pub fn eq(&self, value: impl Into<V>) -> Filter<Root> {
+        self.compare("$eq", value.into())
+    }
+
+    /// `{ path: { $ne: value } }`
+    pub fn ne(&self, value: impl Into<V>) -> Filter<Root> {
+        self.compare("$ne", value.into())
+    }
+
+    /// `{ path: { $gt: value } }`
+    pub fn gt(&self, value: impl Into<V>) -> Filter<Root> {
+        self.compare("$gt", value.into())
+    }
+
+    /// `{ path: { $gte: value } }`
+    pub fn gte(&self, value: impl Into<V>) -> Filter<Root> {
+        self.compare("$gte", value.into())
+    }
+
+    /// `{ path: { $lt: value } }`
+    pub fn lt(&self, value: impl Into<V>) -> Filter<Root> {
+        self.compare("$lt", value.into())
+    }
+
+    /// `{ path: { $lte: value } }`
+    pub fn lte(&self, value: impl Into<V>) -> Filter<Root> {
+        self.compare("$lte", value.into())
+    }
+
+    /// `{ path: { $in: values } }`
+    pub fn is_in<I>(&self, values: I) -> Filter<Root>
+    where
+        I: IntoIterator,
+        I::Item: Into<V>,
+    {
+        self.compare_many("$in", values)
+    }
+
+    /// `{ path: { $nin: values } }`
+    pub fn not_in<I>(&self, values: I) -> Filter<Root>
+    where
+        I: IntoIterator,
+        I::Item: Into<V>,
+    {
+        self.compare_many("$nin", values)
+    }
+}
+
+/// A filter on the documents of `Root`, built from `FieldPath`s
+pub struct Filter<Root> {
+    document: Result<Document, CrudError>,
+    marker: PhantomData<fn() -> Root>,
+}
+
+impl<Root> Clone for Filter<Root> {
+    fn clone(&self) -> Self {
+        Filter {
+            document: self.document.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Root> std::fmt::Debug for Filter<Root> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Filter").field(&self.document).finish()
+    }
+}
+
+impl<Root> Default for Filter<Root> {
+    fn default() -> Self {
+        Filter::all()
+    }
+}
+
+/// `{ $nor: [self] }`
+impl<Root> std::ops::Not for Filter<Root> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        let document = self.document.map(|d| doc! { "$nor": [Bson::Document(d)] });
+
+        Filter {
+            document,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Root> Filter<Root> {
+    fn new(document: Document) -> Self {
+        Filter {
+            document: Ok(document),
+            marker: PhantomData,
+        }
+    }
+
+    fn from_result(document: bson::ser::Result<Document>) -> Self {
+        Filter {
+            document: document.map_err(|e| CrudError::InvalidFilter(e.to_string())),
+            marker: PhantomData,
+        }
+    }
+
+    /// matches every document
+    pub fn all() -> Self {
+        Filter::new(doc! {})
+    }
+
+    fn combine(self, op: &str, other: Self) -> Self {
+        let document = self.document.and_then(|l| {
+            other.document.map(|r| match (l.is_empty(), r.is_empty()) {
+                (true, _) => r,
+                (_, true) => l,
+                _ => doc! { op: [Bson::Document(l), Bson::Document(r)] },
+            })
+        });
+
+        Filter {
+            document,
+            marker: PhantomData,
+        }
+    }
+
+    /// `{ $and: [self, other] }`
+    pub fn and(self, other: Self) -> Self {
+        self.combine("$and", other)
+    }
+
+    /// `{ $or: [self, other] }`, an empty filter on either side matches every document.
+    /// An invalid side keeps the result invalid, rather than matching every document.
+    pub fn or(self, other: Self) -> Self {
+        match (&self.document, &other.document) {
+            (Err(_), _) => self,
+            (_, Err(_)) => other,
+            _ if self.is_all() || other.is_all() => Filter::all(),
+            _ => self.combine("$or", other),
+        }
+    }
+
+    fn is_all(&self) -> bool {
+        matches!(&self.document, Ok(d) if d.is_empty())
+    }
+
+    /// The filter document, an error if a value could not be serialized
+    pub fn into_document(self) -> Result<Document> {
+        Ok(self.document?)
+    }
+}
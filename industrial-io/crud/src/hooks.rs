@@ -0,0 +1,77 @@
+//! Hooks
+//!
+//! Entity-specific side effects of `MongoCRUD`. `#[derive(CRUD)]` implements `Hooks` with
+//! no-op methods, unless the struct declares `#[crud(hooks)]` and implements it by hand:
+//!
+//! ```rust,ignore
+//! #[derive(CRUD)]
+//! #[crud(hooks)]
+//! struct Company { ... }
+//!
+//! #[async_trait]
+//! impl Hooks for Company {
+//!     async fn before_create(&mut self, _client: &MongoClient) -> Result<()> {
+//!         self.name = self.name.trim().to_string();
+//!         Ok(())
+//!     }
+//! }
+//! ```
+//!
+//! An error of a `before_*` hook aborts the operation, nothing is written. An error of an
+//! `after_*` hook is returned as well, but the document has already been written. `update_many`
+//! calls no hook, see `crate::bulk`.
+//!
+//! With `#[crud(soft_delete)]`, `after_delete` is called by a soft delete only, `after_purge` by
+//! a purge and `after_restore` by a restore, e.g. to cascade each of them to related documents.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bson::DateTime;
+
+use crate::{BaseCRUD, MongoClient};
+
+/// Lifecycle hooks of a CRUD type, called by `MongoCRUD`
+#[async_trait]
+pub trait Hooks: BaseCRUD + Send + Sync {
+    /// before `create`, and before the value is validated
+    async fn before_create(&mut self, _client: &MongoClient) -> Result<()> {
+        Ok(())
+    }
+
+    /// after `create`, with the created document
+    async fn after_create(&self, _client: &MongoClient) -> Result<()> {
+        Ok(())
+    }
+
+    /// before `update`, and before the value is validated.
+    /// Not called by `patch`, which does not carry the whole value.
+    async fn before_update(&mut self, _client: &MongoClient) -> Result<()> {
+        Ok(())
+    }
+
+    /// after `update` and `patch`, with the updated document
+    async fn after_update(&self, _client: &MongoClient) -> Result<()> {
+        Ok(())
+    }
+
+    /// before `delete` and `purge`, whether the document exists or not
+    async fn before_delete(_id: &Self::Id, _client: &MongoClient) -> Result<()> {
+        Ok(())
+    }
+
+    /// after `delete`, with the deleted document. Without `#[crud(soft_delete)]`, a delete is
+    /// a purge, see `after_purge`.
+    async fn after_delete(&self, _client: &MongoClient) -> Result<()> {
+        Ok(())
+    }
+
+    /// after `purge`, with the document deleted for good. Calls `after_delete` by default.
+    async fn after_purge(&self, client: &MongoClient) -> Result<()> {
+        self.after_delete(client).await
+    }
+
+    /// after `restore`, with the restored document and the time it had been deleted at
+    async fn after_restore(&self, _deleted_at: DateTime, _client: &MongoClient) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+//! Id
+//!
+//! Client-generated ids, used by `#[crud(id(generate = "uuid_v4"))]` and
+//! `#[crud(id(generate = "uuid_v7"))]`.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bson::Uuid;
+
+/// A random UUID (version 4)
+pub fn uuid_v4() -> Uuid {
+    Uuid::new()
+}
+
+/// A time-ordered UUID (version 7): 48 bits of Unix milliseconds followed by random bits,
+/// so that ids created later sort after the earlier ones.
+pub fn uuid_v7() -> Uuid {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+
+    // the variant bits of a v4 UUID are the same as v7's
+    let mut bytes = Uuid::new().bytes();
+    bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x70;
+
+    Uuid::from_bytes(bytes)
+}
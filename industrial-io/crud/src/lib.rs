@@ -1,8 +1,46 @@
 //! Crud
 
+pub mod bulk;
 pub mod cache;
+pub mod clock;
+pub mod encrypt;
+pub mod errors;
+pub mod filter;
+pub mod hooks;
+pub mod id;
+pub mod page;
+pub mod patch;
 pub mod persistence;
+pub mod polymorphic;
+pub mod projection;
+pub mod query;
+pub mod schema;
+pub mod transaction;
+pub mod validate;
 
+pub use bulk::{BulkError, BulkResult, WriteModel};
 pub use cache::RedisClient;
-pub use crud_derive::CRUD;
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use crud_derive::{Patchable, CRUD};
+pub use encrypt::{EncryptMode, EncryptedField, KeyProvider, LocalKeyFile};
+pub use errors::CrudError;
+pub use filter::{FieldPath, Fields, Filter};
+pub use hooks::Hooks;
+pub use page::{Cursor, Page, PageRequest, Paging};
+pub use patch::{Patch, PatchUpdate, Patchable};
 pub use persistence::*;
+pub use polymorphic::{Polymorphic, Variant};
+pub use projection::Projection;
+pub use query::{Collation, Hint, Order, Query};
+pub use schema::{BsonSchema, ValidationAction, ValidationLevel};
+pub use validate::{FieldError, ValidationErrors};
+
+/// Dependencies of the code generated by `#[derive(CRUD)]` and `#[derive(Patchable)]`, so that a
+/// crate deriving them needs nothing but `crud`. Not a public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use anyhow;
+    pub use async_trait;
+    pub use bson;
+    pub use serde;
+}
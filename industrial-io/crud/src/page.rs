@@ -0,0 +1,384 @@
+//! Page
+//!
+//! Paginated reads by `MongoCRUD::paginate`, a page of a query at a time:
+//!
+//! ```rust,ignore
+//! let fields = Company::fields();
+//! let query = Query::new(fields.category().eq(Industry::Electronics)).sort(&fields.name(), Order::Asc);
+//!
+//! let page = client.paginate(query.clone(), PageRequest::keyset(50)).await?;
+//! // the cursor sent back by the caller, e.g. `?cursor=...`
+//! let next = client.paginate(query, PageRequest::keyset(50).cursor(page.next)).await?;
+//! ```
+//!
+//! - offset paging skips the documents of the previous pages, any page can be reached but the
+//!   skip gets slower as the page number grows
+//! - keyset paging reads the documents after (or before) the last one seen, by the query's sort
+//!   key and `_id`, so every page is as fast as the first with an index on the key. The query
+//!   is sorted by at most one key besides `_id`, which must be kept by a projection. A null or
+//!   missing key sorts before any value.
+//!
+//! A cursor is an opaque, URL-safe string, which is passed back as it is.
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use bson::{doc, Bson, Document};
+use mongodb::options::FindOptions;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::CrudError;
+
+const OFFSET: &str = "offset";
+const AFTER: &str = "after";
+const BEFORE: &str = "before";
+const ID: &str = "_id";
+
+/// A page of documents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// documents matching the query, in all the pages
+    pub total: u64,
+    /// the cursor of the next page, `None` on the last page
+    pub next: Option<Cursor>,
+    /// the cursor of the previous page, `None` on the first page
+    pub previous: Option<Cursor>,
+}
+
+/// The position of a page, see `Page::next` and `Page::previous`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Cursor(String);
+
+impl Cursor {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn encode(position: &Position) -> Result<Self> {
+        let document = match position {
+            Position::Offset(offset) => doc! { OFFSET: i64::try_from(*offset)? },
+            Position::After(values) => doc! { AFTER: values.clone() },
+            Position::Before(values) => doc! { BEFORE: values.clone() },
+        };
+        let mut bytes = vec![];
+        document.to_writer(&mut bytes)?;
+
+        Ok(Cursor(base64::encode_config(
+            bytes,
+            base64::URL_SAFE_NO_PAD,
+        )))
+    }
+
+    fn position(&self) -> Result<Position, CrudError> {
+        let invalid = || CrudError::InvalidCursor(self.0.clone());
+        let bytes =
+            base64::decode_config(&self.0, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+        let document = Document::from_reader(bytes.as_slice()).map_err(|_| invalid())?;
+
+        match document.iter().next() {
+            Some((key, Bson::Int64(offset))) if key == OFFSET && *offset >= 0 => {
+                Ok(Position::Offset(*offset as u64))
+            }
+            Some((key, Bson::Array(values))) if key == AFTER => Ok(Position::After(values.clone())),
+            Some((key, Bson::Array(values))) if key == BEFORE => {
+                Ok(Position::Before(values.clone()))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A cursor received, e.g. from a query string, checked before it is used
+impl FromStr for Cursor {
+    type Err = CrudError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cursor = Cursor(s.to_owned());
+        cursor.position()?;
+        Ok(cursor)
+    }
+}
+
+/// where a page starts
+enum Position {
+    /// the number of documents skipped
+    Offset(u64),
+    /// the keyset values of the last document of the previous page
+    After(Vec<Bson>),
+    /// the keyset values of the first document of the next page
+    Before(Vec<Bson>),
+}
+
+/// How the pages are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Paging {
+    Offset,
+    Keyset,
+}
+
+/// A page to read, by `MongoCRUD::paginate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageRequest {
+    paging: Paging,
+    size: u64,
+    cursor: Option<Cursor>,
+}
+
+impl PageRequest {
+    /// pages of `size` documents, by offset
+    pub fn offset(size: u64) -> Self {
+        PageRequest {
+            paging: Paging::Offset,
+            size,
+            cursor: None,
+        }
+    }
+
+    /// pages of `size` documents, by keyset
+    pub fn keyset(size: u64) -> Self {
+        PageRequest {
+            paging: Paging::Keyset,
+            size,
+            cursor: None,
+        }
+    }
+
+    /// the page of a cursor, `None` for the first page
+    pub fn cursor(mut self, cursor: Option<Cursor>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+
+    /// The filter and the options reading the page, from the query's. The query may not have
+    /// a skip or a limit, the pages have their own.
+    pub(crate) fn plan(
+        &self,
+        filter: Document,
+        options: &mut FindOptions,
+    ) -> Result<(Document, Plan)> {
+        if self.size == 0 {
+            return Err(anyhow!("The page size must be positive!"));
+        }
+        if options.skip.is_some() || options.limit.is_some() {
+            return Err(anyhow!(
+                "A paginated query cannot have a skip or a limit, use the page size!"
+            ));
+        }
+        let position = self.cursor.as_ref().map(Cursor::position).transpose()?;
+        let mismatch =
+            || CrudError::InvalidCursor(format!("not a cursor of {:?} paging", self.paging));
+        // one more document, telling whether there is another page
+        options.limit = Some(i64::try_from(self.size)?.saturating_add(1));
+
+        let mut plan = Plan {
+            paging: self.paging,
+            size: self.size,
+            offset: 0,
+            keys: vec![],
+            backward: false,
+            first: position.is_none(),
+        };
+        let filter = match (self.paging, position) {
+            (Paging::Offset, None) => filter,
+            (Paging::Offset, Some(Position::Offset(offset))) => {
+                plan.offset = offset;
+                filter
+            }
+            (Paging::Keyset, position) => {
+                plan.keys = keyset(options.sort.take())?;
+                let filter = match position {
+                    None => filter,
+                    Some(Position::After(values)) => and(filter, seek(&plan.keys, &values, false)?),
+                    Some(Position::Before(values)) => {
+                        plan.backward = true;
+                        and(filter, seek(&plan.keys, &values, true)?)
+                    }
+                    Some(Position::Offset(_)) => return Err(mismatch().into()),
+                };
+                let sort = plan
+                    .keys
+                    .iter()
+                    .map(|(key, order)| match plan.backward {
+                        true => (key.clone(), Bson::Int32(-order)),
+                        false => (key.clone(), Bson::Int32(*order)),
+                    })
+                    .collect();
+                options.sort = Some(sort);
+                filter
+            }
+            (Paging::Offset, Some(_)) => return Err(mismatch().into()),
+        };
+        options.skip = match plan.offset {
+            0 => None,
+            offset => Some(offset),
+        };
+
+        Ok((filter, plan))
+    }
+}
+
+/// How a page is read, see `PageRequest::plan`
+pub(crate) struct Plan {
+    paging: Paging,
+    size: u64,
+    offset: u64,
+    /// keyset paging: the sort key and `_id`, and their orders
+    keys: Vec<(String, i32)>,
+    /// keyset paging: read in reverse, from a `previous` cursor
+    backward: bool,
+    /// no cursor
+    first: bool,
+}
+
+impl Plan {
+    /// The page of the documents read by the plan, which are decoded by `decode`
+    pub(crate) fn page<T, F>(
+        &self,
+        mut documents: Vec<Document>,
+        total: u64,
+        decode: F,
+    ) -> Result<Page<T>>
+    where
+        F: Fn(Document) -> Result<T>,
+    {
+        let more = documents.len() as u64 > self.size;
+        documents.truncate(self.size as usize);
+        if self.backward {
+            documents.reverse();
+        }
+
+        let (next, previous) = match self.paging {
+            Paging::Offset => (
+                more.then(|| Position::Offset(self.offset + self.size)),
+                (self.offset > 0).then(|| Position::Offset(self.offset.saturating_sub(self.size))),
+            ),
+            Paging::Keyset => {
+                // read backward, the next page is the one the cursor came from
+                let (has_next, has_previous) = match self.backward {
+                    true => (true, more),
+                    false => (more, !self.first),
+                };
+                let last = documents.last().filter(|_| has_next);
+                let first = documents.first().filter(|_| has_previous);
+                (
+                    last.map(|d| Position::After(self.values(d))),
+                    first.map(|d| Position::Before(self.values(d))),
+                )
+            }
+        };
+
+        Ok(Page {
+            total,
+            next: next.as_ref().map(Cursor::encode).transpose()?,
+            previous: previous.as_ref().map(Cursor::encode).transpose()?,
+            items: documents.into_iter().map(decode).collect::<Result<_>>()?,
+        })
+    }
+
+    /// the values of the keys of a document as stored, `null` if missing
+    fn values(&self, document: &Document) -> Vec<Bson> {
+        self.keys
+            .iter()
+            .map(|(key, _)| value_at(document, key).cloned().unwrap_or(Bson::Null))
+            .collect()
+    }
+}
+
+/// the keys of keyset paging: the query's sort key, if any, and `_id` as the tie-breaker
+fn keyset(sort: Option<Document>) -> Result<Vec<(String, i32)>, CrudError> {
+    let invalid = || {
+        CrudError::InvalidCursor(
+            "keyset paging sorts by at most one key besides `_id`, in the order 1 or -1".to_owned(),
+        )
+    };
+    let mut keys = vec![];
+    for (key, order) in sort.unwrap_or_default() {
+        match order {
+            Bson::Int32(order @ (1 | -1)) => keys.push((key, order)),
+            Bson::Int64(order @ (1 | -1)) => keys.push((key, order as i32)),
+            _ => return Err(invalid()),
+        }
+    }
+
+    match keys.as_slice() {
+        [] => Ok(vec![(ID.to_owned(), 1)]),
+        [(key, _)] if key == ID => Ok(keys),
+        [(_, order)] => {
+            let order = *order;
+            keys.push((ID.to_owned(), order));
+            Ok(keys)
+        }
+        [(key, _), (id, _)] if key != ID && id == ID => Ok(keys),
+        _ => Err(invalid()),
+    }
+}
+
+/// the documents after `values` in the order of `keys`, or before them if `backward`
+fn seek(keys: &[(String, i32)], values: &[Bson], backward: bool) -> Result<Document, CrudError> {
+    if keys.len() != values.len() {
+        return Err(CrudError::InvalidCursor(
+            "the cursor is not of the query's sort".to_owned(),
+        ));
+    }
+
+    let mut branches = vec![];
+    for (i, (key, order)) in keys.iter().enumerate() {
+        let mut equal = Document::new();
+        for ((key, _), value) in keys[..i].iter().zip(values) {
+            equal.insert(key, doc! { "$eq": value.clone() });
+        }
+        for condition in beyond(&values[i], (*order == 1) != backward) {
+            let mut branch = equal.clone();
+            branch.insert(key, condition);
+            branches.push(branch);
+        }
+    }
+
+    Ok(match branches.len() {
+        1 => branches.remove(0),
+        _ => doc! { "$or": branches },
+    })
+}
+
+/// The conditions of the values after `value` in ascending order, or before it. `$gt` and
+/// `$lt` only compare values of the same type, and null, or a missing key, sorts first, so
+/// null is compared explicitly.
+fn beyond(value: &Bson, ascending: bool) -> Vec<Document> {
+    match (value, ascending) {
+        (Bson::Null, true) => vec![doc! { "$ne": Bson::Null }],
+        (Bson::Null, false) => vec![],
+        (value, true) => vec![doc! { "$gt": value.clone() }],
+        (value, false) => vec![doc! { "$lt": value.clone() }, doc! { "$eq": Bson::Null }],
+    }
+}
+
+/// `{ $and: [filter, other] }`
+fn and(filter: Document, other: Document) -> Document {
+    match filter.is_empty() {
+        true => other,
+        false => doc! { "$and": [filter, other] },
+    }
+}
+
+/// the value of a dotted path, e.g. `option.position`
+pub(crate) fn value_at<'a>(document: &'a Document, path: &str) -> Option<&'a Bson> {
+    let mut keys = path.split('.');
+    let mut value = document.get(keys.next()?)?;
+    for key in keys {
+        value = match value {
+            Bson::Document(d) => d.get(key)?,
+            _ => return None,
+        };
+    }
+
+    Some(value)
+}
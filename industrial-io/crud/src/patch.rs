@@ -0,0 +1,104 @@
+//! Patch
+//!
+//! Partial updates. `#[derive(CRUD)]` generates a `<Name>Patch` struct in which every field
+//! is optional, only the fields that are present get written.
+//!
+//! ```rust,ignore
+//! let patch = CompanyPatch {
+//!     name: Some("new name".to_string()),  // `$set`
+//!     group: Some(None),                   // `$unset`, the field itself is an `Option`
+//!     option: Some(VertexOptionPatch {     // `$set` of `option.position` only
+//!         position: Some((10, 20)),
+//!         ..Default::default()
+//!     }),
+//!     ..Default::default()
+//! };
+//! client.patch(id, patch).await?;
+//! ```
+
+use anyhow::Result;
+use bson::{doc, Document};
+use serde::Serialize;
+
+use crate::errors::CrudError;
+use crate::validate::ValidationErrors;
+
+/// A type that has a generated `<Name>Patch`.
+/// Implemented by `#[derive(CRUD)]`, or by `#[derive(Patchable)]` for types nested in a
+/// CRUD struct by `#[crud(nested)]`.
+pub trait Patchable {
+    type Patch: Patch<Target = Self> + Clone + Send + Sync;
+}
+
+/// A partial update of `Patchable`
+pub trait Patch {
+    /// the patched type
+    type Target;
+
+    /// write the present fields into `update`, keys are prefixed by `prefix`
+    fn write_update(&self, prefix: &str, update: &mut PatchUpdate) -> Result<()>;
+
+    /// check the present fields by their `#[crud(validate(...))]`, keys are prefixed by `prefix`
+    fn validate(&self, _prefix: &str, _errors: &mut ValidationErrors) {}
+
+    /// The update document, e.g. `{ "$set": { "name": "..." }, "$unset": { "group": "" } }`.
+    /// Empty if no field is present.
+    fn to_update(&self) -> Result<Document> {
+        let mut update = PatchUpdate::default();
+        self.write_update("", &mut update)?;
+        Ok(update.into_document())
+    }
+}
+
+/// The update of the present fields, `CrudError::Validation` if one of them fails before
+/// anything is written
+pub(crate) fn validated_update<P: Patch>(patch: &P) -> Result<PatchUpdate> {
+    let mut errors = ValidationErrors::default();
+    patch.validate("", &mut errors);
+    errors.into_result().map_err(CrudError::Validation)?;
+
+    let mut update = PatchUpdate::default();
+    patch.write_update("", &mut update)?;
+    Ok(update)
+}
+
+/// `$set` and `$unset` of a patch
+#[derive(Debug, Clone, Default)]
+pub struct PatchUpdate {
+    set: Document,
+    unset: Document,
+}
+
+impl PatchUpdate {
+    /// `$set` the field `key`
+    pub fn set<T: Serialize>(&mut self, prefix: &str, key: &str, value: &T) -> Result<()> {
+        self.set
+            .insert(format!("{prefix}{key}"), bson::to_bson(value)?);
+        Ok(())
+    }
+
+    /// `$unset` the field `key`
+    pub fn unset(&mut self, prefix: &str, key: &str) {
+        self.unset.insert(format!("{prefix}{key}"), "");
+    }
+
+    /// prefix of the fields nested in `key`
+    pub fn nested(prefix: &str, key: &str) -> String {
+        format!("{prefix}{key}.")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.unset.is_empty()
+    }
+
+    pub fn into_document(self) -> Document {
+        let mut update = doc! {};
+        if !self.set.is_empty() {
+            update.insert("$set", self.set);
+        }
+        if !self.unset.is_empty() {
+            update.insert("$unset", self.unset);
+        }
+        update
+    }
+}
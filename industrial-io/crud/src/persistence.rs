@@ -1,22 +1,50 @@
 //! Persistence service.
 
 use std::borrow::Cow;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use bson::{doc, oid::ObjectId, to_document};
-use mongodb::{options::IndexOptions as MongoIndexOptions, IndexModel as MongoIndexModel};
+use bson::{doc, oid::ObjectId, to_document, Bson, DateTime, Document};
+use mongodb::{
+    options::{
+        CountOptions, FindOneAndUpdateOptions, FindOptions, IndexOptions as MongoIndexOptions,
+        ReturnDocument,
+    },
+    ClientSession, IndexModel as MongoIndexModel,
+};
 use serde::{de::DeserializeOwned, Serialize};
-use tokio_stream::StreamExt;
+use tokio::sync::{Mutex, OnceCell};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::bulk::{BulkResult, WriteModel};
+use crate::clock::{Clock, SystemClock};
+use crate::encrypt::{EncryptedField, KeyProvider};
+use crate::errors::CrudError;
+use crate::filter::{FieldPath, Filter};
+use crate::hooks::Hooks;
+use crate::page::{Page, PageRequest};
+use crate::patch::{validated_update, Patch, PatchUpdate};
+use crate::projection::Projection;
+use crate::query::Query;
+use crate::validate::ValidationErrors;
 
 const INDEXES_PREFIX: &str = "crud";
 
 /// MongoDB client
 #[derive(Clone)]
 pub struct MongoClient {
-    client: mongodb::Client,
+    pub(crate) client: mongodb::Client,
     pub database: String,
     pub collection: String,
+    clock: Arc<dyn Clock>,
+    key_provider: Option<Arc<dyn KeyProvider>>,
+    /// the session of `with_transaction`, see `crate::transaction`
+    pub(crate) session: Option<Arc<Mutex<ClientSession>>>,
+    /// whether the deployment supports transactions, asked once
+    pub(crate) transactions: Arc<OnceCell<bool>>,
 }
 
 /// Used as a placeholder for `.collection<T>` method.
@@ -39,6 +67,10 @@ impl MongoClient {
             client,
             database: database.into(),
             collection: collection.into(),
+            clock: Arc::new(SystemClock),
+            key_provider: None,
+            session: None,
+            transactions: Arc::new(OnceCell::new()),
         })
     }
 
@@ -52,6 +84,33 @@ impl MongoClient {
         self.collection = collection.into();
     }
 
+    /// set the clock of `#[crud(created_at)]` and `#[crud(updated_at)]`, `SystemClock` by default
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// set the key of `#[crud(encrypt)]` fields, see `crate::encrypt`
+    pub fn set_key_provider(&mut self, key_provider: Arc<dyn KeyProvider>) {
+        self.key_provider = Some(key_provider);
+    }
+
+    /// the key of `#[crud(encrypt)]` fields, `None` if it is not set
+    pub fn key_provider(&self) -> Option<&dyn KeyProvider> {
+        self.key_provider.as_deref()
+    }
+
+    /// database of a scope, falls back to the client's database
+    pub(crate) fn scoped_database(&self, scope: &Scope) -> mongodb::Database {
+        self.client
+            .database(scope.database.unwrap_or(&self.database))
+    }
+
+    /// collection of a scope, falls back to the client's database and collection
+    pub(crate) fn scoped_schema<T>(&self, scope: &Scope) -> mongodb::Collection<T> {
+        self.scoped_database(scope)
+            .collection(scope.collection.unwrap_or(&self.collection))
+    }
+
     /// show databases name
     pub async fn show_dbs(&self) -> Result<Vec<String>> {
         let dbs = self.client.list_database_names(None, None).await?;
@@ -93,15 +152,13 @@ impl MongoClient {
         Ok(result.index_name)
     }
 
-    /// Create indexes by `T
+    /// Create indexes by `T`, in `T`'s own collection (see `typed_schema`)
     pub async fn create_indexes_by_type<T: BaseCRUD>(&self) -> Result<Vec<String>> {
-        let indexes = T::show_indexes();
-
-        let index_models = generate_mongo_index_module(&indexes).into_iter();
+        let index_models = index_models_by_type::<T>().into_iter();
         let mut result = vec![];
 
         for im in index_models {
-            let ci = self.schema::<T>().create_index(im, None).await?;
+            let ci = self.typed_schema::<T>().create_index(im, None).await?;
             result.push(ci.index_name);
         }
 
@@ -121,25 +178,38 @@ impl MongoClient {
     }
 }
 
-pub trait MongoClientAbstraction: Send + Sync {
+/// `MongoClientFactory` hands the client over to `Hooks`
+pub trait MongoClientAbstraction: MongoClientFactory + Send + Sync {
     /// get database
-    fn database(&self) -> Cow<str>;
+    fn database(&self) -> Cow<'_, str>;
 
     /// set database
     fn set_database(&mut self, database: &str);
 
     /// get collection
-    fn collection(&self) -> Cow<str>;
+    fn collection(&self) -> Cow<'_, str>;
 
     /// set collection
     fn set_collection(&mut self, collection: &str);
 
     /// get typed collection
     fn schema<T>(&self) -> mongodb::Collection<T>;
+
+    /// get typed collection, routed by `T`'s `#[crud(database = "...", collection = "...")]`.
+    /// Falls back to the client's database and collection when `T` does not declare them.
+    fn typed_schema<T: BaseCRUD>(&self) -> mongodb::Collection<T>;
+
+    /// `typed_schema` of stored documents, whose `#[crud(encrypt)]` fields are not decrypted
+    fn stored_schema<T: BaseCRUD>(&self) -> mongodb::Collection<Document> {
+        self.typed_schema::<T>().clone_with_type()
+    }
+
+    /// current time of the client's clock
+    fn now(&self) -> DateTime;
 }
 
 impl MongoClientAbstraction for MongoClient {
-    fn database(&self) -> Cow<str> {
+    fn database(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.database)
     }
 
@@ -147,7 +217,7 @@ impl MongoClientAbstraction for MongoClient {
         self.database = database.to_string();
     }
 
-    fn collection(&self) -> Cow<str> {
+    fn collection(&self) -> Cow<'_, str> {
         Cow::Borrowed(&self.collection)
     }
 
@@ -160,6 +230,14 @@ impl MongoClientAbstraction for MongoClient {
             .database(&self.database)
             .collection(&self.collection)
     }
+
+    fn typed_schema<T: BaseCRUD>(&self) -> mongodb::Collection<T> {
+        self.scoped_schema(&Scope::of::<T>())
+    }
+
+    fn now(&self) -> DateTime {
+        self.clock.now()
+    }
 }
 
 pub trait MongoClientFactory {
@@ -172,10 +250,25 @@ impl MongoClientFactory for MongoClient {
     }
 }
 
+/// Index direction, or the kind of a special index key
 #[derive(Debug, Clone)]
 pub enum Dir {
     Asc,
     Desc,
+    Hashed,
+    Sphere2d,
+}
+
+impl Dir {
+    /// value of the key in an index specification
+    fn to_bson(&self) -> Bson {
+        match self {
+            Dir::Asc => Bson::Int32(1),
+            Dir::Desc => Bson::Int32(-1),
+            Dir::Hashed => Bson::String("hashed".to_owned()),
+            Dir::Sphere2d => Bson::String("2dsphere".to_owned()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -183,89 +276,388 @@ pub struct SingleIndex {
     pub key: (String, Dir),
     pub unique: bool,
     pub text: bool,
+    /// skip documents that do not have the field
+    pub sparse: bool,
+    /// TTL, the field must hold a date
+    pub expire_after: Option<Duration>,
+    /// `partialFilterExpression`, only index documents matching the filter
+    pub partial: Option<Document>,
 }
 
 impl SingleIndex {
     pub fn new(key: (String, Dir), unique: bool, text: bool) -> Self {
-        SingleIndex { key, unique, text }
+        SingleIndex {
+            key,
+            unique,
+            text,
+            sparse: false,
+            expire_after: None,
+            partial: None,
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct SingleIndexOptions(pub Vec<SingleIndex>);
-
 #[derive(Debug, Clone)]
 pub struct CompoundIndexOptions {
+    /// `None` for the unnamed compound index, whose name is made of its keys
+    pub name: Option<String>,
     pub keys: Vec<(String, Dir)>,
     pub unique: bool,
     pub text: bool,
+    /// skip documents that do not have any of the fields
+    pub sparse: bool,
+    /// `partialFilterExpression`, only index documents matching the filter
+    pub partial: Option<Document>,
 }
 
 impl CompoundIndexOptions {
-    pub fn new(keys: Vec<(String, Dir)>, unique: bool, text: bool) -> Self {
-        CompoundIndexOptions { keys, unique, text }
+    pub fn new(name: Option<String>, keys: Vec<(String, Dir)>, unique: bool, text: bool) -> Self {
+        CompoundIndexOptions {
+            name,
+            keys,
+            unique,
+            text,
+            sparse: false,
+            partial: None,
+        }
     }
 }
 
-/// index options represent indexes in a collection, the default `_id` index is not included.
-#[derive(Debug)]
-pub enum IndexOptions {
-    Single(SingleIndexOptions),
+/// Text index options
+///
+/// A collection can have at most one text index. Fields of `SingleIndex` and
+/// `CompoundIndexOptions` whose `text` is `true` are merged into it as well.
+#[derive(Debug, Clone, Default)]
+pub struct TextIndex {
+    /// `None` for the default name `_crud_text`
+    pub name: Option<String>,
+    /// fields and their optional weights
+    pub fields: Vec<(String, Option<i32>)>,
+    pub default_language: Option<String>,
+    pub language_override: Option<String>,
+}
+
+impl TextIndex {
+    /// add a field unless it is already there, a weight is never overwritten by `None`
+    fn add_field(&mut self, field: &str, weight: Option<i32>) {
+        match self.fields.iter_mut().find(|(f, _)| f == field) {
+            Some((_, w)) => *w = weight.or(*w),
+            None => self.fields.push((field.to_owned(), weight)),
+        }
+    }
+
+    pub(crate) fn merge(&mut self, other: &TextIndex) {
+        other.fields.iter().for_each(|(f, w)| self.add_field(f, *w));
+        self.name = self.name.take().or_else(|| other.name.clone());
+        self.default_language = self
+            .default_language
+            .take()
+            .or_else(|| other.default_language.clone());
+        self.language_override = self
+            .language_override
+            .take()
+            .or_else(|| other.language_override.clone());
+    }
+
+    pub(crate) fn to_index_model(&self) -> MongoIndexModel {
+        let keys = self.fields.iter().fold(doc! {}, |mut acc, (name, _)| {
+            acc.insert(name.to_owned(), "text");
+            acc
+        });
+        let weights = self
+            .fields
+            .iter()
+            .filter_map(|(name, w)| w.map(|w| (name.to_owned(), w)))
+            .fold(doc! {}, |mut acc, (name, w)| {
+                acc.insert(name, w);
+                acc
+            });
+        let name = self.name.clone().unwrap_or_else(|| "text".to_owned());
+
+        let mio = MongoIndexOptions::builder()
+            .name(format!("_{}_{}", INDEXES_PREFIX, name))
+            .weights((!weights.is_empty()).then_some(weights))
+            .default_language(self.default_language.clone())
+            .language_override(self.language_override.clone())
+            .build();
+        MongoIndexModel::builder().keys(keys).options(mio).build()
+    }
+}
+
+/// An index definition, either on a single field, on several fields or a text index
+#[derive(Debug, Clone)]
+pub enum Index {
+    Single(SingleIndex),
     Compound(CompoundIndexOptions),
-    None,
+    Text(TextIndex),
+}
+
+/// index options represent indexes in a collection, the default `_id` index is not included.
+#[derive(Debug, Clone, Default)]
+pub struct IndexOptions(pub Vec<Index>);
+
+impl IndexOptions {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Index> {
+        self.0.iter()
+    }
 }
 
 /// Turn `IndexOptions` into `Vec<mongodb::MongoIndexModel>`.
 /// Both single-index and compound-index are named in `MongoIndexOptions`.
-fn generate_mongo_index_module(indexes: &IndexOptions) -> Vec<MongoIndexModel> {
-    match indexes {
-        IndexOptions::Single(s) => {
-            s.0.iter()
-                .map(|si| {
-                    let name = si.key.0.to_owned();
-                    let dir: i32 = match si.key.1 {
-                        Dir::Asc => 1,
-                        Dir::Desc => -1,
-                    };
-                    let unique = si.unique;
-                    // let text = si.text;
-
-                    let mio = MongoIndexOptions::builder()
-                        .name(format!("_{}_{}", INDEXES_PREFIX, name))
-                        .unique(unique)
-                        .build();
-                    MongoIndexModel::builder()
-                        .keys(doc! { name : dir })
-                        .options(mio)
-                        .build()
-                })
-                .collect()
+///
+/// Every field marked as `text` is gathered into one text index. A text single-index
+/// field keeps its own index only when it is `unique`.
+///
+/// The text index is returned apart, since a collection shared by several types can only
+/// have one. Other indexes are limited to the type's documents, see `Scope::index_filter`.
+pub(crate) fn generate_mongo_index_module(
+    indexes: &IndexOptions,
+    scope: &Scope,
+) -> (Vec<MongoIndexModel>, TextIndex) {
+    let mut text_index = TextIndex::default();
+    let prefix = match scope.discriminator {
+        Some((_, value)) => format!("_{}_{}_", INDEXES_PREFIX, value),
+        None => format!("_{}_", INDEXES_PREFIX),
+    };
+    let mut result = vec![];
+
+    for index in indexes.iter() {
+        match index {
+            Index::Single(si) => {
+                let name = si.key.0.to_owned();
+                let unique = si.unique;
+
+                if si.text {
+                    text_index.add_field(&name, None);
+                    if !unique {
+                        continue;
+                    }
+                }
+
+                let (sparse, partial) =
+                    scope.index_filter(&[&name], unique, si.sparse, &si.partial);
+                let mio = MongoIndexOptions::builder()
+                    .name(format!("{}{}", prefix, name))
+                    .unique(unique)
+                    .sparse(sparse.then_some(true))
+                    .expire_after(si.expire_after)
+                    .partial_filter_expression(partial)
+                    .build();
+                let im = MongoIndexModel::builder()
+                    .keys(doc! { name : si.key.1.to_bson() })
+                    .options(mio)
+                    .build();
+                result.push(im);
+            }
+            Index::Compound(c) => {
+                let unique = c.unique;
+
+                let mut indexes_name = String::new();
+                let keys = c.keys.iter().fold(doc! {}, |mut acc, (name, dir)| {
+                    indexes_name.push_str(name);
+                    indexes_name.push('_');
+                    acc.insert(name.to_owned(), dir.to_bson());
+                    acc
+                });
+                let indexes_name = c.name.clone().unwrap_or(indexes_name);
+
+                if c.text {
+                    c.keys
+                        .iter()
+                        .for_each(|(name, _)| text_index.add_field(name, None));
+                }
+
+                let names = c.keys.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+                let (sparse, partial) = scope.index_filter(&names, unique, c.sparse, &c.partial);
+                let mio = MongoIndexOptions::builder()
+                    .name(format!("{}{}", prefix, indexes_name))
+                    .unique(unique)
+                    .sparse(sparse.then_some(true))
+                    .partial_filter_expression(partial)
+                    .build();
+                let im = MongoIndexModel::builder().keys(keys).options(mio).build();
+                result.push(im);
+            }
+            Index::Text(t) => text_index.merge(t),
         }
-        IndexOptions::Compound(c) => {
-            let unique = c.unique;
-            // let text = c.text;
-
-            let mut indexes_name = String::new();
-            let keys = c.keys.iter().fold(doc! {}, |mut acc, (name, dir)| {
-                indexes_name.push_str(name);
-                indexes_name.push('_');
-                let dir: i32 = match dir {
-                    Dir::Asc => 1,
-                    Dir::Desc => -1,
-                };
-                acc.extend(doc! { name.to_owned() : dir });
-                acc
-            });
+    }
 
-            let mio = MongoIndexOptions::builder()
-                .name(format!("_{}_{}", INDEXES_PREFIX, indexes_name))
-                .unique(unique)
-                .build();
-            let im = MongoIndexModel::builder().keys(keys).options(mio).build();
+    (result, text_index)
+}
 
-            vec![im]
+/// MongoDB index models of `T`, created by `MongoClient::create_indexes_by_type`
+pub fn index_models_by_type<T: BaseCRUD>() -> Vec<MongoIndexModel> {
+    let (mut result, text_index) =
+        generate_mongo_index_module(&T::show_indexes(), &Scope::of::<T>());
+    if !text_index.fields.is_empty() {
+        result.push(text_index.to_index_model());
+    }
+    result
+}
+
+/// Where the documents of a type are, and which of them are visible
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Scope {
+    pub database: Option<&'static str>,
+    pub collection: Option<&'static str>,
+    pub discriminator: Option<(&'static str, &'static str)>,
+    pub soft_delete: Option<&'static str>,
+}
+
+impl Scope {
+    pub fn of<T: BaseCRUD>() -> Self {
+        Scope {
+            database: T::database_name(),
+            collection: T::collection_name(),
+            discriminator: T::discriminator(),
+            soft_delete: T::soft_delete_key(),
+        }
+    }
+
+    /// Only the documents of the type, soft-deleted or not
+    pub fn type_filter(&self, filter: Document) -> Document {
+        match self.discriminator {
+            Some((key, value)) => and_filter(filter, key, value.into()),
+            None => filter,
+        }
+    }
+
+    /// The filter of every read: documents of other types sharing the collection and
+    /// soft-deleted documents are excluded
+    pub fn filter(&self, filter: Document) -> Document {
+        let filter = self.type_filter(filter);
+        match self.soft_delete {
+            Some(key) => and_filter(filter, key, Bson::Null),
+            None => filter,
         }
-        IndexOptions::None => vec![],
+    }
+
+    /// `sparse` and `partialFilterExpression` of an index.
+    ///
+    /// An index of a type sharing its collection only covers the type's documents. A unique
+    /// index of a soft-deleted type only covers live documents, whose soft delete field is
+    /// `null`, so that a deleted document doesn't block a new one. As `sparse` cannot be used
+    /// along with a partial filter, it becomes `$exists` of the keys.
+    ///
+    /// A unique sparse index is unique when present: a `None` is written as `null`, which a
+    /// sparse index still covers, so it becomes a partial filter of the non-null types.
+    pub fn index_filter(
+        &self,
+        keys: &[&str],
+        unique: bool,
+        sparse: bool,
+        partial: &Option<Document>,
+    ) -> (bool, Option<Document>) {
+        let mut scope = doc! {};
+        if let Some((key, value)) = self.discriminator {
+            scope.insert(key, value);
+        }
+        if let (true, Some(key)) = (unique, self.soft_delete) {
+            scope.insert(key, doc! { "$type": "null" });
+        }
+        if scope.is_empty() && !(sparse && unique) {
+            return (sparse, partial.clone());
+        }
+
+        let mut partial = partial.clone().unwrap_or_default();
+        if sparse {
+            let present = match unique {
+                true => doc! { "$type": PRESENT_TYPES },
+                false => doc! { "$exists": true },
+            };
+            for k in keys {
+                partial.insert(*k, present.clone());
+            }
+        }
+        partial.extend(scope);
+
+        (false, Some(partial))
+    }
+}
+
+/// BSON types of a present value, i.e. neither missing nor `null`
+const PRESENT_TYPES: &[&str] = &[
+    "double",
+    "string",
+    "object",
+    "array",
+    "binData",
+    "objectId",
+    "bool",
+    "date",
+    "regex",
+    "javascript",
+    "int",
+    "timestamp",
+    "long",
+    "decimal",
+    "maxKey",
+];
+
+/// add `{ key: value }` to a filter, by `$and` if the key is already used
+fn and_filter(mut filter: Document, key: &str, value: Bson) -> Document {
+    if filter.contains_key(key) {
+        return doc! { "$and": [filter, { key: value }] };
+    }
+    filter.insert(key, value);
+    filter
+}
+
+/// The filter of every read, see `Scope::filter`
+pub(crate) fn scoped_filter<T: BaseCRUD>(filter: Document) -> Document {
+    Scope::of::<T>().filter(filter)
+}
+
+/// Only the documents of `T`, soft-deleted or not
+pub(crate) fn type_filter<T: BaseCRUD>(filter: Document) -> Document {
+    Scope::of::<T>().type_filter(filter)
+}
+
+/// Filter of the expected version, documents written before the version field was added
+/// don't have it, and they are at version `0`.
+fn version_filter(version: i64) -> Bson {
+    match version {
+        0 => Bson::Document(doc! { "$in": [0, Bson::Null] }),
+        v => Bson::Int64(v),
+    }
+}
+
+/// Why an update by id, at its version, matched nothing: `CrudError::Conflict` if the document
+/// is at another version, `CrudError::NotFound` otherwise
+pub(crate) async fn unmatched_update<T: BaseCRUD>(
+    client: &MongoClient,
+    id: &T::Id,
+    version: Option<(&str, i64)>,
+) -> Result<CrudError> {
+    let current = client
+        .session_schema::<T>()
+        .find_one(scoped_filter::<T>(id_filter(id)?), None)
+        .await?;
+
+    Ok(match (current, version) {
+        (Some(current), Some((key, expected))) => CrudError::Conflict {
+            expected,
+            current: stored_version(&current, key),
+        },
+        _ => CrudError::NotFound,
+    })
+}
+
+/// Version of a stored document, `0` if it doesn't have one
+fn stored_version(document: &Document, key: &str) -> i64 {
+    match document.get(key) {
+        Some(Bson::Int32(v)) => *v as i64,
+        Some(Bson::Int64(v)) => *v,
+        Some(Bson::Double(v)) => *v as i64,
+        _ => 0,
     }
 }
 
@@ -274,62 +666,279 @@ fn generate_mongo_index_module(indexes: &IndexOptions) -> Vec<MongoIndexModel> {
 /// A Rust struct that implements this trait is a schema of MongoDB's collection.
 /// According to the `crud` crate, any struct who derived `CRUD` will automatically implement this trait.
 pub trait BaseCRUD {
-    fn get_id(&self) -> Option<ObjectId>;
+    /// Type of the `_id` field, e.g. `ObjectId`, `String`, `i64` or `bson::Uuid`.
+    /// Detected by `crud_derive` from the id field `Option<Id>`
+    type Id: Send + Sync + Clone + Serialize + DeserializeOwned + Unpin;
+
+    fn get_id(&self) -> Option<Self::Id>;
 
     fn remove_id(&mut self);
 
-    fn mutate_id(&mut self, oid: ObjectId) -> Result<()>;
+    fn mutate_id(&mut self, id: Self::Id) -> Result<()>;
+
+    /// Generate an id for a new document whose id is not set, see `MongoCRUD::create`.
+    ///
+    /// By default a new `ObjectId` is used, if `Id` can be deserialized from it. Otherwise
+    /// `None`, and the id must be set before creating. `#[crud(id(generate = "uuid_v4"))]`
+    /// or `#[crud(id(generate = "uuid_v7"))]` overrides it by `crate::id`.
+    fn new_id() -> Option<Self::Id> {
+        bson::from_bson(Bson::ObjectId(ObjectId::new())).ok()
+    }
 
     /// Show `IndexOptions`, associate function.
     /// Automatically generated by `crud_derive`
     fn show_indexes() -> IndexOptions;
+
+    /// Collection of the type, `None` falls back to the collection of `MongoClient`.
+    /// Generated by `crud_derive` from `#[crud(collection = "...")]`
+    fn collection_name() -> Option<&'static str> {
+        None
+    }
+
+    /// Database of the type, `None` falls back to the database of `MongoClient`.
+    /// Generated by `crud_derive` from `#[crud(database = "...")]`
+    fn database_name() -> Option<&'static str> {
+        None
+    }
+
+    /// Set the `#[crud(created_at)]` field, called by `MongoCRUD::create`
+    fn stamp_created(&mut self, _now: DateTime) {}
+
+    /// Set the `#[crud(updated_at)]` field, called by `MongoCRUD::create` and `MongoCRUD::update`
+    fn stamp_updated(&mut self, _now: DateTime) {}
+
+    /// Key of the `#[crud(created_at)]` field, it is never overwritten by `MongoCRUD::update`
+    fn created_at_key() -> Option<&'static str> {
+        None
+    }
+
+    /// Key of the `#[crud(updated_at)]` field, see `MongoCRUD::read_modified_since`
+    fn updated_at_key() -> Option<&'static str> {
+        None
+    }
+
+    /// Discriminator key and value, from `#[crud(discriminator = "...", value = "...")]`.
+    /// When it is set, the type shares its collection with other types, and only the documents
+    /// whose discriminator is the value are the type's.
+    fn discriminator() -> Option<(&'static str, &'static str)> {
+        None
+    }
+
+    /// Key of the `#[crud(version)]` field, see `MongoCRUD::update`
+    fn version_key() -> Option<&'static str> {
+        None
+    }
+
+    /// Value of the `#[crud(version)]` field
+    fn get_version(&self) -> Option<i64> {
+        None
+    }
+
+    /// Set the `#[crud(version)]` field
+    fn set_version(&mut self, _version: i64) {}
+
+    /// Key of the `#[crud(soft_delete)]` field. When it is set, `MongoCRUD::delete` only
+    /// stamps the field, and deleted documents are excluded from every read.
+    fn soft_delete_key() -> Option<&'static str> {
+        None
+    }
+
+    /// Check the `#[crud(validate(...))]` fields, called by `MongoCRUD::create` and
+    /// `MongoCRUD::update` before writing
+    fn validate(&self) -> std::result::Result<(), ValidationErrors> {
+        Ok(())
+    }
+
+    /// `$jsonSchema` of the stored documents, see `MongoClient::set_validator_by_type`.
+    /// Generated by `crud_derive` from the fields' types
+    fn json_schema() -> Document {
+        doc! { "bsonType": "object" }
+    }
+
+    /// Fields marked by `#[crud(encrypt)]`, encrypted by `MongoCRUD` before writing and
+    /// decrypted after reading
+    fn encrypted_fields() -> &'static [EncryptedField] {
+        &[]
+    }
+}
+
+/// Deserialize a stored document of `T`, whose encrypted fields are decrypted first
+pub(crate) fn decode<T: BaseCRUD + DeserializeOwned>(
+    client: &MongoClient,
+    document: Document,
+) -> Result<T> {
+    decode_as::<T, T>(client, document)
+}
+
+/// `decode` into `P`, `T` itself or a projection of it
+fn decode_as<T: BaseCRUD, P: DeserializeOwned>(
+    client: &MongoClient,
+    mut document: Document,
+) -> Result<P> {
+    client.decrypt_document(T::encrypted_fields(), &mut document)?;
+    Ok(bson::from_document(document)?)
+}
+
+/// Documents read one at a time, see `MongoCRUD::stream`
+pub type CrudStream<'a, T> = Pin<Box<dyn Stream<Item = Result<T>> + Send + 'a>>;
+
+/// Serialize a value of `T` to be stored, whose encrypted fields are encrypted
+pub(crate) fn encode<T: BaseCRUD + Serialize>(client: &MongoClient, value: &T) -> Result<Document> {
+    let mut document = to_document(value)?;
+    client.encrypt_document(T::encrypted_fields(), &mut document)?;
+    Ok(document)
+}
+
+/// The document of a value to be created. The value is hooked and validated, and gets its id
+/// and timestamps.
+pub(crate) async fn created_document<T: Hooks + Serialize>(
+    client: &MongoClient,
+    value: &mut T,
+) -> Result<Document> {
+    value.before_create(client).await?;
+    value.validate().map_err(CrudError::Validation)?;
+    if value.get_id().is_none() {
+        let id = T::new_id().ok_or_else(|| {
+            anyhow!(
+                "No `id` was given, and `{}` cannot be generated!",
+                std::any::type_name::<T::Id>()
+            )
+        })?;
+        value.mutate_id(id)?;
+    }
+    let now = client.now();
+    value.stamp_created(now);
+    value.stamp_updated(now);
+    let mut document = encode(client, value)?;
+    if let Some((key, discriminator)) = T::discriminator() {
+        document.insert(key, discriminator);
+    }
+
+    Ok(document)
+}
+
+/// The filter and the update of a value to be updated, by its id and at its version.
+/// The value is hooked and validated, and `#[crud(updated_at)]` is stamped.
+pub(crate) async fn updated_statement<T: Hooks + Serialize>(
+    client: &MongoClient,
+    value: &mut T,
+) -> Result<(Document, Document)> {
+    value.before_update(client).await?;
+    value.validate().map_err(CrudError::Validation)?;
+    let id = value
+        .get_id()
+        .ok_or_else(|| anyhow!("No `id` field was found!"))?;
+    let version = T::version_key().zip(value.get_version());
+    let mut filter = id_filter(&id)?;
+    if let Some((key, expected)) = version {
+        filter.insert(key, version_filter(expected));
+    }
+    let filter = scoped_filter::<T>(filter);
+
+    value.stamp_updated(client.now());
+    let mut set = encode(client, value)?;
+    let keep = [T::created_at_key(), T::soft_delete_key(), T::version_key()];
+    for key in keep.into_iter().flatten() {
+        set.remove(key);
+    }
+    if let Some((key, discriminator)) = T::discriminator() {
+        set.insert(key, discriminator);
+    }
+    let mut update = doc! {"$set": set};
+    if let Some((key, _)) = version {
+        update.insert("$inc", doc! { key: 1 });
+    }
+
+    Ok((filter, update))
+}
+
+/// The update of a patch, with `#[crud(updated_at)]` stamped and `#[crud(version)]` incremented
+pub(crate) fn patched_update<T: BaseCRUD>(
+    client: &MongoClient,
+    mut update: PatchUpdate,
+) -> Result<Document> {
+    if let Some(key) = T::updated_at_key() {
+        update.set("", key, &client.now())?;
+    }
+    let mut update = update.into_document();
+    if let Ok(set) = update.get_document_mut("$set") {
+        client.encrypt_document(T::encrypted_fields(), set)?;
+    }
+    if let Some(key) = T::version_key() {
+        update.insert("$inc", doc! { key: 1 });
+    }
+
+    Ok(update)
+}
+
+/// The update marking documents as soft-deleted by `key`
+pub(crate) fn soft_deleted_update<T: BaseCRUD>(key: &str, now: DateTime) -> Document {
+    let mut set = doc! { key: now };
+    if let Some(updated_at) = T::updated_at_key() {
+        set.insert(updated_at, now);
+    }
+
+    doc! { "$set": set }
+}
+
+/// `{ "_id": id }`, whatever the type of the id is
+pub(crate) fn id_filter<T: Serialize>(id: &T) -> Result<Document> {
+    Ok(doc! { "_id": bson::to_bson(id)? })
 }
 
 /// MongoCRUD trait
 ///
 /// According to `crud` crate, any struct who derived `CRUD` will automatically implement this trait.
 /// In other words, `MongoClient` can use methods in this trait to persist `TYPE` data.
+/// Every method works on `TYPE`'s own collection (see `typed_schema`), so a single
+/// `MongoClient` can be shared by all entities without switching its collection.
+/// The client given by `MongoClient::with_transaction` runs them in its transaction.
 #[async_trait]
 pub trait MongoCRUD<TYPE>: MongoClientAbstraction
 where
-    TYPE: Send + Sync + Clone + Serialize + DeserializeOwned + Unpin + BaseCRUD,
+    TYPE: Send + Sync + Clone + Serialize + DeserializeOwned + Unpin + BaseCRUD + Hooks,
 {
-    /// Create a new document
+    /// Create a new document.
+    /// A given id is kept, otherwise a new one is generated by `BaseCRUD::new_id`.
+    /// `CrudError::Validation` if a `#[crud(validate(...))]` field fails.
     async fn create<'a>(&'a self, mut value: TYPE) -> Result<TYPE>
     where
         TYPE: 'a,
     {
-        // in case of `id` field exists, we need to remove it
-        value.remove_id();
-        let insert = self
-            .schema::<TYPE>()
-            .insert_one(value.clone(), None)
+        let document = created_document(self.client(), &mut value).await?;
+        self.client()
+            .session_schema::<TYPE>()
+            .insert_one(document)
             .await?;
-        let oid = insert.inserted_id.as_object_id().unwrap();
-        value.mutate_id(oid)?;
+        value.after_create(self.client()).await?;
         Ok(value)
     }
 
     /// Read a document by id
-    async fn read<'a>(&'a self, id: ObjectId) -> Result<Option<TYPE>>
+    async fn read<'a>(&'a self, id: TYPE::Id) -> Result<Option<TYPE>>
     where
         TYPE: 'a,
     {
-        let filter = doc! { "_id": id };
-        let result = self.schema::<TYPE>().find_one(filter, None).await?;
-        Ok(result)
+        let filter = scoped_filter::<TYPE>(id_filter(&id)?);
+        let result = self
+            .client()
+            .session_schema::<TYPE>()
+            .find_one(filter, None)
+            .await?;
+        result.map(|d| decode(self.client(), d)).transpose()
     }
 
     /// Read many documents by ids
-    async fn read_many<'a>(&'a self, ids: Vec<ObjectId>) -> Result<Vec<TYPE>>
+    async fn read_many<'a>(&'a self, ids: Vec<TYPE::Id>) -> Result<Vec<TYPE>>
     where
         TYPE: 'a,
     {
-        let filter = doc! { "_id": { "$in": ids } };
-        self.schema::<TYPE>()
+        let filter = scoped_filter::<TYPE>(doc! { "_id": { "$in": bson::to_bson(&ids)? } });
+        self.client()
+            .session_schema::<TYPE>()
             .find(filter, None)
             .await?
-            .map(|v| v.map_err(anyhow::Error::from))
+            .map(|v| decode(self.client(), v?))
             .collect::<Result<Vec<_>>>()
             .await
     }
@@ -339,40 +948,544 @@ where
     where
         TYPE: 'a,
     {
-        self.schema::<TYPE>()
-            .find(None, None)
+        self.client()
+            .session_schema::<TYPE>()
+            .find(scoped_filter::<TYPE>(doc! {}), None)
             .await?
-            .map(|v| v.map_err(anyhow::Error::from))
+            .map(|v| decode(self.client(), v?))
+            .collect::<Result<Vec<_>>>()
+            .await
+    }
+
+    /// Stream all documents, fetching `batch_size` of them at a time, so that a pass over a
+    /// large collection runs in constant memory
+    async fn stream_all<'a>(&'a self, batch_size: u32) -> Result<CrudStream<'a, TYPE>>
+    where
+        TYPE: 'a,
+    {
+        self.stream(Query::default().batch_size(batch_size)).await
+    }
+
+    /// Stream documents by ids, fetching `batch_size` of them at a time
+    async fn stream_many<'a>(
+        &'a self,
+        ids: Vec<TYPE::Id>,
+        batch_size: u32,
+    ) -> Result<CrudStream<'a, TYPE>>
+    where
+        TYPE: 'a,
+    {
+        let filter = scoped_filter::<TYPE>(doc! { "_id": { "$in": bson::to_bson(&ids)? } });
+        let options = FindOptions::builder().batch_size(batch_size).build();
+        let cursor = self
+            .client()
+            .session_schema::<TYPE>()
+            .find(filter, options)
+            .await?;
+        Ok(Box::pin(cursor.map(move |v| decode(self.client(), v?))))
+    }
+
+    /// Stream documents matching a query, fetching `Query::batch_size` of them at a time.
+    /// Unlike `find`, a document is decoded when the stream reaches it.
+    async fn stream<'a, Q>(&'a self, query: Q) -> Result<CrudStream<'a, TYPE>>
+    where
+        TYPE: 'a,
+        Q: Into<Query<TYPE>> + Send + 'a,
+    {
+        let (filter, options) = query.into().into_find()?;
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        let cursor = self
+            .client()
+            .session_schema::<TYPE>()
+            .find(scoped_filter::<TYPE>(filter), options)
+            .await?;
+        Ok(Box::pin(cursor.map(move |v| decode(self.client(), v?))))
+    }
+
+    /// Read documents matching a filter
+    async fn read_by_filter<'a>(&'a self, filter: Document) -> Result<Vec<TYPE>>
+    where
+        TYPE: 'a,
+    {
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        self.client()
+            .session_schema::<TYPE>()
+            .find(scoped_filter::<TYPE>(filter), None)
+            .await?
+            .map(|v| decode(self.client(), v?))
+            .collect::<Result<Vec<_>>>()
+            .await
+    }
+
+    /// Read documents matching a query, or a typed filter, e.g. `Company::fields().name().eq("x")`
+    async fn find<'a, Q>(&'a self, query: Q) -> Result<Vec<TYPE>>
+    where
+        TYPE: 'a,
+        Q: Into<Query<TYPE>> + Send + 'a,
+    {
+        let (filter, options) = query.into().into_find()?;
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        self.client()
+            .session_schema::<TYPE>()
+            .find(scoped_filter::<TYPE>(filter), options)
+            .await?
+            .map(|v| decode(self.client(), v?))
             .collect::<Result<Vec<_>>>()
             .await
     }
 
-    /// Update an existing document
-    async fn update<'a>(&'a self, value: TYPE) -> Result<TYPE>
+    /// Read the first document matching a query, in the query's sort order
+    async fn find_one<'a, Q>(&'a self, query: Q) -> Result<Option<TYPE>>
+    where
+        TYPE: 'a,
+        Q: Into<Query<TYPE>> + Send + 'a,
+    {
+        let (filter, options) = query.into().into_find_one()?;
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        self.client()
+            .session_schema::<TYPE>()
+            .find_one(scoped_filter::<TYPE>(filter), options)
+            .await?
+            .map(|d| decode::<TYPE>(self.client(), d))
+            .transpose()
+    }
+
+    /// Count documents matching a query, within its skip and limit
+    async fn count<'a, Q>(&'a self, query: Q) -> Result<u64>
     where
         TYPE: 'a,
+        Q: Into<Query<TYPE>> + Send + 'a,
     {
-        let oid = value
+        let (filter, options) = query.into().into_count()?;
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        let count = self
+            .client()
+            .session_schema::<TYPE>()
+            .count_documents(scoped_filter::<TYPE>(filter), options)
+            .await?;
+        Ok(count)
+    }
+
+    /// Whether any document matches a query
+    async fn exists<'a, Q>(&'a self, query: Q) -> Result<bool>
+    where
+        TYPE: 'a,
+        Q: Into<Query<TYPE>> + Send + 'a,
+    {
+        let count = self.count(query.into().limit(1)).await?;
+        Ok(count > 0)
+    }
+
+    /// Read all documents, only the fields of the projection `P`
+    async fn read_all_as<'a, P>(&'a self) -> Result<Vec<P>>
+    where
+        TYPE: 'a,
+        P: Projection<Of = TYPE> + 'a,
+    {
+        self.read_by_filter_as(doc! {}).await
+    }
+
+    /// Read documents matching a filter, only the fields of the projection `P`
+    async fn read_by_filter_as<'a, P>(&'a self, filter: Document) -> Result<Vec<P>>
+    where
+        TYPE: 'a,
+        P: Projection<Of = TYPE> + 'a,
+    {
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        let options = FindOptions::builder().projection(P::projection()).build();
+        self.client()
+            .session_schema::<TYPE>()
+            .find(scoped_filter::<TYPE>(filter), options)
+            .await?
+            .map(|v| decode_as::<TYPE, P>(self.client(), v?))
+            .collect::<Result<Vec<_>>>()
+            .await
+    }
+
+    /// Read a page of the documents matching a query, by offset or by keyset, see `crate::page`
+    async fn paginate<'a, Q>(&'a self, query: Q, page: PageRequest) -> Result<Page<TYPE>>
+    where
+        TYPE: 'a,
+        Q: Into<Query<TYPE>> + Send + 'a,
+    {
+        let (filter, mut options) = query.into().into_find()?;
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        let filter = scoped_filter::<TYPE>(filter);
+        let count_options = CountOptions::builder()
+            .collation(options.collation.clone())
+            .hint(options.hint.clone())
+            .build();
+        let (page_filter, plan) = page.plan(filter.clone(), &mut options)?;
+        let total = self
+            .client()
+            .session_schema::<TYPE>()
+            .count_documents(filter, count_options)
+            .await?;
+
+        let documents = self
+            .client()
+            .session_schema::<TYPE>()
+            .find(page_filter, options)
+            .await?
+            .collect::<Result<Vec<_>>>()
+            .await?;
+
+        plan.page(documents, total, |d| decode(self.client(), d))
+    }
+
+    /// Read documents matching a query, only the fields of the projection `P`, which replaces
+    /// the query's own
+    async fn find_as<'a, P, Q>(&'a self, query: Q) -> Result<Vec<P>>
+    where
+        TYPE: 'a,
+        P: Projection<Of = TYPE> + 'a,
+        Q: Into<Query<TYPE>> + Send + 'a,
+    {
+        let (filter, options) = query.into().projection(P::projection()).into_find()?;
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        self.client()
+            .session_schema::<TYPE>()
+            .find(scoped_filter::<TYPE>(filter), options)
+            .await?
+            .map(|v| decode_as::<TYPE, P>(self.client(), v?))
+            .collect::<Result<Vec<_>>>()
+            .await
+    }
+
+    /// Count documents matching a filter
+    async fn count_by_filter<'a>(&'a self, filter: Document) -> Result<u64>
+    where
+        TYPE: 'a,
+    {
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter)?;
+        let count = self
+            .client()
+            .session_schema::<TYPE>()
+            .count_documents(scoped_filter::<TYPE>(filter), None)
+            .await?;
+        Ok(count)
+    }
+
+    /// Read documents whose `#[crud(updated_at)]` is at or after `since`, the oldest comes first.
+    /// The last one's `updated_at` can be used as `since` of the next pull.
+    async fn read_modified_since<'a>(&'a self, since: DateTime) -> Result<Vec<TYPE>>
+    where
+        TYPE: 'a,
+    {
+        let key = TYPE::updated_at_key().ok_or_else(|| {
+            anyhow!(
+                "`{}` has no `#[crud(updated_at)]` field!",
+                std::any::type_name::<TYPE>()
+            )
+        })?;
+        let filter = scoped_filter::<TYPE>(doc! { key: { "$gte": since } });
+        let options = FindOptions::builder().sort(doc! { key: 1 }).build();
+        self.client()
+            .session_schema::<TYPE>()
+            .find(filter, options)
+            .await?
+            .map(|v| decode(self.client(), v?))
+            .collect::<Result<Vec<_>>>()
+            .await
+    }
+
+    /// Search documents by the collection's text index, the most relevant comes first.
+    /// Fields marked by `#[crud(text)]` are searched, see `create_indexes_by_type`.
+    async fn search_text<'a>(&'a self, query: &str) -> Result<Vec<TYPE>>
+    where
+        TYPE: 'a,
+    {
+        let filter = scoped_filter::<TYPE>(doc! { "$text": { "$search": query } });
+        let options = FindOptions::builder()
+            .sort(doc! { "score": { "$meta": "textScore" } })
+            .build();
+        self.client()
+            .session_schema::<TYPE>()
+            .find(filter, options)
+            .await?
+            .map(|v| decode(self.client(), v?))
+            .collect::<Result<Vec<_>>>()
+            .await
+    }
+
+    /// Update an existing document, `#[crud(created_at)]` is kept as it was.
+    /// `CrudError::NotFound` if no live document has the id.
+    ///
+    /// With `#[crud(version)]`, the document is only updated if it is still at the version
+    /// carried by `value`, and the version is incremented. Otherwise `CrudError::Conflict`
+    /// tells the current version, the document should be read again.
+    ///
+    /// `CrudError::Validation` if a `#[crud(validate(...))]` field fails.
+    async fn update<'a>(&'a self, mut value: TYPE) -> Result<TYPE>
+    where
+        TYPE: 'a,
+    {
+        let (filter, update) = updated_statement(self.client(), &mut value).await?;
+        let id = value
             .get_id()
             .ok_or_else(|| anyhow!("No `id` field was found!"))?;
-        let filter = doc! {"_id": oid};
-        let update = doc! {"$set": to_document(&value).unwrap()};
-        self.schema::<TYPE>()
-            .update_one(filter, update, None)
+        let version = TYPE::version_key().zip(value.get_version());
+
+        let result = self
+            .client()
+            .session_schema::<TYPE>()
+            .update_one(filter, update)
             .await?;
+        if result.matched_count == 0 {
+            return Err(unmatched_update::<TYPE>(self.client(), &id, version)
+                .await?
+                .into());
+        }
+
+        if let Some((_, expected)) = version {
+            value.set_version(expected + 1);
+        }
+        value.after_update(self.client()).await?;
         Ok(value)
     }
 
-    /// Delete an existing document
-    async fn delete<'a>(&'a self, id: ObjectId) -> Result<Option<TYPE>>
+    /// Update the fields present in `patch` only, see `crate::patch`. `#[crud(updated_at)]`
+    /// is stamped and `#[crud(version)]` is incremented as well. Returns the document after
+    /// the update, `None` if no document has the id.
+    ///
+    /// `CrudError::Validation` if a present `#[crud(validate(...))]` field fails.
+    async fn patch<'a, P>(&'a self, id: TYPE::Id, patch: P) -> Result<Option<TYPE>>
+    where
+        TYPE: 'a,
+        P: Patch<Target = TYPE> + Send + 'a,
+    {
+        let filter = scoped_filter::<TYPE>(id_filter(&id)?);
+        let update = validated_update(&patch)?;
+        if update.is_empty() {
+            let result = self
+                .client()
+                .session_schema::<TYPE>()
+                .find_one(filter, None)
+                .await?;
+            return result.map(|d| decode(self.client(), d)).transpose();
+        }
+        let update = patched_update::<TYPE>(self.client(), update)?;
+
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let result = self
+            .client()
+            .session_schema::<TYPE>()
+            .find_one_and_update(filter, update, options)
+            .await?
+            .map(|d| decode::<TYPE>(self.client(), d))
+            .transpose()?;
+        if let Some(v) = &result {
+            v.after_update(self.client()).await?;
+        }
+        Ok(result)
+    }
+
+    /// Create many documents by a few round trips, see `crate::bulk`. Ordered, the documents
+    /// after a failed one are not created; unordered, all the others are.
+    async fn create_many<'a>(
+        &'a self,
+        values: Vec<TYPE>,
+        ordered: bool,
+    ) -> Result<BulkResult<TYPE::Id>>
+    where
+        TYPE: 'a,
+    {
+        let operations = values.into_iter().map(WriteModel::Insert).collect();
+        self.bulk_write(operations, ordered).await
+    }
+
+    /// Update the fields present in `patch`, in every document matching the filter
+    async fn update_many<'a, P>(
+        &'a self,
+        filter: Filter<TYPE>,
+        patch: P,
+    ) -> Result<BulkResult<TYPE::Id>>
+    where
+        TYPE: 'a,
+        P: Patch<Target = TYPE> + Send + 'a,
+    {
+        let operation = WriteModel::update_many(filter, patch)?;
+        self.bulk_write(vec![operation], true).await
+    }
+
+    /// Update the documents whose natural `key`, e.g. `&Company::fields().name()`, is one of
+    /// the values', and insert the others, see `WriteModel::upsert`
+    async fn upsert<'a, V>(
+        &'a self,
+        values: Vec<TYPE>,
+        key: &'a FieldPath<TYPE, V>,
+    ) -> Result<BulkResult<TYPE::Id>>
+    where
+        TYPE: 'a,
+        V: 'a,
+    {
+        let operations = values
+            .into_iter()
+            .map(|v| WriteModel::upsert(v, key))
+            .collect();
+        self.bulk_write(operations, false).await
+    }
+
+    /// Delete every document matching the filter, as `delete` does
+    async fn delete_many<'a>(&'a self, filter: Filter<TYPE>) -> Result<BulkResult<TYPE::Id>>
     where
         TYPE: 'a,
     {
-        let filter = doc! {"_id": id};
+        self.bulk_write(vec![WriteModel::DeleteMany(filter)], true)
+            .await
+    }
+
+    /// Run write operations of any kind, see `crate::bulk`
+    async fn bulk_write<'a>(
+        &'a self,
+        operations: Vec<WriteModel<TYPE>>,
+        ordered: bool,
+    ) -> Result<BulkResult<TYPE::Id>>
+    where
+        TYPE: 'a,
+    {
+        crate::bulk::bulk_write(self.client(), operations, ordered).await
+    }
+
+    /// Delete an existing document.
+    /// With `#[crud(soft_delete)]` the document is only marked as deleted, and it can be restored.
+    async fn delete<'a>(&'a self, id: TYPE::Id) -> Result<Option<TYPE>>
+    where
+        TYPE: 'a,
+    {
+        let key = match TYPE::soft_delete_key() {
+            Some(key) => key,
+            None => return self.purge(id).await,
+        };
+        TYPE::before_delete(&id, self.client()).await?;
+
+        let filter = scoped_filter::<TYPE>(id_filter(&id)?);
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
         let result = self
-            .schema::<TYPE>()
+            .client()
+            .session_schema::<TYPE>()
+            .find_one_and_update(
+                filter,
+                soft_deleted_update::<TYPE>(key, self.now()),
+                options,
+            )
+            .await?
+            .map(|d| decode::<TYPE>(self.client(), d))
+            .transpose()?;
+        if let Some(v) = &result {
+            v.after_delete(self.client()).await?;
+        }
+        Ok(result)
+    }
+
+    /// Restore a soft-deleted document, `None` if no deleted document has the id
+    async fn restore<'a>(&'a self, id: TYPE::Id) -> Result<Option<TYPE>>
+    where
+        TYPE: 'a,
+    {
+        let key = TYPE::soft_delete_key().ok_or_else(|| {
+            anyhow!(
+                "`{}` has no `#[crud(soft_delete)]` field!",
+                std::any::type_name::<TYPE>()
+            )
+        })?;
+
+        let mut filter = type_filter::<TYPE>(id_filter(&id)?);
+        filter.insert(key, doc! { "$ne": Bson::Null });
+        // `null` rather than `$unset`, so that unique indexes cover the document again
+        let mut set = doc! { key: Bson::Null };
+        if let Some(updated_at) = TYPE::updated_at_key() {
+            set.insert(updated_at, self.now());
+        }
+        // the document before, which tells when it was deleted
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::Before)
+            .build();
+        let mut document = match self
+            .client()
+            .session_schema::<TYPE>()
+            .find_one_and_update(filter, doc! { "$set": set.clone() }, options)
+            .await?
+        {
+            Some(document) => document,
+            None => return Ok(None),
+        };
+        let deleted_at = *document.get_datetime(key)?;
+        document.extend(set);
+
+        let value = decode::<TYPE>(self.client(), document)?;
+        value.after_restore(deleted_at, self.client()).await?;
+        Ok(Some(value))
+    }
+
+    /// Read soft-deleted documents matching a filter, e.g. to find the ones to `restore`
+    async fn read_deleted<'a>(&'a self, filter: Filter<TYPE>) -> Result<Vec<TYPE>>
+    where
+        TYPE: 'a,
+    {
+        let key = TYPE::soft_delete_key().ok_or_else(|| {
+            anyhow!(
+                "`{}` has no `#[crud(soft_delete)]` field!",
+                std::any::type_name::<TYPE>()
+            )
+        })?;
+
+        let filter = self
+            .client()
+            .encrypt_filter(TYPE::encrypted_fields(), filter.into_document()?)?;
+        let filter = and_filter(
+            type_filter::<TYPE>(filter),
+            key,
+            doc! { "$ne": Bson::Null }.into(),
+        );
+        self.client()
+            .session_schema::<TYPE>()
+            .find(filter, None)
+            .await?
+            .map(|v| decode(self.client(), v?))
+            .collect::<Result<Vec<_>>>()
+            .await
+    }
+
+    /// Delete a document for good, whether it is soft-deleted or not
+    async fn purge<'a>(&'a self, id: TYPE::Id) -> Result<Option<TYPE>>
+    where
+        TYPE: 'a,
+    {
+        TYPE::before_delete(&id, self.client()).await?;
+        let filter = type_filter::<TYPE>(id_filter(&id)?);
+        let result = self
+            .client()
+            .session_schema::<TYPE>()
             .find_one_and_delete(filter, None)
-            .await?;
+            .await?
+            .map(|d| decode::<TYPE>(self.client(), d))
+            .transpose()?;
+        if let Some(v) = &result {
+            v.after_purge(self.client()).await?;
+        }
         Ok(result)
     }
 }
@@ -0,0 +1,174 @@
+//! Polymorphic
+//!
+//! Several CRUD types stored in one collection, told apart by a discriminator field:
+//!
+//! ```rust,ignore
+//! #[crud(collection = "graph", discriminator = "etype", value = "Company")]
+//! struct Company { ... }
+//!
+//! #[crud(collection = "graph", discriminator = "etype", value = "Property")]
+//! struct Property { ... }
+//!
+//! enum GraphEntity {
+//!     Company(Company),
+//!     Property(Property),
+//! }
+//!
+//! impl Polymorphic for GraphEntity {
+//!     fn variants() -> Vec<Variant> {
+//!         vec![Variant::of::<Company>(), Variant::of::<Property>()]
+//!     }
+//!
+//!     fn from_document(value: &str, document: Document) -> Result<Self> {
+//!         match value {
+//!             "Company" => Ok(GraphEntity::Company(bson::from_document(document)?)),
+//!             "Property" => Ok(GraphEntity::Property(bson::from_document(document)?)),
+//!             _ => Err(anyhow!("unknown entity `{}`", value)),
+//!         }
+//!     }
+//! }
+//!
+//! // companies and properties, from one query
+//! let entities: Vec<GraphEntity> = client.read_polymorphic(doc! {}).await?;
+//! ```
+
+use anyhow::{anyhow, Result};
+use bson::{doc, Bson, Document};
+use tokio_stream::StreamExt;
+
+use crate::encrypt::EncryptedField;
+use crate::persistence::{generate_mongo_index_module, Scope, TextIndex};
+use crate::{BaseCRUD, IndexOptions, MongoClient};
+
+/// A CRUD type of a `Polymorphic`
+pub struct Variant {
+    pub(crate) scope: Scope,
+    indexes: IndexOptions,
+    pub(crate) schema: Document,
+    encrypted: &'static [EncryptedField],
+}
+
+impl Variant {
+    pub fn of<T: BaseCRUD>() -> Self {
+        Variant {
+            scope: Scope::of::<T>(),
+            indexes: T::show_indexes(),
+            schema: T::json_schema(),
+            encrypted: T::encrypted_fields(),
+        }
+    }
+}
+
+/// A closed set of CRUD types sharing one collection, usually an enum of them.
+///
+/// Each variant must declare `#[crud(discriminator = "...", value = "...")]`, with the same
+/// discriminator, collection and database.
+pub trait Polymorphic: Sized {
+    fn variants() -> Vec<Variant>;
+
+    /// Deserialize a document, whose discriminator is `value`
+    fn from_document(value: &str, document: Document) -> Result<Self>;
+}
+
+/// the discriminator key shared by the variants
+pub(crate) fn discriminator_key<P: Polymorphic>(variants: &[Variant]) -> Result<&'static str> {
+    let name = std::any::type_name::<P>();
+    let first = variants
+        .first()
+        .ok_or_else(|| anyhow!("`{}` has no variant!", name))?;
+
+    let mut values = vec![];
+    let mut key = None;
+    for v in variants.iter() {
+        let (k, value) = v
+            .scope
+            .discriminator
+            .ok_or_else(|| anyhow!("A variant of `{}` has no discriminator!", name))?;
+        if key.is_some_and(|key| key != k)
+            || v.scope.collection != first.scope.collection
+            || v.scope.database != first.scope.database
+        {
+            return Err(anyhow!(
+                "Variants of `{}` must share the same collection and discriminator!",
+                name
+            ));
+        }
+        if values.contains(&value) {
+            return Err(anyhow!(
+                "Duplicated discriminator value `{}` in `{}`!",
+                value,
+                name
+            ));
+        }
+        values.push(value);
+        key = Some(k);
+    }
+
+    Ok(key.unwrap())
+}
+
+impl MongoClient {
+    /// Read documents of every variant of `P` matching the filter, by one query.
+    /// Soft-deleted documents are excluded, as `MongoCRUD::read_by_filter` does, and encrypted
+    /// fields are decrypted. The filter is used as it is, without encrypting its values.
+    pub async fn read_polymorphic<P: Polymorphic>(&self, filter: Document) -> Result<Vec<P>> {
+        let variants = P::variants();
+        let key = discriminator_key::<P>(&variants)?;
+
+        let scopes = variants
+            .iter()
+            .map(|v| Bson::Document(v.scope.filter(doc! {})))
+            .collect::<Vec<_>>();
+        let filter = match filter.is_empty() {
+            true => doc! { "$or": scopes },
+            false => doc! { "$and": [filter, { "$or": scopes }] },
+        };
+
+        self.scoped_session_schema(&variants[0].scope)
+            .find(filter, None)
+            .await?
+            .map(|document| {
+                let mut document = document?;
+                let value = document.get_str(key)?.to_owned();
+                if let Some(v) = variants
+                    .iter()
+                    .find(|v| v.scope.discriminator.map(|(_, value)| value) == Some(value.as_str()))
+                {
+                    self.decrypt_document(v.encrypted, &mut document)?;
+                }
+                P::from_document(&value, document)
+            })
+            .collect::<Result<Vec<_>>>()
+            .await
+    }
+
+    /// Create indexes of every variant of `P`.
+    ///
+    /// A collection can only have one text index, the text fields of all the variants are
+    /// gathered into it. `create_indexes_by_type` of a single variant fails if another variant
+    /// with different text fields has already created the text index.
+    pub async fn create_indexes_by_polymorphic<P: Polymorphic>(&self) -> Result<Vec<String>> {
+        let variants = P::variants();
+        discriminator_key::<P>(&variants)?;
+
+        let mut index_models = vec![];
+        let mut text_index = TextIndex::default();
+        for v in variants.iter() {
+            let (models, text) = generate_mongo_index_module(&v.indexes, &v.scope);
+            index_models.extend(models);
+            text_index.merge(&text);
+        }
+        if !text_index.fields.is_empty() {
+            index_models.push(text_index.to_index_model());
+        }
+
+        let collection = self.scoped_schema::<Document>(&variants[0].scope);
+        let mut result = vec![];
+        for im in index_models {
+            let ci = collection.create_index(im, None).await?;
+            result.push(ci.index_name);
+        }
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,29 @@
+//! Projection
+//!
+//! Lightweight structs of some of the fields of a CRUD type, read without the others:
+//!
+//! ```rust,ignore
+//! #[derive(CRUD)]
+//! #[crud(projection(name = "CompanySummary", fields("name", "category"), derive(Debug)))]
+//! struct Company { ... }
+//!
+//! // `_id`, `name` and `category` only, the other fields are not sent by MongoDB
+//! let summaries: Vec<CompanySummary> = client.read_all_as().await?;
+//! ```
+//!
+//! The generated struct has the id and the listed fields, with their types and serde attributes.
+//! It derives `Clone`, `Serialize` and `Deserialize`, and the traits listed by `derive(...)`.
+
+use bson::Document;
+use serde::de::DeserializeOwned;
+
+use crate::BaseCRUD;
+
+/// Some of the fields of `Of`, generated by `#[crud(projection(...))]`
+pub trait Projection: DeserializeOwned + Send + Sync + Unpin {
+    /// the CRUD type projected
+    type Of: BaseCRUD;
+
+    /// `{ key: 1, ... }` of the fields
+    fn projection() -> Document;
+}
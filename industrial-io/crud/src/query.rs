@@ -0,0 +1,200 @@
+//! Query
+//!
+//! A typed filter along with the options of a read, used by `MongoCRUD::find`, `find_one`,
+//! `count` and `exists`:
+//!
+//! ```rust,ignore
+//! let fields = Company::fields();
+//! let query = Query::new(fields.category().eq(Industry::Electronics))
+//!     .sort(&fields.name(), Order::Asc)
+//!     .skip(20)
+//!     .limit(10);
+//! let companies = client.find(query).await?;
+//! ```
+//!
+//! A `Filter` is a query without options, `client.find(filter)` works as well.
+//!
+//! The results are `TYPE`s, so a projection may only leave out fields which can be missing,
+//! i.e. `Option`s or `#[serde(default)]`s. See `crate::projection` for lightweight structs.
+
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use bson::Document;
+pub use mongodb::options::{Collation, Hint};
+use mongodb::options::{CountOptions, FindOneOptions, FindOptions};
+
+use crate::filter::{FieldPath, Filter};
+
+/// Sort order of a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// A filter on the documents of `Root`, and how they are read
+pub struct Query<Root> {
+    filter: Filter<Root>,
+    sort: Document,
+    skip: Option<u64>,
+    limit: Option<u64>,
+    projection: Option<Document>,
+    collation: Option<Collation>,
+    hint: Option<Hint>,
+    batch_size: Option<u32>,
+    marker: PhantomData<fn() -> Root>,
+}
+
+impl<Root> Clone for Query<Root> {
+    fn clone(&self) -> Self {
+        Query {
+            filter: self.filter.clone(),
+            sort: self.sort.clone(),
+            skip: self.skip,
+            limit: self.limit,
+            projection: self.projection.clone(),
+            collation: self.collation.clone(),
+            hint: self.hint.clone(),
+            batch_size: self.batch_size,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<Root> std::fmt::Debug for Query<Root> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Query")
+            .field("filter", &self.filter)
+            .field("sort", &self.sort)
+            .field("skip", &self.skip)
+            .field("limit", &self.limit)
+            .field("projection", &self.projection)
+            .field("collation", &self.collation)
+            .field("hint", &self.hint)
+            .field("batch_size", &self.batch_size)
+            .finish()
+    }
+}
+
+impl<Root> Default for Query<Root> {
+    fn default() -> Self {
+        Query::new(Filter::all())
+    }
+}
+
+impl<Root> From<Filter<Root>> for Query<Root> {
+    fn from(filter: Filter<Root>) -> Self {
+        Query::new(filter)
+    }
+}
+
+impl<Root> Query<Root> {
+    pub fn new(filter: Filter<Root>) -> Self {
+        Query {
+            filter,
+            sort: Document::new(),
+            skip: None,
+            limit: None,
+            projection: None,
+            collation: None,
+            hint: None,
+            batch_size: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// sort by a field, after the fields already sorted by
+    pub fn sort<V>(mut self, field: &FieldPath<Root, V>, order: Order) -> Self {
+        let order = match order {
+            Order::Asc => 1_i32,
+            Order::Desc => -1_i32,
+        };
+        self.sort.insert(field.path(), order);
+        self
+    }
+
+    /// skip the first `skip` documents
+    pub fn skip(mut self, skip: u64) -> Self {
+        self.skip = Some(skip);
+        self
+    }
+
+    /// at most `limit` documents
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// only the fields of the projection, e.g. `doc! { "data": 0 }`
+    pub fn projection(mut self, projection: Document) -> Self {
+        self.projection = Some(projection);
+        self
+    }
+
+    /// how strings are compared, e.g. case-insensitively
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = Some(collation);
+        self
+    }
+
+    /// the index to use, by its keys or its name
+    pub fn hint(mut self, hint: Hint) -> Self {
+        self.hint = Some(hint);
+        self
+    }
+
+    /// documents fetched by each round trip of a cursor, see `MongoCRUD::stream`
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    fn sort_document(&self) -> Option<Document> {
+        match self.sort.is_empty() {
+            true => None,
+            false => Some(self.sort.clone()),
+        }
+    }
+
+    /// The filter document and the options of `find`
+    pub fn into_find(self) -> Result<(Document, FindOptions)> {
+        let options = FindOptions::builder()
+            .sort(self.sort_document())
+            .skip(self.skip)
+            .limit(self.limit.map(|v| i64::try_from(v).unwrap_or(i64::MAX)))
+            .projection(self.projection)
+            .collation(self.collation)
+            .hint(self.hint)
+            .batch_size(self.batch_size)
+            .build();
+
+        Ok((self.filter.into_document()?, options))
+    }
+
+    /// The filter document and the options of `find_one`, the limit is ignored
+    pub fn into_find_one(self) -> Result<(Document, FindOneOptions)> {
+        let options = FindOneOptions::builder()
+            .sort(self.sort_document())
+            .skip(self.skip)
+            .projection(self.projection)
+            .collation(self.collation)
+            .hint(self.hint)
+            .build();
+
+        Ok((self.filter.into_document()?, options))
+    }
+
+    /// The filter document and the options of `count_documents`, the sort and the projection
+    /// are ignored
+    pub fn into_count(self) -> Result<(Document, CountOptions)> {
+        let options = CountOptions::builder()
+            .skip(self.skip)
+            .limit(self.limit)
+            .collation(self.collation)
+            .hint(self.hint)
+            .build();
+
+        Ok((self.filter.into_document()?, options))
+    }
+}
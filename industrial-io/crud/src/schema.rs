@@ -0,0 +1,140 @@
+//! Schema
+//!
+//! `$jsonSchema` validators, so that documents written by other clients keep the shape the
+//! CRUD types can read. `#[derive(CRUD)]` generates `BaseCRUD::json_schema` from the fields:
+//!
+//! - `String`, `bool`, integers, floats, `ObjectId`, `DateTime`, `Document`, ... have their
+//!   BSON types, `Vec<T>` and tuples are arrays, `HashMap<String, V>` is an object
+//! - `Option<T>` accepts `null` as well, and is not required
+//! - a field with `#[serde(default)]` or `skip_serializing_if` is not required
+//! - `#[crud(nested)] T` is `T`'s own schema, see `BsonSchema`
+//! - any other type, e.g. an enum or a type alias, is not checked unless it is declared by
+//!   `#[crud(schema = "...")]`
+//!
+//! ```rust,ignore
+//! struct Company {
+//!     #[crud(schema = r#"{"enum": ["Company"]}"#)]
+//!     etype: EntityType,
+//!     ...
+//! }
+//!
+//! client
+//!     .set_validator_by_type::<Company>(ValidationLevel::Moderate, ValidationAction::Error)
+//!     .await?;
+//! ```
+
+use anyhow::{anyhow, Result};
+use bson::{doc, Bson, Document};
+use mongodb::options::CreateCollectionOptions;
+pub use mongodb::options::{ValidationAction, ValidationLevel};
+
+use crate::persistence::Scope;
+use crate::polymorphic::{discriminator_key, Polymorphic};
+use crate::{BaseCRUD, MongoClient};
+
+/// A type nested in a CRUD struct by `#[crud(nested)]`, whose schema is checked as well.
+/// Implemented by `#[derive(Patchable)]`.
+pub trait BsonSchema {
+    /// `$jsonSchema` of the type
+    fn bson_schema() -> Document;
+}
+
+/// `schema`, accepting `null` as well
+pub fn nullable(mut schema: Document) -> Document {
+    for key in ["bsonType", "enum"] {
+        let values = match schema.get(key) {
+            Some(Bson::Array(values)) => values.clone(),
+            Some(value) => vec![value.clone()],
+            None => continue,
+        };
+        let null = match key {
+            "bsonType" => Bson::String("null".to_owned()),
+            _ => Bson::Null,
+        };
+        if !values.contains(&null) {
+            schema.insert(key, values.into_iter().chain([null]).collect::<Vec<_>>());
+        }
+    }
+
+    schema
+}
+
+impl MongoClient {
+    /// Create `T`'s collection with `T::json_schema()` as its validator, or replace the validator
+    /// of the existing collection by `collMod`.
+    ///
+    /// A type sharing its collection with other types is validated by
+    /// `set_validator_by_polymorphic`, since the validator of a collection is unique.
+    pub async fn set_validator_by_type<T: BaseCRUD>(
+        &self,
+        level: ValidationLevel,
+        action: ValidationAction,
+    ) -> Result<()> {
+        if T::discriminator().is_some() {
+            return Err(anyhow!(
+                "`{}` shares its collection, use `set_validator_by_polymorphic`!",
+                std::any::type_name::<T>()
+            ));
+        }
+
+        let validator = doc! { "$jsonSchema": T::json_schema() };
+        self.set_validator(&Scope::of::<T>(), validator, level, action)
+            .await
+    }
+
+    /// Set the validator of the collection shared by the variants of `P`: a document must be
+    /// of one of the variants, and match the variant's schema.
+    pub async fn set_validator_by_polymorphic<P: Polymorphic>(
+        &self,
+        level: ValidationLevel,
+        action: ValidationAction,
+    ) -> Result<()> {
+        let variants = P::variants();
+        let key = discriminator_key::<P>(&variants)?;
+
+        let schemas = variants
+            .iter()
+            .map(|v| {
+                let (_, value) = v.scope.discriminator.unwrap();
+                doc! { "$and": [{ key: value }, { "$jsonSchema": v.schema.clone() }] }
+            })
+            .collect::<Vec<_>>();
+        let validator = doc! { "$or": schemas };
+
+        self.set_validator(&variants[0].scope, validator, level, action)
+            .await
+    }
+
+    async fn set_validator(
+        &self,
+        scope: &Scope,
+        validator: Document,
+        level: ValidationLevel,
+        action: ValidationAction,
+    ) -> Result<()> {
+        let database = self.scoped_database(scope);
+        let collection = scope.collection.unwrap_or(&self.collection);
+
+        let existing = database
+            .list_collection_names(doc! { "name": collection })
+            .await?;
+        if existing.is_empty() {
+            let options = CreateCollectionOptions::builder()
+                .validator(validator)
+                .validation_level(level)
+                .validation_action(action)
+                .build();
+            database.create_collection(collection, options).await?;
+        } else {
+            let command = doc! {
+                "collMod": collection,
+                "validator": validator,
+                "validationLevel": bson::to_bson(&level)?,
+                "validationAction": bson::to_bson(&action)?,
+            };
+            database.run_command(command, None).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+//! Transaction
+//!
+//! Multi-document transactions. `MongoClient::with_transaction` hands a client bound to a
+//! session over to the closure, every `MongoCRUD` method of that client, and every hook it
+//! calls, runs in the transaction:
+//!
+//! ```rust,ignore
+//! let company = client
+//!     .with_transaction(|tx| {
+//!         let (company, relationships) = (company.clone(), relationships.clone());
+//!         async move {
+//!             let company = tx.create(company).await?;
+//!             tx.create_many(relationships, true).await?;
+//!             Ok(company)
+//!         }
+//!     })
+//!     .await?;
+//! ```
+//!
+//! The transaction is committed when the closure returns `Ok`, and aborted when it returns an
+//! error. It is run again on a transient transaction error, e.g. a write conflict, and its
+//! commit is retried on an unknown commit result, for 2 minutes at most. Each retry waits a bit
+//! longer than the previous one, with some jitter so conflicting transactions drift apart. So
+//! the closure may be called more than once, and should not have other side effects.
+//!
+//! `with_transaction` of a client already in a transaction joins it. Transactions need a
+//! replica set, a single-node one is enough, e.g. `mongod --replSet rs0` and
+//! `rs.initiate()`, or a sharded cluster. On a standalone server, the closure runs without a
+//! transaction, see `MongoClient::supports_transactions`. Collections and indexes are not
+//! created in a transaction.
+//!
+//! A write error, e.g. one of the `errors` of `bulk_write`, aborts the transaction on the
+//! server, its commit fails. Streams of a transaction read their documents by batches, as
+//! other streams do, and lock the session only while getting the next document, so the
+//! transaction may write while streaming.
+
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use bson::{doc, Document};
+use mongodb::error::{TRANSIENT_TRANSACTION_ERROR, UNKNOWN_TRANSACTION_COMMIT_RESULT};
+use mongodb::options::{
+    CountOptions, FindOneAndDeleteOptions, FindOneAndUpdateOptions, FindOneOptions, FindOptions,
+};
+use mongodb::results::UpdateResult;
+use mongodb::{ClientSession, Collection, Database, SessionCursor};
+use tokio::sync::Mutex;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::persistence::{CrudStream, Scope};
+use crate::{BaseCRUD, MongoClient};
+
+/// how long a transaction is retried
+const RETRY_TIMEOUT: Duration = Duration::from_secs(120);
+/// delay before the first retry, doubled by every other one
+const RETRY_DELAY: Duration = Duration::from_millis(5);
+/// longest delay before a retry
+const MAX_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+impl MongoClient {
+    /// Run `f` in a transaction, with a client bound to the transaction's session.
+    /// See `crate::transaction`.
+    pub async fn with_transaction<F, Fut, R>(&self, mut f: F) -> Result<R>
+    where
+        F: FnMut(MongoClient) -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        if self.in_transaction() || !self.supports_transactions().await? {
+            return f(self.clone()).await;
+        }
+
+        let session = self.client.start_session(None).await?;
+        let session = Arc::new(Mutex::new(session));
+        let mut bound = self.clone();
+        bound.session = Some(session.clone());
+        let start = Instant::now();
+        let mut attempt = 0;
+
+        'transaction: loop {
+            session.lock().await.start_transaction(None).await?;
+            let value = match f(bound.clone()).await {
+                Ok(value) => value,
+                Err(e) => {
+                    // an error of the server may have aborted it already
+                    let _ = session.lock().await.abort_transaction().await;
+                    if has_label(&e, TRANSIENT_TRANSACTION_ERROR) && start.elapsed() < RETRY_TIMEOUT
+                    {
+                        tokio::time::sleep(backoff(&mut attempt)).await;
+                        continue 'transaction;
+                    }
+                    return Err(e);
+                }
+            };
+
+            loop {
+                let e = match session.lock().await.commit_transaction().await {
+                    Ok(()) => return Ok(value),
+                    Err(e) => e,
+                };
+                if start.elapsed() >= RETRY_TIMEOUT {
+                    return Err(e.into());
+                }
+                if e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) {
+                    tokio::time::sleep(backoff(&mut attempt)).await;
+                    continue;
+                }
+                if e.contains_label(TRANSIENT_TRANSACTION_ERROR) {
+                    tokio::time::sleep(backoff(&mut attempt)).await;
+                    continue 'transaction;
+                }
+                return Err(e.into());
+            }
+        }
+    }
+
+    /// Whether the client is bound to a transaction, by `with_transaction`
+    pub fn in_transaction(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Whether the deployment is a replica set or a sharded cluster, which support
+    /// transactions, rather than a standalone server. Asked once, and kept by the clones.
+    pub async fn supports_transactions(&self) -> Result<bool> {
+        let supported = self
+            .transactions
+            .get_or_try_init(|| async {
+                let reply = self
+                    .client
+                    .database("admin")
+                    .run_command(doc! { "hello": 1 }, None)
+                    .await?;
+                let sharded = reply.get_str("msg") == Ok("isdbgrid");
+                Ok::<_, anyhow::Error>(reply.contains_key("setName") || sharded)
+            })
+            .await?;
+
+        Ok(*supported)
+    }
+
+    /// `stored_schema` of `T`, in the client's transaction if any
+    pub(crate) fn session_schema<T: BaseCRUD>(&self) -> SessionSchema<'_> {
+        self.scoped_session_schema(&Scope::of::<T>())
+    }
+
+    /// `scoped_schema` of stored documents, in the client's transaction if any
+    pub(crate) fn scoped_session_schema(&self, scope: &Scope) -> SessionSchema<'_> {
+        SessionSchema {
+            collection: self.scoped_schema(scope),
+            session: self.session.as_ref(),
+        }
+    }
+
+    /// Run a command, in the client's transaction if any
+    pub(crate) async fn session_command(
+        &self,
+        database: Database,
+        command: Document,
+    ) -> Result<Document> {
+        let reply = match &self.session {
+            Some(session) => {
+                let mut session = session.lock().await;
+                database
+                    .run_command_with_session(command, None, &mut session)
+                    .await?
+            }
+            None => database.run_command(command, None).await?,
+        };
+
+        Ok(reply)
+    }
+}
+
+/// The delay before the next retry: `RETRY_DELAY` doubled at every attempt, up to
+/// `MAX_RETRY_DELAY`, then scaled by a random factor between 0.5 and 1
+fn backoff(attempt: &mut u32) -> Duration {
+    let delay = RETRY_DELAY
+        .saturating_mul(1 << (*attempt).min(16))
+        .min(MAX_RETRY_DELAY);
+    *attempt += 1;
+
+    // a fresh `RandomState` is randomly seeded, enough for a jitter
+    let random = RandomState::new().build_hasher().finish();
+    delay.mul_f64(0.5 + (random % 1024) as f64 / 2048.0)
+}
+
+/// whether an error, or one of its causes, is a MongoDB error with the label
+fn has_label(error: &anyhow::Error, label: &str) -> bool {
+    error
+        .chain()
+        .filter_map(|e| e.downcast_ref::<mongodb::error::Error>())
+        .any(|e| e.contains_label(label))
+}
+
+/// A collection of stored documents, whose operations run in a session if there is one
+pub(crate) struct SessionSchema<'a> {
+    collection: Collection<Document>,
+    session: Option<&'a Arc<Mutex<ClientSession>>>,
+}
+
+impl SessionSchema<'_> {
+    /// The documents found, read by batches. In a session, see `SessionStream`.
+    pub async fn find(
+        &self,
+        filter: Document,
+        options: impl Into<Option<FindOptions>>,
+    ) -> Result<CrudStream<'static, Document>> {
+        let session = match self.session {
+            Some(session) => session,
+            None => {
+                let cursor = self.collection.find(filter, options).await?;
+                return Ok(Box::pin(cursor.map(|v| Ok(v?))));
+            }
+        };
+
+        let cursor = self
+            .collection
+            .find_with_session(filter, options, &mut *session.lock().await)
+            .await?;
+        Ok(Box::pin(SessionStream::Idle(
+            Box::new(cursor),
+            session.clone(),
+        )))
+    }
+
+    pub async fn find_one(
+        &self,
+        filter: Document,
+        options: impl Into<Option<FindOneOptions>>,
+    ) -> Result<Option<Document>> {
+        let document = match self.session {
+            Some(session) => {
+                let mut session = session.lock().await;
+                self.collection
+                    .find_one_with_session(filter, options, &mut session)
+                    .await?
+            }
+            None => self.collection.find_one(filter, options).await?,
+        };
+
+        Ok(document)
+    }
+
+    pub async fn insert_one(&self, document: Document) -> Result<()> {
+        match self.session {
+            Some(session) => {
+                let mut session = session.lock().await;
+                self.collection
+                    .insert_one_with_session(document, None, &mut session)
+                    .await?;
+            }
+            None => {
+                self.collection.insert_one(document, None).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn update_one(&self, filter: Document, update: Document) -> Result<UpdateResult> {
+        let result = match self.session {
+            Some(session) => {
+                let mut session = session.lock().await;
+                self.collection
+                    .update_one_with_session(filter, update, None, &mut session)
+                    .await?
+            }
+            None => self.collection.update_one(filter, update, None).await?,
+        };
+
+        Ok(result)
+    }
+
+    pub async fn find_one_and_update(
+        &self,
+        filter: Document,
+        update: Document,
+        options: impl Into<Option<FindOneAndUpdateOptions>>,
+    ) -> Result<Option<Document>> {
+        let document = match self.session {
+            Some(session) => {
+                let mut session = session.lock().await;
+                self.collection
+                    .find_one_and_update_with_session(filter, update, options, &mut session)
+                    .await?
+            }
+            None => {
+                self.collection
+                    .find_one_and_update(filter, update, options)
+                    .await?
+            }
+        };
+
+        Ok(document)
+    }
+
+    pub async fn find_one_and_delete(
+        &self,
+        filter: Document,
+        options: impl Into<Option<FindOneAndDeleteOptions>>,
+    ) -> Result<Option<Document>> {
+        let document = match self.session {
+            Some(session) => {
+                let mut session = session.lock().await;
+                self.collection
+                    .find_one_and_delete_with_session(filter, options, &mut session)
+                    .await?
+            }
+            None => self.collection.find_one_and_delete(filter, options).await?,
+        };
+
+        Ok(document)
+    }
+
+    pub async fn count_documents(
+        &self,
+        filter: Document,
+        options: impl Into<Option<CountOptions>>,
+    ) -> Result<u64> {
+        let count = match self.session {
+            Some(session) => {
+                let mut session = session.lock().await;
+                self.collection
+                    .count_documents_with_session(filter, options, &mut session)
+                    .await?
+            }
+            None => self.collection.count_documents(filter, options).await?,
+        };
+
+        Ok(count)
+    }
+}
+
+/// the next document of a session cursor, with the cursor and its session
+type Next = Pin<
+    Box<
+        dyn Future<
+                Output = (
+                    Option<mongodb::error::Result<Document>>,
+                    Box<SessionCursor<Document>>,
+                    Arc<Mutex<ClientSession>>,
+                ),
+            > + Send,
+    >,
+>;
+
+/// A stream of a session cursor. The stream of the driver borrows the session for as long as
+/// it lives, this one locks the session for each document only, most of which come from the
+/// batch already read.
+enum SessionStream {
+    Idle(Box<SessionCursor<Document>>, Arc<Mutex<ClientSession>>),
+    Pending(Next),
+    Done,
+}
+
+impl Stream for SessionStream {
+    type Item = Result<Document>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match std::mem::replace(&mut *self, SessionStream::Done) {
+                SessionStream::Idle(mut cursor, session) => {
+                    *self = SessionStream::Pending(Box::pin(async move {
+                        let next = cursor.next(&mut *session.lock().await).await;
+                        (next, cursor, session)
+                    }));
+                }
+                SessionStream::Pending(mut next) => {
+                    return match next.as_mut().poll(cx) {
+                        Poll::Pending => {
+                            *self = SessionStream::Pending(next);
+                            Poll::Pending
+                        }
+                        Poll::Ready((Some(document), cursor, session)) => {
+                            *self = SessionStream::Idle(cursor, session);
+                            Poll::Ready(Some(document.map_err(Into::into)))
+                        }
+                        Poll::Ready((None, _, _)) => Poll::Ready(None),
+                    };
+                }
+                SessionStream::Done => return Poll::Ready(None),
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Validate
+//!
+//! Field validation, checked by `MongoCRUD::create` and `MongoCRUD::update` before writing,
+//! and by `MongoCRUD::patch` on the fields present in the patch:
+//!
+//! ```rust,ignore
+//! struct Company {
+//!     #[crud(validate(length(min = 1, max = 200)))]
+//!     name: String,
+//!     #[crud(validate(range(min = 0.0)))]
+//!     weight: Option<f64>,                            // `None` is not checked
+//!     #[crud(validate(regex = "^[A-Z]{2}$"))]
+//!     country: String,
+//!     #[crud(validate(custom = "check_code"))]       // `fn(&T) -> Result<(), String>`
+//!     code: String,
+//!     ...
+//! }
+//! ```
+//!
+//! A failed write returns `CrudError::Validation`, listing every failing field.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// A field that failed validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// stored name of the field
+    pub field: String,
+    pub message: String,
+}
+
+/// All the failing fields of a value
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl ValidationErrors {
+    /// record the result of a check of `field`
+    pub fn check(&mut self, field: &str, result: Result<(), String>) {
+        if let Err(message) = result {
+            self.0.push(FieldError {
+                field: field.to_owned(),
+                message,
+            });
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `Ok` if no field failed
+    pub fn into_result(self) -> Result<(), ValidationErrors> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(self),
+        }
+    }
+}
+
+impl Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let errors = self
+            .0
+            .iter()
+            .map(|e| format!("`{}` {}", e.field, e.message))
+            .collect::<Vec<_>>();
+        write!(f, "{}", errors.join("; "))
+    }
+}
+
+/// A value with a length, checked by `length(min = .., max = ..)`
+pub trait Length {
+    fn length(&self) -> usize;
+}
+
+impl Length for str {
+    /// number of characters, not bytes
+    fn length(&self) -> usize {
+        self.chars().count()
+    }
+}
+
+impl Length for String {
+    fn length(&self) -> usize {
+        self.as_str().length()
+    }
+}
+
+impl<T> Length for [T] {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T> Length for Vec<T> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<K, V> Length for HashMap<K, V> {
+    fn length(&self) -> usize {
+        self.len()
+    }
+}
+
+/// `length(min = .., max = ..)`
+pub fn length<T: Length + ?Sized>(
+    value: &T,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> Result<(), String> {
+    let len = value.length();
+    match (min, max) {
+        (Some(min), _) if len < min => Err(format!("length must be at least {min}, got {len}")),
+        (_, Some(max)) if len > max => Err(format!("length must be at most {max}, got {len}")),
+        _ => Ok(()),
+    }
+}
+
+/// `range(min = .., max = ..)`, both bounds are inclusive
+pub fn range<T: PartialOrd + Display>(
+    value: &T,
+    min: Option<T>,
+    max: Option<T>,
+) -> Result<(), String> {
+    match (min, max) {
+        (Some(min), _) if *value < min => Err(format!("must be at least {min}, got {value}")),
+        (_, Some(max)) if *value > max => Err(format!("must be at most {max}, got {value}")),
+        _ => Ok(()),
+    }
+}
+
+/// `regex = ".."`, compiled once on first use
+pub struct Pattern {
+    pattern: &'static str,
+    regex: OnceLock<Regex>,
+}
+
+impl Pattern {
+    pub const fn new(pattern: &'static str) -> Self {
+        Pattern {
+            pattern,
+            regex: OnceLock::new(),
+        }
+    }
+
+    pub fn check<T: AsRef<str> + ?Sized>(&self, value: &T) -> Result<(), String> {
+        // the pattern has been checked by `#[derive(CRUD)]`
+        let regex = self
+            .regex
+            .get_or_init(|| Regex::new(self.pattern).expect("invalid regex"));
+        match regex.is_match(value.as_ref()) {
+            true => Ok(()),
+            false => Err(format!("must match `{}`", self.pattern)),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use bson::oid::ObjectId;
+use bson::{doc, oid::ObjectId, DateTime, Uuid};
 use crud::*;
 use serde::{Deserialize, Serialize};
 
@@ -43,6 +43,7 @@ struct TestCompoundIndexCrud {
     #[crud(compound_index)]
     age: u32,
     content: Option<String>,
+    #[crud(version)]
     version: i32,
 }
 
@@ -52,3 +53,906 @@ fn test_custom_derive_compound_index() {
 
     println!("{:?}", indexes);
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct TestMixedIndexCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "asc")]
+    etype: String,
+    #[crud(compound_index(name = "by_source_target", order = 2))]
+    #[crud(compound_index(name = "by_target", dir = "desc"))]
+    target: String,
+    #[crud(compound_index(name = "by_source_target", order = 1, unique))]
+    source: String,
+    #[crud(single_index = "desc")]
+    weight: Option<f64>,
+}
+
+#[test]
+fn test_custom_derive_mixed_index() {
+    let indexes = TestMixedIndexCrud::show_indexes();
+
+    println!("{:?}", indexes);
+    assert_eq!(indexes.len(), 4);
+
+    let compound = indexes
+        .iter()
+        .find_map(|i| match i {
+            Index::Compound(c) if c.name.as_deref() == Some("by_source_target") => Some(c),
+            _ => None,
+        })
+        .unwrap();
+    let keys = compound
+        .keys
+        .iter()
+        .map(|(k, _)| k.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec!["source", "target"]);
+    assert!(compound.unique);
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(text_index(default_language = "english", language_override = "lang"))]
+struct TestTextIndexCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(text(weight = 10))]
+    name: String,
+    #[crud(text)]
+    description: Option<String>,
+    lang: Option<String>,
+}
+
+#[test]
+fn test_custom_derive_text_index() {
+    let indexes = TestTextIndexCrud::show_indexes();
+
+    println!("{:?}", indexes);
+    assert_eq!(indexes.len(), 1);
+
+    match indexes.iter().next().unwrap() {
+        Index::Text(t) => {
+            assert_eq!(
+                t.fields,
+                vec![
+                    ("name".to_owned(), Some(10)),
+                    ("description".to_owned(), None)
+                ]
+            );
+            assert_eq!(t.default_language.as_deref(), Some("english"));
+            assert_eq!(t.language_override.as_deref(), Some("lang"));
+        }
+        other => panic!("expected a text index, got {:?}", other),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct TestSessionCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "hashed")]
+    token: String,
+    #[crud(single_index(expire_after = "7d"))]
+    created_at: DateTime,
+    #[crud(single_index(unique, partial = "{\"code\": {\"$exists\": true}}"))]
+    code: Option<String>,
+    #[crud(single_index = "unique,sparse")]
+    email: Option<String>,
+    #[crud(single_index(dir = "2dsphere"))]
+    location: Option<bson::Document>,
+}
+
+#[test]
+fn test_custom_derive_special_index() {
+    let indexes = TestSessionCrud::show_indexes();
+
+    println!("{:?}", indexes);
+    assert_eq!(indexes.len(), 5);
+
+    let singles = indexes
+        .iter()
+        .filter_map(|i| match i {
+            Index::Single(s) => Some((s.key.0.as_str(), s)),
+            _ => None,
+        })
+        .collect::<std::collections::HashMap<_, _>>();
+
+    assert!(matches!(singles["token"].key.1, Dir::Hashed));
+    assert_eq!(
+        singles["created_at"].expire_after,
+        Some(std::time::Duration::from_secs(7 * 24 * 60 * 60))
+    );
+    assert!(singles["code"].unique);
+    assert_eq!(
+        singles["code"].partial,
+        Some(doc! { "code": { "$exists": true } })
+    );
+    assert!(singles["email"].unique && singles["email"].sparse);
+    assert!(matches!(singles["location"].key.1, Dir::Sphere2d));
+
+    // unique when present, `None` is written as `null`
+    let email = index_models_by_type::<TestSessionCrud>()
+        .into_iter()
+        .filter_map(|m| m.options)
+        .find(|o| o.name.as_deref() == Some("_crud_email"))
+        .unwrap();
+    assert_eq!(email.sparse, None);
+    let present = email.partial_filter_expression.unwrap();
+    let types = present
+        .get_document("email")
+        .unwrap()
+        .get_array("$type")
+        .unwrap();
+    assert!(types.contains(&"string".into()));
+    assert!(!types.contains(&"null".into()));
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct TestPosition {
+    position: (i64, i64),
+    vtype: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+struct TestRenamedCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "unique")]
+    full_name: String,
+    #[serde(rename = "n")]
+    #[crud(compound_index(name = "by_n_type", order = 1))]
+    nickname: String,
+    #[crud(path = "option.position", single_index = "asc")]
+    #[crud(compound_index(name = "by_n_type", order = 2, path = "option.vtype"))]
+    option: TestPosition,
+}
+
+#[test]
+fn test_custom_derive_renamed_index() {
+    let indexes = TestRenamedCrud::show_indexes();
+
+    println!("{:?}", indexes);
+
+    let keys = indexes
+        .iter()
+        .flat_map(|i| match i {
+            Index::Single(s) => vec![s.key.0.clone()],
+            Index::Compound(c) => c.keys.iter().map(|(k, _)| k.clone()).collect(),
+            Index::Text(t) => t.fields.iter().map(|(k, _)| k.clone()).collect(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        vec!["fullName", "option.position", "n", "option.vtype"]
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(collection = "companies", database = "iio")]
+struct TestRoutedCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    name: String,
+}
+
+#[test]
+fn test_custom_derive_route() {
+    assert_eq!(TestRoutedCrud::collection_name(), Some("companies"));
+    assert_eq!(TestRoutedCrud::database_name(), Some("iio"));
+
+    // falls back to `MongoClient`'s database and collection
+    assert_eq!(TestRenamedCrud::collection_name(), None);
+    assert_eq!(TestRenamedCrud::database_name(), None);
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct TestStockCrud {
+    /// stock code
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[crud(id)]
+    code: Option<String>,
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct TestUuidCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[crud(id(generate = "uuid_v7"))]
+    id: Option<Uuid>,
+    name: String,
+}
+
+#[test]
+fn test_custom_derive_id_type() {
+    let mut stock = TestStockCrud {
+        code: Some("600519".to_string()),
+        name: "Kweichow Moutai".to_string(),
+    };
+    assert_eq!(stock.get_id(), Some("600519".to_string()));
+    stock.mutate_id("000858".to_string()).unwrap();
+    assert_eq!(stock.get_id(), Some("000858".to_string()));
+    // a stock code cannot be generated
+    assert_eq!(TestStockCrud::new_id(), None);
+
+    // `ObjectId` is generated by default
+    assert!(TestNoneIndexCrud::new_id().is_some());
+
+    let first = TestUuidCrud::new_id().unwrap();
+    let second = TestUuidCrud::new_id().unwrap();
+    assert_eq!(first.bytes()[6] >> 4, 7);
+    assert_ne!(first, second);
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, Patchable)]
+struct TestVertexOption {
+    position: (i64, i64),
+    #[serde(rename = "type")]
+    vtype: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+struct TestPatchCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    full_name: String,
+    group: Option<String>,
+    #[crud(nested)]
+    option: TestVertexOption,
+}
+
+#[test]
+fn test_custom_derive_patch() {
+    let patch = TestPatchCrudPatch::default();
+    assert_eq!(patch.to_update().unwrap(), doc! {});
+
+    let patch = TestPatchCrudPatch {
+        full_name: Some("patched".to_string()),
+        group: Some(None),
+        option: Some(TestVertexOptionPatch {
+            position: Some((10, 20)),
+            ..Default::default()
+        }),
+    };
+    assert_eq!(
+        patch.to_update().unwrap(),
+        doc! {
+            "$set": { "fullName": "patched", "option.position": [10_i64, 20_i64] },
+            "$unset": { "group": "" },
+        }
+    );
+
+    let patch = TestPatchCrudPatch {
+        group: Some(Some("a".to_string())),
+        option: Some(TestVertexOptionPatch {
+            vtype: Some("custom".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    assert_eq!(
+        patch.to_update().unwrap(),
+        doc! { "$set": { "group": "a", "option.type": "custom" } }
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+struct TestTimestampCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    name: String,
+    #[crud(created_at)]
+    created_at: Option<DateTime>,
+    #[crud(updated_at)]
+    modified: DateTime,
+}
+
+#[test]
+fn test_custom_derive_timestamps() {
+    assert_eq!(TestTimestampCrud::created_at_key(), Some("createdAt"));
+    assert_eq!(TestTimestampCrud::updated_at_key(), Some("modified"));
+    assert_eq!(TestNoneIndexCrud::updated_at_key(), None);
+
+    let clock = FixedClock::new(DateTime::from_millis(1_000));
+    let mut value = TestTimestampCrud {
+        id: None,
+        name: "stamped".to_string(),
+        created_at: None,
+        modified: DateTime::from_millis(0),
+    };
+    value.stamp_created(clock.now());
+    value.stamp_updated(clock.now());
+    assert_eq!(value.created_at, Some(DateTime::from_millis(1_000)));
+    assert_eq!(value.modified, DateTime::from_millis(1_000));
+
+    clock.set(DateTime::from_millis(2_000));
+    value.stamp_updated(clock.now());
+    assert_eq!(value.created_at, Some(DateTime::from_millis(1_000)));
+    assert_eq!(value.modified, DateTime::from_millis(2_000));
+
+    // timestamps are not patched
+    let patch = TestTimestampCrudPatch {
+        name: Some("patched".to_string()),
+    };
+    assert_eq!(
+        patch.to_update().unwrap(),
+        doc! { "$set": { "name": "patched" } }
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct TestSoftDeleteCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "unique")]
+    name: String,
+    #[crud(single_index = "unique,sparse")]
+    email: Option<String>,
+    #[crud(single_index = "asc")]
+    group: Option<String>,
+    #[crud(soft_delete)]
+    deleted_at: Option<DateTime>,
+}
+
+#[test]
+fn test_custom_derive_soft_delete() {
+    assert_eq!(TestSoftDeleteCrud::soft_delete_key(), Some("deleted_at"));
+    assert_eq!(TestNoneIndexCrud::soft_delete_key(), None);
+
+    let models = index_models_by_type::<TestSoftDeleteCrud>();
+    let options = models
+        .iter()
+        .map(|m| m.options.clone().unwrap())
+        .map(|o| (o.name.clone().unwrap(), o))
+        .collect::<std::collections::HashMap<_, _>>();
+
+    // unique indexes only cover live documents
+    let name = &options["_crud_name"];
+    assert_eq!(
+        name.partial_filter_expression,
+        Some(doc! { "deleted_at": { "$type": "null" } })
+    );
+    // `sparse` cannot be used along with a partial filter
+    let email = &options["_crud_email"];
+    assert_eq!(email.sparse, None);
+    let partial = email.partial_filter_expression.clone().unwrap();
+    assert!(partial.get_document("email").unwrap().contains_key("$type"));
+    assert_eq!(
+        partial.get_document("deleted_at").unwrap(),
+        &doc! { "$type": "null" }
+    );
+    // not unique, nothing changes
+    assert_eq!(options["_crud_group"].partial_filter_expression, None);
+
+    // the soft delete field is not patched
+    let patch = TestSoftDeleteCrudPatch {
+        name: Some("patched".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        patch.to_update().unwrap(),
+        doc! { "$set": { "name": "patched" } }
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+struct TestVersionCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    name: String,
+    #[crud(version)]
+    row_version: u64,
+}
+
+#[test]
+fn test_custom_derive_version() {
+    assert_eq!(TestVersionCrud::version_key(), Some("rowVersion"));
+    assert_eq!(TestCompoundIndexCrud::version_key(), Some("version"));
+    assert_eq!(TestNoneIndexCrud::version_key(), None);
+
+    let mut value = TestVersionCrud {
+        id: None,
+        name: "versioned".to_string(),
+        row_version: 3,
+    };
+    assert_eq!(value.get_version(), Some(3));
+    value.set_version(4);
+    assert_eq!(value.row_version, 4);
+
+    // the version is not patched
+    let patch = TestVersionCrudPatch {
+        name: Some("patched".to_string()),
+    };
+    assert_eq!(
+        patch.to_update().unwrap(),
+        doc! { "$set": { "name": "patched" } }
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(collection = "graph", discriminator = "etype", value = "Company")]
+struct TestCompanyVariant {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "unique")]
+    name: String,
+}
+
+#[test]
+fn test_custom_derive_discriminator() {
+    assert_eq!(
+        TestCompanyVariant::discriminator(),
+        Some(("etype", "Company"))
+    );
+    assert_eq!(TestCompanyVariant::collection_name(), Some("graph"));
+    assert_eq!(TestNoneIndexCrud::discriminator(), None);
+
+    // indexes of a variant are named after it, and only cover its documents
+    let models = index_models_by_type::<TestCompanyVariant>();
+    let options = models[0].options.clone().unwrap();
+    assert_eq!(options.name, Some("_crud_Company_name".to_string()));
+    assert_eq!(
+        options.partial_filter_expression,
+        Some(doc! { "etype": "Company" })
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+struct TestVersioned<T> {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(nested)]
+    data: T,
+    #[crud(single_index = "asc")]
+    label: Option<String>,
+    #[crud(version)]
+    version: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct TestBorrowedCrud<'a, I>
+where
+    I: Clone,
+{
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<I>,
+    name: std::borrow::Cow<'a, str>,
+}
+
+#[test]
+fn test_custom_derive_generics() {
+    assert_eq!(
+        TestVersioned::<TestVertexOption>::version_key(),
+        Some("version")
+    );
+    assert_eq!(TestVersioned::<TestVertexOption>::show_indexes().0.len(), 1);
+
+    let patch = TestVersionedPatch::<TestVertexOption> {
+        data: Some(TestVertexOptionPatch {
+            position: Some((1, 2)),
+            ..Default::default()
+        }),
+        label: Some(None),
+        ..Default::default()
+    };
+    assert_eq!(
+        patch.to_update().unwrap(),
+        doc! { "$set": { "data.position": [1_i64, 2_i64] }, "$unset": { "label": "" } }
+    );
+
+    let mut borrowed = TestBorrowedCrud::<String> {
+        id: None,
+        name: "borrowed".into(),
+    };
+    borrowed.mutate_id("stock".to_string()).unwrap();
+    assert_eq!(borrowed.get_id(), Some("stock".to_string()));
+
+    fn assert_crud<T>()
+    where
+        MongoClient: MongoCRUD<T>,
+        T: Send + Sync + Clone + Serialize + serde::de::DeserializeOwned + Unpin + BaseCRUD + Hooks,
+    {
+    }
+    assert_crud::<TestVersioned<TestVertexOption>>();
+    assert_crud::<TestBorrowedCrud<'static, Uuid>>();
+}
+
+/// no import, every path of the generated code is resolved through `crud`
+mod self_contained {
+    #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, crud::Patchable)]
+    pub struct TestSelfContainedOption {
+        pub position: (i64, i64),
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, crud::CRUD)]
+    #[crud(collection = "graph", discriminator = "etype", value = "SelfContained")]
+    pub struct TestSelfContainedCrud {
+        #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+        #[crud(id(generate = "uuid_v4"))]
+        pub id: Option<crud::__private::bson::Uuid>,
+        #[crud(single_index(unique, partial = "{\"code\": {\"$exists\": true}}"))]
+        pub code: String,
+        #[crud(nested)]
+        pub option: TestSelfContainedOption,
+        #[crud(created_at)]
+        pub created_at: Option<crud::__private::bson::DateTime>,
+        #[crud(soft_delete)]
+        pub deleted_at: Option<crud::__private::bson::DateTime>,
+        #[crud(version)]
+        pub version: i64,
+    }
+}
+
+/// `crud` renamed by the consuming crate
+mod renamed {
+    use crud as storage;
+
+    #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize, storage::Patchable)]
+    #[crud(crate = "storage")]
+    pub struct TestRenamedOption {
+        pub position: (i64, i64),
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, storage::CRUD)]
+    #[crud(crate = "storage")]
+    pub struct TestRenamedPathCrud {
+        #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+        pub id: Option<storage::__private::bson::oid::ObjectId>,
+        #[crud(nested)]
+        pub option: TestRenamedOption,
+    }
+}
+
+#[test]
+fn test_custom_derive_crate_path() {
+    use renamed::*;
+    use self_contained::*;
+
+    assert!(TestSelfContainedCrud::new_id().is_some());
+    assert_eq!(TestSelfContainedCrud::show_indexes().0.len(), 1);
+    assert_eq!(TestSelfContainedCrud::version_key(), Some("version"));
+
+    let patch = TestRenamedPathCrudPatch {
+        option: Some(TestRenamedOptionPatch {
+            position: Some((1, 2)),
+        }),
+    };
+    assert_eq!(
+        patch.to_update().unwrap(),
+        doc! { "$set": { "option.position": [1_i64, 2_i64] } }
+    );
+}
+
+#[test]
+fn test_custom_derive_fields() {
+    let fields = TestPatchCrud::fields();
+
+    // stored names, following `rename_all`
+    assert_eq!(fields.full_name().path(), "fullName");
+    assert_eq!(fields.option().position().path(), "option.position");
+    assert_eq!(TestStockCrud::fields().code().path(), "_id");
+
+    let filter = fields
+        .full_name()
+        .eq("x")
+        .and(fields.option().vtype().is_in(["a", "b"]))
+        .or(!fields.group().exists(false));
+    assert_eq!(
+        filter.into_document().unwrap(),
+        doc! {
+            "$or": [
+                { "$and": [
+                    { "fullName": { "$eq": "x" } },
+                    { "option.type": { "$in": ["a", "b"] } },
+                ] },
+                { "$nor": [{ "group": { "$exists": false } }] },
+            ]
+        }
+    );
+
+    // an `Option` field is compared with its inner value
+    let filter = fields.group().ne("g").and(Filter::all());
+    assert_eq!(
+        filter.into_document().unwrap(),
+        doc! { "group": { "$ne": "g" } }
+    );
+    assert_eq!(
+        fields.group().is_null().into_document().unwrap(),
+        doc! { "group": null }
+    );
+
+    // a value that cannot be stored is reported by `into_document`
+    let filter = TestVersionCrud::fields().row_version().gt(u64::MAX);
+    assert!(filter.clone().into_document().is_err());
+    // even when the other side of `or` matches every document
+    assert!(filter.clone().or(Filter::all()).into_document().is_err());
+    assert!(Filter::all().or(filter).into_document().is_err());
+
+    // generic structs
+    let fields = TestVersioned::<TestVertexOption>::fields();
+    assert_eq!(fields.data().position().path(), "data.position");
+}
+
+#[test]
+fn test_custom_derive_query() {
+    let fields = TestPatchCrud::fields();
+
+    let query = Query::new(fields.group().eq("g"))
+        .sort(&fields.full_name(), Order::Asc)
+        .sort(&fields.option().position(), Order::Desc)
+        .skip(20)
+        .limit(10)
+        .projection(doc! { "fullName": 1 })
+        .hint(Hint::Name("group_1".to_string()))
+        .batch_size(100);
+    let (filter, options) = query.clone().into_find().unwrap();
+    assert_eq!(filter, doc! { "group": { "$eq": "g" } });
+    assert_eq!(
+        options.sort,
+        Some(doc! { "fullName": 1, "option.position": -1 })
+    );
+    assert_eq!(options.skip, Some(20));
+    assert_eq!(options.limit, Some(10));
+    assert_eq!(options.projection, Some(doc! { "fullName": 1 }));
+    assert_eq!(options.batch_size, Some(100));
+
+    // counted within the skip and the limit, regardless of the order
+    let (_, options) = query.into_count().unwrap();
+    assert_eq!((options.skip, options.limit), (Some(20), Some(10)));
+
+    // a filter is a query without options
+    let (filter, options) = Query::from(fields.group().is_null()).into_find().unwrap();
+    assert_eq!(filter, doc! { "group": null });
+    assert_eq!(options.sort, None);
+    assert_eq!(options.limit, None);
+}
+
+fn check_even(value: &i64) -> Result<(), String> {
+    match value % 2 {
+        0 => Ok(()),
+        _ => Err("must be even".to_string()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+struct TestValidateCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(validate(length(min = 1, max = 5)))]
+    full_name: String,
+    #[crud(validate(range(min = 0.0, max = 1.0)))]
+    weight: Option<f64>,
+    #[crud(validate(range(min = "-90", max = 90), custom = "check_even"))]
+    latitude: i64,
+    #[crud(validate(regex = "^[A-Z]{2}$"))]
+    country: String,
+}
+
+#[test]
+fn test_custom_derive_validate() {
+    let mut value = TestValidateCrud {
+        id: None,
+        full_name: "name".to_string(),
+        weight: None,
+        latitude: -90,
+        country: "CN".to_string(),
+    };
+    assert_eq!(value.validate(), Ok(()));
+    // no validation, nothing is checked
+    let unchecked = TestNoneIndexCrud {
+        id: None,
+        name: String::new(),
+        content: None,
+    };
+    assert_eq!(unchecked.validate(), Ok(()));
+
+    value.full_name = String::new();
+    value.weight = Some(-0.5);
+    value.latitude = 91;
+    value.country = "cn".to_string();
+    let errors = value.validate().unwrap_err();
+
+    let failed = errors
+        .0
+        .iter()
+        .map(|e| (e.field.as_str(), e.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failed,
+        vec![
+            ("fullName", "length must be at least 1, got 0"),
+            ("weight", "must be at least 0, got -0.5"),
+            ("latitude", "must be at most 90, got 91"),
+            ("latitude", "must be even"),
+            ("country", "must match `^[A-Z]{2}$`"),
+        ]
+    );
+
+    // a patch checks its present fields only
+    let patch = TestValidateCrudPatch {
+        full_name: Some("too long".to_string()),
+        weight: Some(Some(2.0)),
+        ..Default::default()
+    };
+    let mut errors = ValidationErrors::default();
+    patch.validate("", &mut errors);
+    let failed = errors
+        .0
+        .iter()
+        .map(|e| e.field.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(failed, vec!["fullName", "weight"]);
+    let mut errors = ValidationErrors::default();
+    TestValidateCrudPatch {
+        weight: Some(None),
+        ..Default::default()
+    }
+    .validate("", &mut errors);
+    assert!(errors.is_empty());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+struct TestSchemaCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    full_name: String,
+    tags: Vec<String>,
+    weight: Option<f64>,
+    #[serde(default)]
+    count: i64,
+    created_at: DateTime,
+    #[crud(nested)]
+    option: TestVertexOption,
+    #[crud(schema = r#"{"enum": ["Left", "Right"]}"#)]
+    side: Option<String>,
+}
+
+#[test]
+fn test_custom_derive_schema() {
+    assert_eq!(
+        TestVertexOption::bson_schema(),
+        doc! {
+            "bsonType": "object",
+            "required": ["position", "type"],
+            "properties": {
+                "position": {
+                    "bsonType": "array",
+                    "minItems": 2_i64,
+                    "maxItems": 2_i64,
+                    "items": [
+                        { "bsonType": ["int", "long"] },
+                        { "bsonType": ["int", "long"] },
+                    ],
+                },
+                "type": { "bsonType": "string" },
+            },
+        }
+    );
+
+    assert_eq!(
+        TestSchemaCrud::json_schema(),
+        doc! {
+            "bsonType": "object",
+            "required": ["fullName", "tags", "createdAt", "option"],
+            "properties": {
+                "_id": { "bsonType": ["objectId", "null"] },
+                "fullName": { "bsonType": "string" },
+                "tags": { "bsonType": "array", "items": { "bsonType": "string" } },
+                "weight": { "bsonType": ["double", "int", "long", "null"] },
+                "count": { "bsonType": ["int", "long"] },
+                "createdAt": { "bsonType": "date" },
+                "option": TestVertexOption::bson_schema(),
+                "side": { "enum": ["Left", "Right", null] },
+            },
+        }
+    );
+
+    // a type unknown to the derive is required, but its content is not checked
+    let schema = TestRenamedCrud::json_schema();
+    assert_eq!(
+        schema.get_document("properties").unwrap().get("option"),
+        Some(&bson::Bson::Document(doc! {}))
+    );
+    assert_eq!(
+        schema.get_array("required").unwrap(),
+        &vec!["fullName".into(), "n".into(), "option".into()]
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+struct TestEncryptCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(encrypt)]
+    contract_terms: Option<String>,
+    #[crud(encrypt(deterministic), single_index = "unique")]
+    code: String,
+    name: String,
+}
+
+#[test]
+fn test_custom_derive_encrypt() {
+    assert_eq!(
+        TestEncryptCrud::encrypted_fields(),
+        &[
+            EncryptedField {
+                key: "contractTerms",
+                mode: EncryptMode::Random,
+            },
+            EncryptedField {
+                key: "code",
+                mode: EncryptMode::Deterministic,
+            },
+        ]
+    );
+    assert!(TestNoneIndexCrud::encrypted_fields().is_empty());
+
+    // stored as ciphertexts
+    let schema = TestEncryptCrud::json_schema();
+    let properties = schema.get_document("properties").unwrap();
+    assert_eq!(
+        properties.get_document("contractTerms").unwrap(),
+        &doc! { "bsonType": ["binData", "null"] }
+    );
+    assert_eq!(
+        properties.get_document("code").unwrap(),
+        &doc! { "bsonType": "binData" }
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[serde(rename_all = "camelCase")]
+#[crud(projection(
+    name = "TestProjectionSummary",
+    fields("full_name", "tags"),
+    derive(Debug, PartialEq)
+))]
+#[crud(projection(name = "TestProjectionName", fields("full_name")))]
+struct TestProjectionCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    full_name: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    content: Option<String>,
+}
+
+#[test]
+fn test_custom_derive_projection() {
+    // stored keys, the id is always returned
+    assert_eq!(
+        TestProjectionSummary::projection(),
+        doc! { "fullName": 1, "tags": 1 }
+    );
+    assert_eq!(TestProjectionName::projection(), doc! { "fullName": 1 });
+
+    // a projected document is read by the projection
+    let value = TestProjectionCrud {
+        id: Some(ObjectId::new()),
+        full_name: "Acme".to_string(),
+        tags: vec!["steel".to_string()],
+        content: Some("unread".to_string()),
+    };
+    let document = bson::to_document(&value).unwrap();
+    let summary: TestProjectionSummary = bson::from_document(document).unwrap();
+    assert_eq!(
+        summary,
+        TestProjectionSummary {
+            id: value.id,
+            full_name: value.full_name.clone(),
+            tags: value.tags.clone(),
+        }
+    );
+
+    let name: TestProjectionName =
+        bson::from_document(doc! { "_id": value.id.unwrap(), "fullName": "Acme" }).unwrap();
+    assert_eq!(name.full_name, "Acme");
+}
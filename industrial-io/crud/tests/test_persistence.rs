@@ -1,4 +1,4 @@
-use bson::oid::ObjectId;
+use bson::{doc, oid::ObjectId, Document};
 use crud::*;
 use serde::{Deserialize, Serialize};
 
@@ -14,9 +14,20 @@ struct TestSingleIndexCrud {
     #[crud(single_index = "unique,text")]
     name: String,
     content: Option<String>,
+    #[crud(version)]
     version: i32,
 }
 
+/// id of the `TestSingleIndexCrud` named `name`
+async fn id_of(client: &MongoClient, name: &str) -> ObjectId {
+    let value: TestSingleIndexCrud = client
+        .find_one(TestSingleIndexCrud::fields().name().eq(name))
+        .await
+        .unwrap()
+        .unwrap();
+    value.idx.unwrap()
+}
+
 #[tokio::test]
 async fn test_mongo_client() {
     let client = MongoClient::new(URI, DB, CL).await.unwrap();
@@ -59,9 +70,9 @@ async fn test_curd_operations() {
     println!("read: {:?}", read);
     assert_eq!(create, read);
 
+    // the version is incremented by `update`
     let mut update_value = read;
     update_value.name = "update".to_string();
-    update_value.version += 1;
 
     let update = client.update(update_value).await;
     assert!(update.is_ok());
@@ -88,6 +99,7 @@ struct TestCompoundIndexCrud {
     #[crud(compound_index)]
     age: u32,
     content: Option<String>,
+    #[crud(version)]
     version: i32,
 }
 
@@ -116,9 +128,8 @@ async fn test_indexes_operations() {
     let create_compound_index = create_compound_index.unwrap();
     println!("create indexes: {:?}", create_compound_index);
 
-    // we know `TestCompoundIndexCrud` has a compound index, and currently single_index
-    // and compound_index are not coexisted, so we are sure the first index in the result
-    // is the compound index
+    // we know `TestCompoundIndexCrud` only has a compound index, so we are sure the first
+    // index in the result is the compound index
     let index_name = create_compound_index;
     assert_eq!(index_name.len(), 1);
     let index_name = index_name[0].clone();
@@ -138,3 +149,1313 @@ async fn test_indexes_operations() {
     assert_eq!(indexes_names.len(), 1);
     println!("indexes names: {:?}", indexes_names);
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "dev_sparse")]
+struct TestSparseCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "unique,sparse")]
+    email: Option<String>,
+}
+
+#[tokio::test]
+async fn test_unique_sparse_index() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+    client
+        .create_indexes_by_type::<TestSparseCrud>()
+        .await
+        .unwrap();
+    let _: anyhow::Result<BulkResult<ObjectId>> =
+        client.delete_many(Filter::<TestSparseCrud>::all()).await;
+
+    // `None` is written as `null`, which is not covered by the index
+    let none = TestSparseCrud {
+        id: None,
+        email: None,
+    };
+    assert!(client.create(none.clone()).await.is_ok());
+    assert!(client.create(none).await.is_ok());
+
+    let some = TestSparseCrud {
+        id: None,
+        email: Some("sparse@example.com".to_string()),
+    };
+    assert!(client.create(some.clone()).await.is_ok());
+    assert!(client.create(some).await.is_err());
+
+    let read: Vec<TestSparseCrud> = client.read_by_filter(doc! { "email": null }).await.unwrap();
+    assert_eq!(read.len(), 2);
+    let delete: anyhow::Result<BulkResult<ObjectId>> =
+        client.delete_many(Filter::<TestSparseCrud>::all()).await;
+    assert!(delete.is_ok());
+}
+
+#[tokio::test]
+async fn test_search_text() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    // `TestSingleIndexCrud.name` is marked as `text`
+    let create_indexes = client.create_indexes_by_type::<TestSingleIndexCrud>().await;
+    assert!(create_indexes.is_ok());
+
+    let value = TestSingleIndexCrud {
+        idx: None,
+        name: "industrial chain of lithium battery".to_string(),
+        content: None,
+        version: 1,
+    };
+    let create = client.create(value).await.unwrap();
+
+    let search: anyhow::Result<Vec<TestSingleIndexCrud>> = client.search_text("battery").await;
+    assert!(search.is_ok());
+    let search = search.unwrap();
+    println!("search: {:?}", search);
+    assert!(search.iter().any(|v| v.idx == create.idx));
+
+    let delete: anyhow::Result<Option<TestSingleIndexCrud>> =
+        client.delete(create.idx.unwrap()).await;
+    assert!(delete.is_ok());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "dev_routed")]
+struct TestRoutedCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    name: String,
+}
+
+#[tokio::test]
+async fn test_collection_routing() {
+    // one shared client, each type is stored in its own collection
+    let client = std::sync::Arc::new(MongoClient::new(URI, DB, CL).await.unwrap());
+
+    let routed = {
+        let client = client.clone();
+        tokio::spawn(async move {
+            let value = TestRoutedCrud {
+                id: None,
+                name: "routed".to_string(),
+            };
+            client.create(value).await
+        })
+    };
+    let unrouted = {
+        let client = client.clone();
+        tokio::spawn(async move {
+            let value = TestSingleIndexCrud {
+                idx: None,
+                name: "unrouted".to_string(),
+                content: None,
+                version: 1,
+            };
+            client.create(value).await
+        })
+    };
+    let routed = routed.await.unwrap().unwrap();
+    let unrouted = unrouted.await.unwrap().unwrap();
+
+    let collections = client.show_collections().await.unwrap();
+    assert!(collections.contains(&"dev_routed".to_string()));
+    assert_eq!(client.collection, CL);
+
+    let read: anyhow::Result<Option<TestRoutedCrud>> = client.read(routed.id.unwrap()).await;
+    assert_eq!(read.unwrap(), Some(routed.clone()));
+    // not in the client's own collection
+    let read: anyhow::Result<Option<TestSingleIndexCrud>> = client.read(routed.id.unwrap()).await;
+    assert_eq!(read.unwrap(), None);
+
+    let delete: anyhow::Result<Option<TestRoutedCrud>> = client.delete(routed.id.unwrap()).await;
+    assert!(delete.is_ok());
+    let delete: anyhow::Result<Option<TestSingleIndexCrud>> =
+        client.delete(unrouted.idx.unwrap()).await;
+    assert!(delete.is_ok());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "dev_stock")]
+struct TestStockCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[crud(id)]
+    code: Option<String>,
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "dev_uuid")]
+struct TestUuidCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    #[crud(id(generate = "uuid_v4"))]
+    id: Option<bson::Uuid>,
+    name: String,
+}
+
+#[tokio::test]
+async fn test_id_types() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    // the given stock code is kept
+    let stock = TestStockCrud {
+        code: Some("600519".to_string()),
+        name: "Kweichow Moutai".to_string(),
+    };
+    let create = client.create(stock.clone()).await.unwrap();
+    assert_eq!(create, stock);
+
+    let read = client.read("600519".to_string()).await.unwrap();
+    assert_eq!(read, Some(stock));
+
+    // a stock code cannot be generated
+    let no_code = TestStockCrud {
+        code: None,
+        name: "unknown".to_string(),
+    };
+    assert!(client.create(no_code).await.is_err());
+
+    let delete: anyhow::Result<Option<TestStockCrud>> = client.delete("600519".to_string()).await;
+    assert!(delete.unwrap().is_some());
+
+    // uuid generated by the client
+    let value = TestUuidCrud {
+        id: None,
+        name: "uuid".to_string(),
+    };
+    let create = client.create(value).await.unwrap();
+    let id = create.id.unwrap();
+
+    let read: Vec<TestUuidCrud> = client.read_many(vec![id]).await.unwrap();
+    assert_eq!(read, vec![create]);
+
+    let delete: anyhow::Result<Option<TestUuidCrud>> = client.delete(id).await;
+    assert!(delete.unwrap().is_some());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Patchable)]
+struct TestVertexOption {
+    position: (i64, i64),
+    vtype: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "dev_patch")]
+struct TestPatchCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    name: String,
+    group: Option<String>,
+    #[crud(nested)]
+    option: TestVertexOption,
+}
+
+#[tokio::test]
+async fn test_patch() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    let value = TestPatchCrud {
+        id: None,
+        name: "patch".to_string(),
+        group: Some("group".to_string()),
+        option: TestVertexOption {
+            position: (0, 0),
+            vtype: "default".to_string(),
+        },
+    };
+    let create = client.create(value).await.unwrap();
+    let id = create.id.unwrap();
+
+    // only `option.position` is moved, `group` is cleared
+    let patch = TestPatchCrudPatch {
+        group: Some(None),
+        option: Some(TestVertexOptionPatch {
+            position: Some((10, 20)),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let patched = client.patch(id, patch).await.unwrap().unwrap();
+    assert_eq!(patched.name, "patch");
+    assert_eq!(patched.group, None);
+    assert_eq!(patched.option.position, (10, 20));
+    assert_eq!(patched.option.vtype, "default");
+
+    // a missing document
+    let missing = client
+        .patch(ObjectId::new(), TestPatchCrudPatch::default())
+        .await
+        .unwrap();
+    assert_eq!(missing, None);
+
+    let delete: anyhow::Result<Option<TestPatchCrud>> = client.delete(id).await;
+    assert!(delete.is_ok());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "dev_timestamp")]
+struct TestTimestampCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    name: String,
+    #[crud(created_at)]
+    created_at: Option<bson::DateTime>,
+    #[crud(updated_at, single_index = "asc")]
+    updated_at: Option<bson::DateTime>,
+}
+
+#[tokio::test]
+async fn test_timestamps() {
+    let clock = std::sync::Arc::new(FixedClock::new(bson::DateTime::from_millis(1_000)));
+    let mut client = MongoClient::new(URI, DB, CL).await.unwrap();
+    client.set_clock(clock.clone());
+    client
+        .create_indexes_by_type::<TestTimestampCrud>()
+        .await
+        .unwrap();
+
+    let value = TestTimestampCrud {
+        id: None,
+        name: "stamped".to_string(),
+        created_at: None,
+        updated_at: None,
+    };
+    let create = client.create(value).await.unwrap();
+    assert_eq!(create.created_at, Some(bson::DateTime::from_millis(1_000)));
+    assert_eq!(create.updated_at, Some(bson::DateTime::from_millis(1_000)));
+
+    // `created_at` is kept even if the value doesn't carry it
+    clock.set(bson::DateTime::from_millis(2_000));
+    let mut update_value = create.clone();
+    update_value.created_at = None;
+    let update = client.update(update_value).await.unwrap();
+    assert_eq!(update.updated_at, Some(bson::DateTime::from_millis(2_000)));
+
+    let read: TestTimestampCrud = client.read(create.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(read.created_at, Some(bson::DateTime::from_millis(1_000)));
+    assert_eq!(read.updated_at, Some(bson::DateTime::from_millis(2_000)));
+
+    // patched documents are stamped as well
+    clock.set(bson::DateTime::from_millis(3_000));
+    let patch = TestTimestampCrudPatch {
+        name: Some("patched".to_string()),
+    };
+    let patched = client.patch(create.id.unwrap(), patch).await.unwrap();
+    assert_eq!(
+        patched.unwrap().updated_at,
+        Some(bson::DateTime::from_millis(3_000))
+    );
+
+    let modified: Vec<TestTimestampCrud> = client
+        .read_modified_since(bson::DateTime::from_millis(2_500))
+        .await
+        .unwrap();
+    assert!(modified.iter().any(|v| v.id == create.id));
+    let modified: Vec<TestTimestampCrud> = client
+        .read_modified_since(bson::DateTime::from_millis(3_500))
+        .await
+        .unwrap();
+    assert!(modified.iter().all(|v| v.id != create.id));
+
+    // `read_modified_since` needs an `updated_at` field
+    let unstamped: anyhow::Result<Vec<TestSingleIndexCrud>> = client
+        .read_modified_since(bson::DateTime::from_millis(0))
+        .await;
+    assert!(unstamped.is_err());
+
+    let delete: anyhow::Result<Option<TestTimestampCrud>> = client.delete(create.id.unwrap()).await;
+    assert!(delete.is_ok());
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "dev_soft_delete")]
+struct TestSoftDeleteCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "unique")]
+    name: String,
+    #[crud(soft_delete)]
+    deleted_at: Option<bson::DateTime>,
+}
+
+#[tokio::test]
+async fn test_soft_delete() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+    client
+        .create_indexes_by_type::<TestSoftDeleteCrud>()
+        .await
+        .unwrap();
+
+    let value = TestSoftDeleteCrud {
+        id: None,
+        name: "soft".to_string(),
+        deleted_at: None,
+    };
+    let create = client.create(value.clone()).await.unwrap();
+    let id = create.id.unwrap();
+
+    let delete: TestSoftDeleteCrud = client.delete(id).await.unwrap().unwrap();
+    assert!(delete.deleted_at.is_some());
+
+    // deleted documents are excluded from every read
+    let read: Option<TestSoftDeleteCrud> = client.read(id).await.unwrap();
+    assert_eq!(read, None);
+    let read_many: Vec<TestSoftDeleteCrud> = client.read_many(vec![id]).await.unwrap();
+    assert!(read_many.is_empty());
+    let read_all: Vec<TestSoftDeleteCrud> = client.read_all().await.unwrap();
+    assert!(read_all.iter().all(|v| v.id != Some(id)));
+    // but by `read_deleted`
+    let fields = TestSoftDeleteCrud::fields();
+    let deleted = client.read_deleted(fields.id().eq(id)).await.unwrap();
+    assert_eq!(deleted, vec![delete.clone()]);
+
+    // the unique index only covers live documents
+    let recreate = client.create(value.clone()).await.unwrap();
+    // ... so the deleted one cannot be restored while the name is taken
+    let restore: anyhow::Result<Option<TestSoftDeleteCrud>> = client.restore(id).await;
+    assert!(restore.is_err());
+    let purge: Option<TestSoftDeleteCrud> = client.purge(recreate.id.unwrap()).await.unwrap();
+    assert!(purge.is_some());
+
+    let restore: TestSoftDeleteCrud = client.restore(id).await.unwrap().unwrap();
+    assert_eq!(restore.deleted_at, None);
+    assert!(client
+        .read_deleted(fields.id().eq(id))
+        .await
+        .unwrap()
+        .is_empty());
+    let read: Option<TestSoftDeleteCrud> = client.read(id).await.unwrap();
+    assert_eq!(read, Some(create));
+
+    let purge: Option<TestSoftDeleteCrud> = client.purge(id).await.unwrap();
+    assert!(purge.is_some());
+    let restore: Option<TestSoftDeleteCrud> = client.restore(id).await.unwrap();
+    assert_eq!(restore, None);
+}
+
+#[tokio::test]
+async fn test_version_conflict() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    let value = TestSingleIndexCrud {
+        idx: None,
+        name: "versioned".to_string(),
+        content: None,
+        version: 1,
+    };
+    let create = client.create(value).await.unwrap();
+
+    // two editors read the same version
+    let mut first = create.clone();
+    let mut second = create.clone();
+
+    first.content = Some("first".to_string());
+    let first = client.update(first).await.unwrap();
+    assert_eq!(first.version, 2);
+
+    // the second one is rejected, and told the current version
+    second.content = Some("second".to_string());
+    let conflict = client.update(second).await.unwrap_err();
+    assert_eq!(
+        conflict.downcast_ref::<CrudError>(),
+        Some(&CrudError::Conflict {
+            expected: 1,
+            current: 2
+        })
+    );
+
+    let read: TestSingleIndexCrud = client.read(create.idx.unwrap()).await.unwrap().unwrap();
+    assert_eq!(read.content.as_deref(), Some("first"));
+
+    let delete: TestSingleIndexCrud = client.delete(create.idx.unwrap()).await.unwrap().unwrap();
+
+    // nothing matched
+    let missing = client.update(delete).await.unwrap_err();
+    assert_eq!(
+        missing.downcast_ref::<CrudError>(),
+        Some(&CrudError::NotFound)
+    );
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "graph", discriminator = "etype", value = "Company")]
+struct TestCompanyVariant {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "unique")]
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "graph", discriminator = "etype", value = "Property")]
+struct TestPropertyVariant {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(single_index = "unique")]
+    name: String,
+}
+
+#[derive(Debug, PartialEq)]
+enum TestGraphEntity {
+    Company(TestCompanyVariant),
+    Property(TestPropertyVariant),
+}
+
+impl Polymorphic for TestGraphEntity {
+    fn variants() -> Vec<Variant> {
+        vec![
+            Variant::of::<TestCompanyVariant>(),
+            Variant::of::<TestPropertyVariant>(),
+        ]
+    }
+
+    fn from_document(value: &str, document: Document) -> anyhow::Result<Self> {
+        match value {
+            "Company" => Ok(TestGraphEntity::Company(bson::from_document(document)?)),
+            "Property" => Ok(TestGraphEntity::Property(bson::from_document(document)?)),
+            _ => Err(anyhow::anyhow!("unknown entity `{}`", value)),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_polymorphic() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    // both variants can use the same name, their unique indexes are apart
+    let indexes = client
+        .create_indexes_by_polymorphic::<TestGraphEntity>()
+        .await
+        .unwrap();
+    assert_eq!(indexes.len(), 2);
+
+    let company = TestCompanyVariant {
+        id: None,
+        name: "shared".to_string(),
+    };
+    let company = client.create(company).await.unwrap();
+    let property = TestPropertyVariant {
+        id: None,
+        name: "shared".to_string(),
+    };
+    let property = client.create(property).await.unwrap();
+
+    // each variant only reads and counts its own documents
+    let companies: Vec<TestCompanyVariant> = client.read_all().await.unwrap();
+    assert_eq!(companies, vec![company.clone()]);
+    let count =
+        MongoCRUD::<TestPropertyVariant>::count_by_filter(&client, doc! { "name": "shared" })
+            .await
+            .unwrap();
+    assert_eq!(count, 1);
+
+    // both of them, by one query
+    let entities: Vec<TestGraphEntity> = client
+        .read_polymorphic(doc! { "name": "shared" })
+        .await
+        .unwrap();
+    assert_eq!(entities.len(), 2);
+    assert!(entities.contains(&TestGraphEntity::Company(company.clone())));
+    assert!(entities.contains(&TestGraphEntity::Property(property.clone())));
+
+    let _: Option<TestCompanyVariant> = client.delete(company.id.unwrap()).await.unwrap();
+    let _: Option<TestPropertyVariant> = client.delete(property.id.unwrap()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_find() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    let value = TestSingleIndexCrud {
+        idx: None,
+        name: "typed filter".to_string(),
+        content: Some("found".to_string()),
+        version: 1,
+    };
+    let create = client.create(value).await.unwrap();
+
+    let fields = TestSingleIndexCrud::fields();
+    let found = client
+        .find(
+            fields
+                .name()
+                .eq("typed filter")
+                .and(fields.content().eq("found")),
+        )
+        .await
+        .unwrap();
+    assert_eq!(found, vec![create.clone()]);
+
+    let found = client
+        .find(
+            fields
+                .idx()
+                .eq(create.idx.unwrap())
+                .and(fields.content().is_null()),
+        )
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+
+    let _: Option<TestSingleIndexCrud> = client.delete(create.idx.unwrap()).await.unwrap();
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+struct TestValidateCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(validate(length(min = 1)))]
+    name: String,
+    #[crud(validate(range(min = 0.0)))]
+    weight: Option<f64>,
+}
+
+#[tokio::test]
+async fn test_validate() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    // nothing is written
+    let invalid = TestValidateCrud {
+        id: None,
+        name: String::new(),
+        weight: Some(-1.0),
+    };
+    let err = client.create(invalid).await.unwrap_err();
+    match err.downcast_ref::<CrudError>() {
+        Some(CrudError::Validation(errors)) => {
+            let fields = errors
+                .0
+                .iter()
+                .map(|e| e.field.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(fields, vec!["name", "weight"]);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    let count = MongoCRUD::<TestValidateCrud>::count_by_filter(&client, doc! {})
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    let valid = TestValidateCrud {
+        id: None,
+        name: "valid".to_string(),
+        weight: None,
+    };
+    let mut create = client.create(valid).await.unwrap();
+
+    create.weight = Some(-1.0);
+    let err = client.update(create.clone()).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<CrudError>(),
+        Some(CrudError::Validation(_))
+    ));
+
+    // nor by a patch
+    let patch = TestValidateCrudPatch {
+        name: Some(String::new()),
+        ..Default::default()
+    };
+    let err = client.patch(create.id.unwrap(), patch).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<CrudError>(),
+        Some(CrudError::Validation(_))
+    ));
+
+    let _: Option<TestValidateCrud> = client.delete(create.id.unwrap()).await.unwrap();
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(hooks)]
+struct TestHooksCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    name: String,
+}
+
+#[async_trait::async_trait]
+impl Hooks for TestHooksCrud {
+    async fn before_create(&mut self, _client: &MongoClient) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow::anyhow!("rejected by hook"));
+        }
+        self.name = self.name.trim().to_string();
+        Ok(())
+    }
+
+    /// delete the documents of the same name along with it
+    async fn after_delete(&self, client: &MongoClient) -> anyhow::Result<()> {
+        let others: Vec<TestHooksCrud> = client
+            .find(TestHooksCrud::fields().name().eq(self.name.clone()))
+            .await?;
+        for other in others {
+            MongoCRUD::<TestHooksCrud>::delete(client, other.id.unwrap()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_hooks() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    // aborted, nothing is written
+    let rejected = TestHooksCrud {
+        id: None,
+        name: String::new(),
+    };
+    assert!(client.create(rejected).await.is_err());
+
+    let value = TestHooksCrud {
+        id: None,
+        name: "  hooked  ".to_string(),
+    };
+    let first = client.create(value.clone()).await.unwrap();
+    assert_eq!(first.name, "hooked");
+    let second = client.create(value).await.unwrap();
+
+    // cascades to the second one
+    let _: Option<TestHooksCrud> = client.delete(first.id.unwrap()).await.unwrap();
+    let read: Option<TestHooksCrud> = client.read(second.id.unwrap()).await.unwrap();
+    assert_eq!(read, None);
+
+    // upserts are hooked as well, the trimmed name is the key
+    let fields = TestHooksCrud::fields();
+    let upserted = TestHooksCrud {
+        id: None,
+        name: "  upserted  ".to_string(),
+    };
+    let result = client
+        .upsert(vec![upserted.clone()], &fields.name())
+        .await
+        .unwrap();
+    assert_eq!(result.upserted_ids.len(), 1);
+    let result = client.upsert(vec![upserted], &fields.name()).await.unwrap();
+    assert_eq!((result.upserted_ids.len(), result.matched), (0, 1));
+    let read: Vec<TestHooksCrud> = client.find(fields.name().eq("upserted")).await.unwrap();
+    assert_eq!(read.len(), 1);
+    let _: Option<TestHooksCrud> = client.delete(read[0].id.unwrap()).await.unwrap();
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "schema")]
+struct TestSchemaCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    name: String,
+    score: Option<i64>,
+}
+
+#[tokio::test]
+async fn test_schema() {
+    let mut client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    // created, then replaced by `collMod`
+    client
+        .set_validator_by_type::<TestSchemaCrud>(ValidationLevel::Strict, ValidationAction::Error)
+        .await
+        .unwrap();
+    client
+        .set_validator_by_type::<TestSchemaCrud>(ValidationLevel::Strict, ValidationAction::Error)
+        .await
+        .unwrap();
+
+    let value = TestSchemaCrud {
+        id: None,
+        name: "valid".to_string(),
+        score: None,
+    };
+    let value = client.create(value).await.unwrap();
+
+    // a document the type cannot read is rejected
+    client.set_collection("schema");
+    let invalid = client
+        .schema::<Document>()
+        .insert_one(doc! { "score": "high" }, None)
+        .await;
+    assert!(invalid.is_err());
+
+    // a variant is validated along with the other variants of its collection
+    let variant = client
+        .set_validator_by_type::<TestCompanyVariant>(
+            ValidationLevel::Strict,
+            ValidationAction::Error,
+        )
+        .await;
+    assert!(variant.is_err());
+    client
+        .set_validator_by_polymorphic::<TestGraphEntity>(
+            ValidationLevel::Moderate,
+            ValidationAction::Error,
+        )
+        .await
+        .unwrap();
+    client.set_collection("graph");
+    let unknown = client
+        .schema::<Document>()
+        .insert_one(doc! { "etype": "Unknown", "name": "x" }, None)
+        .await;
+    assert!(unknown.is_err());
+
+    let _: Option<TestSchemaCrud> = client.purge(value.id.unwrap()).await.unwrap();
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[crud(collection = "encrypt")]
+struct TestEncryptCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    #[crud(encrypt)]
+    terms: Option<String>,
+    #[crud(encrypt(deterministic), single_index = "unique")]
+    code: String,
+}
+
+#[tokio::test]
+async fn test_encrypt() {
+    let mut client = MongoClient::new(URI, DB, CL).await.unwrap();
+    let key_path = std::env::temp_dir().join(format!("crud-{}.key", ObjectId::new()));
+    let key_file = LocalKeyFile::create(&key_path).unwrap();
+    assert!(LocalKeyFile::create(&key_path).is_err());
+
+    // no key, nothing can be written
+    let value = TestEncryptCrud {
+        id: None,
+        terms: Some("confidential".to_string()),
+        code: "S-001".to_string(),
+    };
+    assert!(client.create(value.clone()).await.is_err());
+
+    client.set_key_provider(std::sync::Arc::new(key_file));
+    client
+        .create_indexes_by_type::<TestEncryptCrud>()
+        .await
+        .unwrap();
+    let value = client.create(value).await.unwrap();
+
+    // only ciphertexts are stored
+    let stored = client
+        .stored_schema::<TestEncryptCrud>()
+        .find_one(doc! { "_id": value.id.unwrap() }, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(stored.get("terms"), Some(bson::Bson::Binary(_))));
+    assert!(matches!(stored.get("code"), Some(bson::Bson::Binary(_))));
+
+    let read: TestEncryptCrud = client.read(value.id.unwrap()).await.unwrap().unwrap();
+    assert_eq!(read, value);
+
+    // a deterministic field is queried by equality, and stays unique
+    let found = client
+        .find(TestEncryptCrud::fields().code().eq("S-001"))
+        .await
+        .unwrap();
+    assert_eq!(found, vec![value.clone()]);
+    let duplicated = TestEncryptCrud {
+        id: None,
+        terms: None,
+        code: "S-001".to_string(),
+    };
+    assert!(client.create(duplicated).await.is_err());
+
+    // a random one cannot be queried
+    let random = client
+        .find(TestEncryptCrud::fields().terms().eq("confidential"))
+        .await;
+    assert!(random.is_err());
+    // neither by type, a ciphertext is binary
+    let typed: anyhow::Result<Vec<TestEncryptCrud>> = client
+        .read_by_filter(doc! { "code": { "$type": "string" } })
+        .await;
+    assert!(typed.is_err());
+
+    // another key cannot read it
+    let other_path = std::env::temp_dir().join(format!("crud-{}.key", ObjectId::new()));
+    let mut other = client.clone();
+    other.set_key_provider(std::sync::Arc::new(
+        LocalKeyFile::create(&other_path).unwrap(),
+    ));
+    let unreadable: anyhow::Result<Option<TestEncryptCrud>> = other.read(value.id.unwrap()).await;
+    assert!(unreadable.is_err());
+
+    let _: Option<TestEncryptCrud> = client.delete(value.id.unwrap()).await.unwrap();
+
+    // a document written before its fields were encrypted is read as it is
+    let legacy = ObjectId::new();
+    client
+        .stored_schema::<TestEncryptCrud>()
+        .insert_one(
+            doc! { "_id": legacy, "terms": "plaintext", "code": "S-002" },
+            None,
+        )
+        .await
+        .unwrap();
+    let read: TestEncryptCrud = client.read(legacy).await.unwrap().unwrap();
+    assert_eq!(read.terms, Some("plaintext".to_string()));
+    assert_eq!(read.code, "S-002");
+    // and encrypted by its next write
+    client.update(read.clone()).await.unwrap();
+    let stored = client
+        .stored_schema::<TestEncryptCrud>()
+        .find_one(doc! { "_id": legacy }, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(stored.get("terms"), Some(bson::Bson::Binary(_))));
+    let _: Option<TestEncryptCrud> = client.purge(legacy).await.unwrap();
+
+    std::fs::remove_file(key_path).unwrap();
+    std::fs::remove_file(other_path).unwrap();
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[crud(collection = "projection")]
+#[crud(projection(
+    name = "TestProjectionSummary",
+    fields("full_name"),
+    derive(Debug, PartialEq)
+))]
+struct TestProjectionCrud {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    id: Option<ObjectId>,
+    full_name: String,
+    content: Vec<String>,
+}
+
+#[tokio::test]
+async fn test_projection() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    let value = TestProjectionCrud {
+        id: None,
+        full_name: "Acme".to_string(),
+        content: vec!["a large content".to_string(); 16],
+    };
+    let value = client.create(value).await.unwrap();
+
+    let summaries = client.read_all_as::<TestProjectionSummary>().await.unwrap();
+    assert!(summaries.contains(&TestProjectionSummary {
+        id: value.id,
+        full_name: "Acme".to_string(),
+    }));
+
+    let found = client
+        .find_as::<TestProjectionSummary, _>(
+            TestProjectionCrud::fields().id().eq(value.id.unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].full_name, "Acme");
+
+    let _: Option<TestProjectionCrud> = client.delete(value.id.unwrap()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_query() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    let mut created = vec![];
+    for name in ["query c", "query a", "query b"] {
+        let value = TestSingleIndexCrud {
+            idx: None,
+            name: name.to_string(),
+            content: Some("query".to_string()),
+            version: 1,
+        };
+        created.push(client.create(value).await.unwrap());
+    }
+
+    let fields = TestSingleIndexCrud::fields();
+    let query = Query::new(fields.content().eq("query")).sort(&fields.name(), Order::Asc);
+
+    let found = client.find(query.clone().skip(1).limit(1)).await.unwrap();
+    let names = found.iter().map(|v| v.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["query b"]);
+
+    let first: Option<TestSingleIndexCrud> = client.find_one(query.clone()).await.unwrap();
+    assert_eq!(first.unwrap().name, "query a");
+
+    assert_eq!(client.count(query.clone()).await.unwrap(), 3);
+    assert_eq!(client.count(query.limit(2)).await.unwrap(), 2);
+    assert!(client.exists(fields.name().eq("query c")).await.unwrap());
+    assert!(!client.exists(fields.name().eq("query d")).await.unwrap());
+
+    for value in created {
+        let _: Option<TestSingleIndexCrud> = client.delete(value.idx.unwrap()).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_paginate() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    let mut created = vec![];
+    for i in 0..5 {
+        let value = TestSingleIndexCrud {
+            idx: None,
+            name: format!("page {i}"),
+            content: Some("page".to_string()),
+            version: 1,
+        };
+        created.push(client.create(value).await.unwrap());
+    }
+    let names = |page: &Page<TestSingleIndexCrud>| {
+        page.items
+            .iter()
+            .map(|v| v.name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let fields = TestSingleIndexCrud::fields();
+    let query = Query::new(fields.content().eq("page")).sort(&fields.name(), Order::Desc);
+
+    // by offset
+    let first = client
+        .paginate(query.clone(), PageRequest::offset(2))
+        .await
+        .unwrap();
+    assert_eq!(names(&first), vec!["page 4", "page 3"]);
+    assert_eq!(first.total, 5);
+    assert!(first.previous.is_none());
+    let second = client
+        .paginate(query.clone(), PageRequest::offset(2).cursor(first.next))
+        .await
+        .unwrap();
+    assert_eq!(names(&second), vec!["page 2", "page 1"]);
+
+    // by keyset, forward to the last page and back
+    let mut pages = vec![];
+    let mut cursor = None;
+    loop {
+        let page = client
+            .paginate(query.clone(), PageRequest::keyset(2).cursor(cursor))
+            .await
+            .unwrap();
+        cursor = page.next.clone();
+        pages.push(page);
+        if cursor.is_none() {
+            break;
+        }
+    }
+    let all = pages.iter().flat_map(names).collect::<Vec<_>>();
+    assert_eq!(all, vec!["page 4", "page 3", "page 2", "page 1", "page 0"]);
+    let back = client
+        .paginate(
+            query.clone(),
+            PageRequest::keyset(2).cursor(pages[2].previous.clone()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(names(&back), vec!["page 2", "page 1"]);
+
+    // a cursor of the other paging, or not a cursor at all
+    let mismatched = client
+        .paginate(
+            query.clone(),
+            PageRequest::offset(2).cursor(pages[0].next.clone()),
+        )
+        .await;
+    assert!(mismatched.is_err());
+    assert!(matches!(
+        "not a cursor".parse::<Cursor>(),
+        Err(CrudError::InvalidCursor(_))
+    ));
+
+    // by a key which can be null, sorted before the values
+    let mut nullable = vec![];
+    for (i, content) in [None, Some("a"), None, Some("b")].into_iter().enumerate() {
+        let value = TestSingleIndexCrud {
+            idx: None,
+            name: format!("null page {i}"),
+            content: content.map(str::to_string),
+            version: 7,
+        };
+        nullable.push(client.create(value).await.unwrap());
+    }
+    for order in [Order::Asc, Order::Desc] {
+        let query = Query::new(fields.version().eq(7)).sort(&fields.content(), order);
+        let mut read = vec![];
+        let mut cursor = None;
+        loop {
+            let page = client
+                .paginate(query.clone(), PageRequest::keyset(1).cursor(cursor))
+                .await
+                .unwrap();
+            read.extend(page.items.iter().map(|v| v.content.clone()));
+            cursor = page.next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        let mut expected = vec![None, None, Some("a".to_string()), Some("b".to_string())];
+        if order == Order::Desc {
+            expected.reverse();
+        }
+        assert_eq!(read, expected);
+    }
+    created.extend(nullable);
+
+    // the page size is the only limit
+    let limited = client
+        .paginate(query.clone().limit(10), PageRequest::offset(2))
+        .await;
+    assert!(limited.is_err());
+
+    for value in created {
+        let _: Option<TestSingleIndexCrud> = client.delete(value.idx.unwrap()).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_stream() {
+    use tokio_stream::StreamExt;
+
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+
+    let mut ids = vec![];
+    for i in 0..5 {
+        let value = TestSingleIndexCrud {
+            idx: None,
+            name: format!("stream {i}"),
+            content: Some("stream".to_string()),
+            version: 1,
+        };
+        ids.push(client.create(value).await.unwrap().idx.unwrap());
+    }
+
+    // fetched 2 at a time, read one at a time
+    let fields = TestSingleIndexCrud::fields();
+    let query = Query::new(fields.content().eq("stream"))
+        .sort(&fields.name(), Order::Asc)
+        .batch_size(2);
+    let mut stream = client.stream(query).await.unwrap();
+    let mut names = vec![];
+    while let Some(value) = stream.next().await {
+        names.push(value.unwrap().name);
+    }
+    assert_eq!(
+        names,
+        vec!["stream 0", "stream 1", "stream 2", "stream 3", "stream 4"]
+    );
+
+    let streamed: Vec<TestSingleIndexCrud> = client
+        .stream_many(ids[..3].to_vec(), 2)
+        .await
+        .unwrap()
+        .collect::<anyhow::Result<_>>()
+        .await
+        .unwrap();
+    assert_eq!(streamed.len(), 3);
+
+    let mut all: CrudStream<TestSingleIndexCrud> = client.stream_all(2).await.unwrap();
+    let mut count = 0;
+    while let Some(value) = all.next().await {
+        value.unwrap();
+        count += 1;
+    }
+    assert!(count >= 5);
+
+    for id in ids {
+        let _: Option<TestSingleIndexCrud> = client.delete(id).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_bulk() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+    let fields = TestSingleIndexCrud::fields();
+    let value = |name: &str| TestSingleIndexCrud {
+        idx: None,
+        name: name.to_string(),
+        content: Some("bulk".to_string()),
+        version: 0,
+    };
+
+    // unordered, the duplicated name fails alone
+    let result = client
+        .create_many(
+            vec![value("bulk a"), value("bulk a"), value("bulk b")],
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.inserted_ids.keys().collect::<Vec<_>>(), vec![&0, &2]);
+    assert_eq!(result.errors.len(), 1);
+    assert_eq!(result.errors[0].index, 1);
+    assert_eq!(result.errors[0].code, Some(11000));
+
+    // ordered, nothing is created after the failure
+    let result = client
+        .create_many(
+            vec![value("bulk c"), value("bulk a"), value("bulk d")],
+            true,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.inserted_ids.keys().collect::<Vec<_>>(), vec![&0]);
+    assert_eq!(result.errors[0].index, 1);
+    assert!(!client.exists(fields.name().eq("bulk d")).await.unwrap());
+
+    // by the natural key, "bulk a" is updated and "bulk e" inserted
+    let mut updated = value("bulk a");
+    updated.content = Some("upserted".to_string());
+    let result = client
+        .upsert(vec![updated, value("bulk e")], &fields.name())
+        .await
+        .unwrap();
+    assert_eq!((result.matched, result.modified), (1, 1));
+    assert_eq!(result.upserted_ids.keys().collect::<Vec<_>>(), vec![&1]);
+    let upserted: TestSingleIndexCrud = client
+        .find_one(fields.name().eq("bulk a"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(upserted.content.as_deref(), Some("upserted"));
+    assert_eq!(upserted.version, 1);
+    // inserted at its own version, as `create` does
+    let inserted: TestSingleIndexCrud = client
+        .find_one(fields.name().eq("bulk e"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(inserted.version, 0);
+
+    let patch = TestSingleIndexCrudPatch {
+        content: Some(Some("patched".to_string())),
+        ..Default::default()
+    };
+    let result = client
+        .update_many(fields.content().eq("bulk"), patch)
+        .await
+        .unwrap();
+    assert_eq!(result.matched, 3);
+
+    // mixed
+    let id = id_of(&client, "bulk b").await;
+    let result = client
+        .bulk_write(
+            vec![
+                WriteModel::Insert(value("bulk f")),
+                WriteModel::Delete(id),
+                WriteModel::DeleteMany(fields.name().is_in(["bulk a", "bulk c"])),
+            ],
+            true,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.inserted_ids.len(), 1);
+    assert_eq!(result.deleted, 3);
+    assert!(result.errors.is_empty());
+
+    // updates by id, a stale version and a missing id fail on their own
+    let current: TestSingleIndexCrud = client
+        .find_one(fields.name().eq("bulk e"))
+        .await
+        .unwrap()
+        .unwrap();
+    let mut missing = current.clone();
+    missing.idx = Some(ObjectId::new());
+    let result = client
+        .bulk_write(
+            vec![
+                WriteModel::Update(current.clone()),
+                WriteModel::Update(current),
+                WriteModel::Update(missing),
+            ],
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.matched, 1);
+    let failed = result
+        .errors
+        .iter()
+        .map(|e| (e.index, e.message.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(failed.len(), 2);
+    assert_eq!(failed[0].0, 1);
+    assert!(failed[0].1.starts_with("Version conflict"));
+    assert_eq!(failed[1], (2, "Document not found"));
+
+    let rest = client
+        .delete_many(fields.name().is_in(["bulk e", "bulk f"]))
+        .await
+        .unwrap();
+    assert_eq!(rest.deleted, 2);
+}
+
+// transactions need a replica set, e.g. a single node started by `mongod --replSet rs0`
+#[tokio::test]
+async fn test_transaction() {
+    let client = MongoClient::new(URI, DB, CL).await.unwrap();
+    if !client.supports_transactions().await.unwrap() {
+        // a standalone server runs the closure as it is
+        let inside = client
+            .with_transaction(|tx| async move { Ok(tx.in_transaction()) })
+            .await
+            .unwrap();
+        assert!(!inside);
+        return;
+    }
+    let fields = TestSingleIndexCrud::fields();
+    let value = |name: &str| TestSingleIndexCrud {
+        idx: None,
+        name: name.to_string(),
+        content: Some("transaction".to_string()),
+        version: 0,
+    };
+    assert!(!client.in_transaction());
+
+    // committed
+    let created = client
+        .with_transaction(|tx| async move {
+            assert!(tx.in_transaction());
+            let a = tx.create(value("transaction a")).await?;
+            let b = tx.create(value("transaction b")).await?;
+            // read by the transaction before it is committed
+            let found = tx.find_one(TestSingleIndexCrud::fields().name().eq("transaction a"));
+            assert!(found.await?.is_some());
+            Ok(vec![a, b])
+        })
+        .await
+        .unwrap();
+    assert_eq!(created.len(), 2);
+    assert!(client
+        .exists(fields.name().eq("transaction b"))
+        .await
+        .unwrap());
+
+    // rolled back by an error, the duplicated name
+    let result = client
+        .with_transaction(|tx| async move {
+            tx.create(value("transaction c")).await?;
+            tx.create(value("transaction a")).await
+        })
+        .await;
+    assert!(result.is_err());
+    assert!(!client
+        .exists(fields.name().eq("transaction c"))
+        .await
+        .unwrap());
+
+    // a nested transaction joins the outer one
+    let result: Result<(), _> = client
+        .with_transaction(|tx| async move {
+            tx.with_transaction(|inner| async move { inner.create(value("transaction d")).await })
+                .await?;
+            Err(anyhow::anyhow!("rolled back"))
+        })
+        .await;
+    assert!(result.is_err());
+    assert!(!client
+        .exists(fields.name().eq("transaction d"))
+        .await
+        .unwrap());
+
+    let rest = client
+        .delete_many(fields.content().eq("transaction"))
+        .await
+        .unwrap();
+    assert_eq!(rest.deleted, 2);
+}
@@ -0,0 +1,11 @@
+//! Compile-fail tests of `#[derive(CRUD)]`
+//!
+//! Every misuse of the derive must be reported as a compile error pointing at the offending
+//! tokens. The expected messages are pinned in `tests/ui/*.stderr`, regenerate them with
+//! `TRYBUILD=overwrite cargo test -p crud --test test_ui`.
+
+#[test]
+fn test_ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,14 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct MissingOrder {
+    id: Option<ObjectId>,
+    #[crud(compound_index(name = "by_source_target", order = 1, unique))]
+    source: ObjectId,
+    #[crud(compound_index(name = "by_source_target"))]
+    target: ObjectId,
+}
+
+fn main() {}
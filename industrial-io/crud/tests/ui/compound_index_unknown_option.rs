@@ -0,0 +1,14 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct UnknownCompoundOption {
+    id: Option<ObjectId>,
+    #[crud(compound_index(name = "by_source_target", uniqe))]
+    source: ObjectId,
+    #[crud(compound_index(name = "by_source_target", dir = "up"))]
+    target: ObjectId,
+}
+
+fn main() {}
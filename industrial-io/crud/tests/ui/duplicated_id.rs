@@ -0,0 +1,20 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct DuplicatedId {
+    #[crud(id)]
+    idx: Option<ObjectId>,
+    #[crud(id)]
+    oid: Option<ObjectId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct IdAndGeneratedId {
+    id: Option<ObjectId>,
+    #[crud(id(generate = "uuid_v4"))]
+    uid: Option<bson::Uuid>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+enum NotAStruct {
+    Company,
+    Property,
+}
+
+fn main() {}
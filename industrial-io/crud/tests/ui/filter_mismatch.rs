@@ -0,0 +1,19 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct Company {
+    id: Option<ObjectId>,
+    name: String,
+    size: i64,
+}
+
+fn main() {
+    let fields = Company::fields();
+
+    // misspelled field
+    let _ = fields.nmae().eq("x");
+    // value of another type
+    let _ = fields.size().eq("x");
+}
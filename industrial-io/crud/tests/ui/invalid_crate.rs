@@ -0,0 +1,16 @@
+use bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, crud::CRUD)]
+#[crud(crate = "not a path")]
+struct InvalidCrate {
+    id: Option<ObjectId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, crud::CRUD)]
+#[crud(crate = "crud", crate = "crud")]
+struct DuplicatedCrate {
+    id: Option<ObjectId>,
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(collection = "graph", value = "Company")]
+struct MissingDiscriminator {
+    id: Option<ObjectId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(discriminator = "$type", value = "Company")]
+struct InvalidDiscriminator {
+    id: Option<ObjectId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(discriminator = "etype", value = "Company", value = "Property")]
+struct DuplicatedValue {
+    id: Option<ObjectId>,
+}
+
+fn main() {}
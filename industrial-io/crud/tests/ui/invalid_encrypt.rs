@@ -0,0 +1,32 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct UnknownMode {
+    id: Option<ObjectId>,
+    #[crud(encrypt(random))]
+    code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct RandomIndexed {
+    id: Option<ObjectId>,
+    #[crud(encrypt, single_index = "unique")]
+    code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct EncryptedText {
+    id: Option<ObjectId>,
+    #[crud(encrypt(deterministic), text)]
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct EncryptedId {
+    #[crud(encrypt)]
+    id: Option<ObjectId>,
+}
+
+fn main() {}
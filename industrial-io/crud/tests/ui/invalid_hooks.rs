@@ -0,0 +1,18 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(hooks = "company_hooks")]
+struct HooksWithValue {
+    id: Option<ObjectId>,
+}
+
+// `#[crud(hooks)]` without an impl of `Hooks`
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(hooks)]
+struct MissingHooks {
+    id: Option<ObjectId>,
+}
+
+fn main() {}
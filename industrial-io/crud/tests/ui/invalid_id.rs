@@ -0,0 +1,22 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct NotOptionId {
+    id: ObjectId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct UnknownGenerator {
+    #[crud(id(generate = "uuid_v5"))]
+    id: Option<bson::Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct IdWithValue {
+    #[crud(id = "uuid_v4")]
+    idx: Option<bson::Uuid>,
+}
+
+fn main() {}
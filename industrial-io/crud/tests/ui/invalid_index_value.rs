@@ -0,0 +1,12 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct InvalidIndexValue {
+    id: Option<ObjectId>,
+    #[crud(single_index = 1)]
+    name: String,
+}
+
+fn main() {}
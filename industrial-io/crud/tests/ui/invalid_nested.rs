@@ -0,0 +1,30 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Patchable)]
+struct VertexOption {
+    position: (i64, i64),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct OptionalNested {
+    id: Option<ObjectId>,
+    #[crud(nested)]
+    option: Option<VertexOption>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct NestedWithValue {
+    id: Option<ObjectId>,
+    #[crud(nested = "true")]
+    option: VertexOption,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Patchable)]
+struct UnknownPatchableAttribute {
+    #[crud(single_index = "asc")]
+    position: (i64, i64),
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct InvalidPath {
+    id: Option<ObjectId>,
+    #[crud(path = "option..position", single_index = "asc")]
+    option: bson::Document,
+}
+
+fn main() {}
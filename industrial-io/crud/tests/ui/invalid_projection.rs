@@ -0,0 +1,35 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(projection(name = "UnknownFieldSummary", fields("nmae")))]
+struct UnknownField {
+    id: Option<ObjectId>,
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(projection(fields("name")))]
+struct MissingName {
+    id: Option<ObjectId>,
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(projection(name = "Summary", fields("name")))]
+#[crud(projection(name = "Summary", fields("code")))]
+struct DuplicatedName {
+    id: Option<ObjectId>,
+    name: String,
+    code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(projection(name = "UnknownOptionSummary", fields("name"), sort("name")))]
+struct UnknownOption {
+    id: Option<ObjectId>,
+    name: String,
+}
+
+fn main() {}
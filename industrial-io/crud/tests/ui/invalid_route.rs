@@ -0,0 +1,18 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(collection = "companies")]
+#[crud(collection = "firms")]
+struct DuplicatedCollection {
+    id: Option<ObjectId>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(database = "iio.dev")]
+struct InvalidDatabase {
+    id: Option<ObjectId>,
+}
+
+fn main() {}
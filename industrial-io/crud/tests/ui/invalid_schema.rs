@@ -0,0 +1,26 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct InvalidJson {
+    id: Option<ObjectId>,
+    #[crud(schema = "{\"enum\": [}")]
+    side: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct NotAnObject {
+    id: Option<ObjectId>,
+    #[crud(schema = "[\"Left\"]")]
+    side: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct SchemaWithoutValue {
+    id: Option<ObjectId>,
+    #[crud(schema)]
+    side: String,
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+use bson::{oid::ObjectId, DateTime};
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct NotOptionSoftDelete {
+    id: Option<ObjectId>,
+    #[crud(soft_delete)]
+    deleted_at: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct SkippedSoftDelete {
+    id: Option<ObjectId>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[crud(soft_delete)]
+    deleted_at: Option<DateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct UpdatedAndDeleted {
+    id: Option<ObjectId>,
+    #[crud(updated_at, soft_delete)]
+    stamped: Option<DateTime>,
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+use bson::{oid::ObjectId, DateTime};
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct DuplicatedCreatedAt {
+    id: Option<ObjectId>,
+    #[crud(created_at)]
+    created: Option<DateTime>,
+    #[crud(created_at)]
+    inserted: Option<DateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct BothTimestamps {
+    id: Option<ObjectId>,
+    #[crud(created_at, updated_at)]
+    stamped: Option<DateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct TimestampWithValue {
+    id: Option<ObjectId>,
+    #[crud(updated_at = "now")]
+    updated_at: Option<DateTime>,
+}
+
+fn main() {}
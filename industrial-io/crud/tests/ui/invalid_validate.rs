@@ -0,0 +1,33 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct UnknownValidator {
+    id: Option<ObjectId>,
+    #[crud(validate(lenght(min = 1)))]
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct InvalidLength {
+    id: Option<ObjectId>,
+    #[crud(validate(length(min = 5, max = 1)))]
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct InvalidRegex {
+    id: Option<ObjectId>,
+    #[crud(validate(regex = "[A-Z"))]
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct MissingBounds {
+    id: Option<ObjectId>,
+    #[crud(validate(range))]
+    weight: f64,
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct NotIntegerVersion {
+    id: Option<ObjectId>,
+    #[crud(version)]
+    version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct DuplicatedVersion {
+    id: Option<ObjectId>,
+    #[crud(version)]
+    version: i32,
+    #[crud(version)]
+    revision: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct VersionWithValue {
+    id: Option<ObjectId>,
+    #[crud(version = "1")]
+    version: i32,
+}
+
+fn main() {}
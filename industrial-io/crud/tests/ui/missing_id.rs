@@ -0,0 +1,10 @@
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct MissingId {
+    idx: Option<bson::oid::ObjectId>,
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(collection = "graph", discriminator = "etype", value = "Company")]
+struct Company {
+    id: Option<ObjectId>,
+    name: String,
+    etype: String,
+}
+
+fn main() {
+    // the discriminator is not patched, a company stays a company
+    let _ = CompanyPatch {
+        etype: Some("Property".to_string()),
+        ..Default::default()
+    };
+}
@@ -0,0 +1,33 @@
+use bson::{oid::ObjectId, DateTime};
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct InvalidTtl {
+    id: Option<ObjectId>,
+    #[crud(single_index(expire_after = "7 days"))]
+    created_at: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct InvalidPartial {
+    id: Option<ObjectId>,
+    #[crud(single_index(unique, partial = "{code: 1}"))]
+    code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct UniqueHashed {
+    id: Option<ObjectId>,
+    #[crud(single_index = "unique,hashed")]
+    token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct CompoundTtl {
+    id: Option<ObjectId>,
+    #[crud(compound_index(name = "by_created", expire_after = "7d"))]
+    created_at: DateTime,
+}
+
+fn main() {}
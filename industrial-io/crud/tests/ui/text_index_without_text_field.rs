@@ -0,0 +1,12 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+#[crud(text_index(default_language = "english"))]
+struct TextIndexWithoutField {
+    id: Option<ObjectId>,
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct TextWeightOutOfRange {
+    id: Option<ObjectId>,
+    #[crud(text(weight = 0))]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct Tuple(Option<ObjectId>, String);
+
+fn main() {}
@@ -0,0 +1,12 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct UnknownAttribute {
+    id: Option<ObjectId>,
+    #[crud(singel_index = "unique")]
+    name: String,
+}
+
+fn main() {}
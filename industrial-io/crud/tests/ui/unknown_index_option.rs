@@ -0,0 +1,12 @@
+use bson::oid::ObjectId;
+use crud::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CRUD)]
+struct UnknownIndexOption {
+    id: Option<ObjectId>,
+    #[crud(single_index = "uniqe,asc")]
+    name: String,
+}
+
+fn main() {}
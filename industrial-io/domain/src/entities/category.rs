@@ -1,5 +1,6 @@
 //! Category
 
+use bson::DateTime;
 use crud::*;
 use serde::{Deserialize, Serialize};
 
@@ -8,12 +9,24 @@ use super::ID;
 /// Catalog of all graphs.
 /// A category is a collection of one specific graph.
 #[derive(Serialize, Deserialize, Debug, Clone, Default, CRUD)]
+#[crud(collection = "categories")]
 pub struct Category {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ID>,
     // TODO: unique name, needs `mongodb::options::IndexOptions` when initializing a collection
+    #[crud(validate(length(min = 1, max = 200)))]
     pub name: String,
     pub description: Option<String>,
+    #[crud(created_at)]
+    pub created_at: Option<DateTime>,
+    #[crud(updated_at)]
+    pub updated_at: Option<DateTime>,
+    #[crud(soft_delete)]
+    pub deleted_at: Option<DateTime>,
+    /// documents written before versioning are at version `0`
+    #[serde(default)]
+    #[crud(version)]
+    pub version: i64,
 }
 
 impl Category {
@@ -22,6 +35,10 @@ impl Category {
             id: None,
             name: name.into(),
             description: description.map(Into::into),
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+            version: 0,
         }
     }
 
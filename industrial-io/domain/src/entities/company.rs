@@ -5,23 +5,53 @@
 //! They can be chained together by relationships.
 
 use anyhow::Context;
+use async_trait::async_trait;
+use bson::DateTime;
 use crud::*;
 use serde::{Deserialize, Serialize};
 use serde_json::value::Value as JsonValue;
 
-use super::{EntityType, Industry, VertexOption, ID};
+use super::{
+    delete_relationships_of, purge_relationships_of, restore_relationships_of, EntityType,
+    Industry, VertexOption, ID,
+};
 use crate::TGResult;
 
 #[derive(Serialize, Deserialize, Debug, Clone, CRUD)]
+#[crud(
+    collection = "graph",
+    discriminator = "etype",
+    value = "Company",
+    hooks
+)]
+#[crud(projection(
+    name = "CompanySummary",
+    fields("name", "category", "group"),
+    derive(Debug)
+))]
 pub struct Company {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ID>,
     pub etype: EntityType,
+    #[crud(text, validate(length(min = 1, max = 200)))]
     pub name: String,
     pub category: Industry,
     pub group: Option<String>,
+    /// confidential terms, encrypted in the database
+    #[crud(encrypt)]
     pub data: Option<JsonValue>,
+    #[crud(nested)]
     pub option: VertexOption,
+    #[crud(created_at)]
+    pub created_at: Option<DateTime>,
+    #[crud(updated_at, single_index = "asc")]
+    pub updated_at: Option<DateTime>,
+    #[crud(soft_delete)]
+    pub deleted_at: Option<DateTime>,
+    /// documents written before versioning are at version `0`
+    #[serde(default)]
+    #[crud(version)]
+    pub version: i64,
 }
 
 impl Company {
@@ -44,8 +74,49 @@ impl Company {
             group: group.map(Into::into),
             data,
             option: option.unwrap_or_default(),
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+            version: 0,
         };
 
         Ok(company)
     }
 }
+
+#[async_trait]
+impl Hooks for Company {
+    async fn before_create(&mut self, _client: &MongoClient) -> TGResult<()> {
+        self.name = self.name.trim().to_string();
+        Ok(())
+    }
+
+    async fn before_update(&mut self, _client: &MongoClient) -> TGResult<()> {
+        self.name = self.name.trim().to_string();
+        Ok(())
+    }
+
+    /// a deleted company is no longer related to anything
+    async fn after_delete(&self, client: &MongoClient) -> TGResult<()> {
+        match (self.id, self.deleted_at) {
+            (Some(id), Some(deleted_at)) => delete_relationships_of(id, deleted_at, client).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// neither are its relationships, deleted or not, kept
+    async fn after_purge(&self, client: &MongoClient) -> TGResult<()> {
+        match self.id {
+            Some(id) => purge_relationships_of(id, client).await,
+            None => Ok(()),
+        }
+    }
+
+    /// the relationships deleted along with the company are restored with it
+    async fn after_restore(&self, deleted_at: DateTime, client: &MongoClient) -> TGResult<()> {
+        match self.id {
+            Some(id) => restore_relationships_of(id, deleted_at, client).await,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Graph
+//!
+//! Companies, properties and relationships of an industrial graph are saved in a single
+//! collection, told apart by their `etype` (see `EntityType`).
+
+use anyhow::anyhow;
+use bson::Document;
+use crud::{Polymorphic, Variant};
+use serde::{Deserialize, Serialize};
+
+use super::{Company, Property, Relationship};
+use crate::TGResult;
+
+/// Any entity of an industrial graph
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum GraphEntity {
+    Company(Company),
+    Property(Property),
+    Relationship(Relationship),
+}
+
+impl Polymorphic for GraphEntity {
+    fn variants() -> Vec<Variant> {
+        vec![
+            Variant::of::<Company>(),
+            Variant::of::<Property>(),
+            Variant::of::<Relationship>(),
+        ]
+    }
+
+    fn from_document(value: &str, document: Document) -> TGResult<Self> {
+        match value {
+            "Company" => Ok(GraphEntity::Company(bson::from_document(document)?)),
+            "Property" => Ok(GraphEntity::Property(bson::from_document(document)?)),
+            "Relationship" => Ok(GraphEntity::Relationship(bson::from_document(document)?)),
+            _ => Err(anyhow!("Unknown entity type `{}`", value)),
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 pub mod category;
 pub mod company;
+pub mod graph;
 pub mod industry;
 pub mod objects;
 pub mod property;
@@ -10,6 +11,7 @@ pub mod view;
 
 pub use category::*;
 pub use company::*;
+pub use graph::*;
 pub use industry::*;
 pub use objects::*;
 pub use property::*;
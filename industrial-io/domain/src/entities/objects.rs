@@ -3,6 +3,7 @@
 //!
 
 use bson::oid::ObjectId;
+use crud::Patchable;
 use serde::{Deserialize, Serialize};
 use serde_json::value::Value as JsonValue;
 
@@ -22,21 +23,16 @@ pub enum Position {
 Vertex option
 */
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub enum VertexType {
+    #[default]
     Default,
     Input,
     Output,
     Custom(String),
 }
 
-impl Default for VertexType {
-    fn default() -> Self {
-        VertexType::Default
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Patchable)]
 pub struct VertexOption {
     pub position: (i64, i64),
     pub vtype: VertexType,
@@ -61,33 +57,23 @@ impl Default for VertexOption {
 Edge option
 */
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub enum EdgeType {
+    #[default]
     Bezier,
     Straight,
     Step,
     Smoothstep,
 }
 
-impl Default for EdgeType {
-    fn default() -> Self {
-        EdgeType::Bezier
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub enum ArrowType {
+    #[default]
     Arrow,
     ArrowClosed,
 }
 
-impl Default for ArrowType {
-    fn default() -> Self {
-        ArrowType::Arrow
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Patchable)]
 pub struct EdgeOption {
     pub etype: EdgeType,
     pub style: Option<JsonValue>,
@@ -3,22 +3,42 @@
 //! (A relationship is an edge in the graph.)
 //! Used for connecting companies or properties.
 
+use std::sync::Arc;
+
+use bson::DateTime;
 use crud::*;
 use serde::{Deserialize, Serialize};
 use serde_json::value::Value as JsonValue;
 
 use super::{EdgeOption, EntityType, Weight, ID};
+use crate::TGResult;
 
 #[derive(Serialize, Deserialize, Debug, Clone, CRUD)]
+#[crud(collection = "graph", discriminator = "etype", value = "Relationship")]
 pub struct Relationship {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
     pub id: Option<ID>,
+    #[crud(single_index = "asc")]
     pub etype: EntityType,
+    #[crud(compound_index(name = "by_source_target", order = 1, unique))]
     pub source: ID,
+    #[crud(compound_index(name = "by_source_target", order = 2))]
     pub target: ID,
+    #[crud(single_index = "desc", validate(range(min = 0.0)))]
     pub weight: Option<Weight>,
     pub data: Option<JsonValue>,
+    #[crud(nested)]
     pub option: EdgeOption,
+    #[crud(created_at)]
+    pub created_at: Option<DateTime>,
+    #[crud(updated_at, single_index = "asc")]
+    pub updated_at: Option<DateTime>,
+    #[crud(soft_delete)]
+    pub deleted_at: Option<DateTime>,
+    /// documents written before versioning are at version `0`
+    #[serde(default)]
+    #[crud(version)]
+    pub version: i64,
 }
 
 impl Relationship {
@@ -37,6 +57,63 @@ impl Relationship {
             weight,
             data,
             option: option.unwrap_or_default(),
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+            version: 0,
+        }
+    }
+}
+
+/// the relationships from or to a vertex, whose id is `id`
+fn relationships_of(id: ID) -> Filter<Relationship> {
+    let fields = Relationship::fields();
+    fields.source().eq(id).or(fields.target().eq(id))
+}
+
+/// soft delete the relationships from or to a vertex, at the time the vertex was deleted, so
+/// that `restore_relationships_of` tells them from the ones deleted before
+pub(crate) async fn delete_relationships_of(
+    id: ID,
+    deleted_at: DateTime,
+    client: &MongoClient,
+) -> TGResult<()> {
+    let mut client = client.clone();
+    client.set_clock(Arc::new(FixedClock::new(deleted_at)));
+    for relationship in client.find(relationships_of(id)).await? {
+        if let Some(id) = relationship.id {
+            MongoCRUD::<Relationship>::delete(&client, id).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// purge the relationships from or to a vertex, soft-deleted or not
+pub(crate) async fn purge_relationships_of(id: ID, client: &MongoClient) -> TGResult<()> {
+    let live: Vec<Relationship> = client.find(relationships_of(id)).await?;
+    let deleted = client.read_deleted(relationships_of(id)).await?;
+    for relationship in live.into_iter().chain(deleted) {
+        if let Some(id) = relationship.id {
+            MongoCRUD::<Relationship>::purge(client, id).await?;
         }
     }
+
+    Ok(())
+}
+
+/// restore the relationships from or to a vertex, which were deleted along with it
+pub(crate) async fn restore_relationships_of(
+    id: ID,
+    deleted_at: DateTime,
+    client: &MongoClient,
+) -> TGResult<()> {
+    let filter = relationships_of(id).and(Relationship::fields().deleted_at().eq(deleted_at));
+    for relationship in client.read_deleted(filter).await? {
+        if let Some(id) = relationship.id {
+            MongoCRUD::<Relationship>::restore(client, id).await?;
+        }
+    }
+
+    Ok(())
 }
@@ -1,9 +1,14 @@
 //! Repository
 //!
-//! Trait
+//! Trait. Deleting, restoring or purging a company or a property cascades to its relationships,
+//! all in one transaction on a replica set, see `crud::transaction`.
 
 use async_trait::async_trait;
-use crud::{MongoCRUD, MongoClientFactory};
+use bson::{DateTime, Document};
+use crud::{
+    BulkResult, CrudStream, Fields, MongoCRUD, MongoClientFactory, Order, Page, PageRequest, Query,
+    ValidationAction, ValidationLevel,
+};
 
 use crate::entities::*;
 use crate::TGResult;
@@ -26,17 +31,52 @@ pub trait Repository: Send + Sync + MongoClientFactory {
         self.client().create(category).await
     }
 
+    /// fails with `CrudError::Conflict` if the category was changed since it was read
+    async fn update_category(&self, category: Category) -> TGResult<Category> {
+        self.client().update(category).await
+    }
+
+    /// soft delete, see `restore_category` and `purge_category`
     async fn delete_category(&self, id: ID) -> TGResult<Option<Category>> {
         self.client().delete(id).await
     }
 
+    async fn restore_category(&self, id: ID) -> TGResult<Option<Category>> {
+        self.client().restore(id).await
+    }
+
+    async fn purge_category(&self, id: ID) -> TGResult<Option<Category>> {
+        self.client().purge(id).await
+    }
+
     /// `View` is a collection who contains all the industrial data.
     /// All the `View`s name must be unique.
+    #[allow(clippy::diverging_sub_expression)]
     async fn get_view(&self, _name: &str) -> TGResult<Option<View>> {
         // TODO: implement
         unimplemented!()
     }
 
+    // ===========================================================================
+    // graph
+    // ===========================================================================
+
+    /// companies, properties and relationships matching the filter, by one query
+    async fn get_graph_entities(&self, filter: Document) -> TGResult<Vec<GraphEntity>> {
+        self.client().read_polymorphic(filter).await
+    }
+
+    /// reject graph documents that cannot be read as a company, a property or a relationship.
+    /// Documents already invalid can still be updated.
+    async fn set_graph_validator(&self) -> TGResult<()> {
+        self.client()
+            .set_validator_by_polymorphic::<GraphEntity>(
+                ValidationLevel::Moderate,
+                ValidationAction::Error,
+            )
+            .await
+    }
+
     // ===========================================================================
     // company
     // ===========================================================================
@@ -45,12 +85,73 @@ pub trait Repository: Send + Sync + MongoClientFactory {
         self.client().read(id).await
     }
 
+    /// names and categories of all the companies, for listing
+    async fn get_company_summaries(&self) -> TGResult<Vec<CompanySummary>> {
+        MongoCRUD::<Company>::read_all_as(self.client()).await
+    }
+
+    /// companies of an industry, sorted by name
+    async fn get_companies_by_category(&self, category: Industry) -> TGResult<Vec<Company>> {
+        let fields = Company::fields();
+        let query = Query::new(fields.category().eq(category)).sort(&fields.name(), Order::Asc);
+        self.client().find(query).await
+    }
+
+    /// a page of the companies, sorted by name
+    async fn get_companies_page(&self, page: PageRequest) -> TGResult<Page<Company>> {
+        let query = Query::default().sort(&Company::fields().name(), Order::Asc);
+        self.client().paginate(query, page).await
+    }
+
     async fn save_company(&self, company: Company) -> TGResult<Company> {
         self.client().create(company).await
     }
 
+    /// companies of an import, those failing are reported along with their indexes
+    async fn save_companies(&self, companies: Vec<Company>) -> TGResult<BulkResult<ID>> {
+        self.client().create_many(companies, false).await
+    }
+
+    /// fails with `CrudError::Conflict` if the company was changed since it was read
+    async fn update_company(&self, company: Company) -> TGResult<Company> {
+        self.client().update(company).await
+    }
+
+    /// soft delete, along with the relationships from or to the company, see `restore_company`
+    /// and `purge_company`
     async fn delete_company(&self, id: ID) -> TGResult<Option<Company>> {
-        self.client().delete(id).await
+        self.client()
+            .with_transaction(|tx| async move { MongoCRUD::<Company>::delete(&tx, id).await })
+            .await
+    }
+
+    /// restore, along with the relationships deleted with the company
+    async fn restore_company(&self, id: ID) -> TGResult<Option<Company>> {
+        self.client()
+            .with_transaction(|tx| async move { MongoCRUD::<Company>::restore(&tx, id).await })
+            .await
+    }
+
+    /// delete for good, along with the relationships from or to the company, soft-deleted or not
+    async fn purge_company(&self, id: ID) -> TGResult<Option<Company>> {
+        self.client()
+            .with_transaction(|tx| async move { MongoCRUD::<Company>::purge(&tx, id).await })
+            .await
+    }
+
+    /// update the present fields only, e.g. `option.position` when a vertex is moved
+    async fn patch_company(&self, id: ID, patch: CompanyPatch) -> TGResult<Option<Company>> {
+        self.client().patch(id, patch).await
+    }
+
+    /// companies created or updated at or after `since`, the oldest first
+    async fn get_companies_modified_since(&self, since: DateTime) -> TGResult<Vec<Company>> {
+        self.client().read_modified_since(since).await
+    }
+
+    /// full text search on companies' name
+    async fn search_companies(&self, query: &str) -> TGResult<Vec<Company>> {
+        self.client().search_text(query).await
     }
 
     // ===========================================================================
@@ -65,8 +166,41 @@ pub trait Repository: Send + Sync + MongoClientFactory {
         self.client().create(property).await
     }
 
+    /// fails with `CrudError::Conflict` if the property was changed since it was read
+    async fn update_property(&self, property: Property) -> TGResult<Property> {
+        self.client().update(property).await
+    }
+
+    /// soft delete, along with the relationships from or to the property, see `restore_property`
+    /// and `purge_property`
     async fn delete_property(&self, id: ID) -> TGResult<Option<Property>> {
-        self.client().delete(id).await
+        self.client()
+            .with_transaction(|tx| async move { MongoCRUD::<Property>::delete(&tx, id).await })
+            .await
+    }
+
+    /// restore, along with the relationships deleted with the property
+    async fn restore_property(&self, id: ID) -> TGResult<Option<Property>> {
+        self.client()
+            .with_transaction(|tx| async move { MongoCRUD::<Property>::restore(&tx, id).await })
+            .await
+    }
+
+    /// delete for good, along with the relationships from or to the property, soft-deleted or not
+    async fn purge_property(&self, id: ID) -> TGResult<Option<Property>> {
+        self.client()
+            .with_transaction(|tx| async move { MongoCRUD::<Property>::purge(&tx, id).await })
+            .await
+    }
+
+    /// update the present fields only, e.g. `option.position` when a vertex is moved
+    async fn patch_property(&self, id: ID, patch: PropertyPatch) -> TGResult<Option<Property>> {
+        self.client().patch(id, patch).await
+    }
+
+    /// full text search on properties' label
+    async fn search_properties(&self, query: &str) -> TGResult<Vec<Property>> {
+        self.client().search_text(query).await
     }
 
     // ===========================================================================
@@ -77,11 +211,69 @@ pub trait Repository: Send + Sync + MongoClientFactory {
         self.client().read(id).await
     }
 
+    /// all the relationships, `batch_size` at a time, for passes over the whole graph
+    async fn stream_relationships<'a>(
+        &'a self,
+        batch_size: u32,
+    ) -> TGResult<CrudStream<'a, Relationship>> {
+        self.client().stream_all(batch_size).await
+    }
+
     async fn save_relationship(&self, relationship: Relationship) -> TGResult<Relationship> {
         self.client().create(relationship).await
     }
 
+    /// all the relationships or none of them on a replica set, e.g. the edges of a subgraph
+    async fn save_relationships(
+        &self,
+        relationships: Vec<Relationship>,
+    ) -> TGResult<Vec<Relationship>> {
+        self.client()
+            .with_transaction(|tx| {
+                let relationships = relationships.clone();
+                async move {
+                    let mut saved = vec![];
+                    for relationship in relationships {
+                        saved.push(tx.create(relationship).await?);
+                    }
+                    Ok(saved)
+                }
+            })
+            .await
+    }
+
+    /// fails with `CrudError::Conflict` if the relationship was changed since it was read
+    async fn update_relationship(&self, relationship: Relationship) -> TGResult<Relationship> {
+        self.client().update(relationship).await
+    }
+
+    /// soft delete, see `restore_relationship` and `purge_relationship`
     async fn delete_relationship(&self, id: ID) -> TGResult<Option<Relationship>> {
         self.client().delete(id).await
     }
+
+    async fn restore_relationship(&self, id: ID) -> TGResult<Option<Relationship>> {
+        self.client().restore(id).await
+    }
+
+    async fn purge_relationship(&self, id: ID) -> TGResult<Option<Relationship>> {
+        self.client().purge(id).await
+    }
+
+    /// relationships created or updated at or after `since`, the oldest first
+    async fn get_relationships_modified_since(
+        &self,
+        since: DateTime,
+    ) -> TGResult<Vec<Relationship>> {
+        self.client().read_modified_since(since).await
+    }
+
+    /// update the present fields only
+    async fn patch_relationship(
+        &self,
+        id: ID,
+        patch: RelationshipPatch,
+    ) -> TGResult<Option<Relationship>> {
+        self.client().patch(id, patch).await
+    }
 }
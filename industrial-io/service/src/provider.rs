@@ -2,7 +2,10 @@
 //!
 //! Used for implementing domain specific logic for CRUD operations.
 
-use crud::{MongoClient, MongoClientFactory, RedisClient};
+use std::path::Path;
+use std::sync::Arc;
+
+use crud::{LocalKeyFile, MongoClient, MongoClientFactory, RedisClient};
 
 pub struct Provider {
     pub cache_client: RedisClient,
@@ -67,6 +70,17 @@ impl ProviderBuilder {
         Ok(self)
     }
 
+    /// key of the encrypted fields, see `LocalKeyFile`
+    pub fn persistence_key_file<P: AsRef<Path>>(&mut self, path: P) -> anyhow::Result<&mut Self> {
+        let key_file = LocalKeyFile::open(path)?;
+        let client = self
+            .persistence_client
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("Persistence client not set"))?;
+        client.set_key_provider(Arc::new(key_file));
+        Ok(self)
+    }
+
     pub fn build(&mut self) -> anyhow::Result<Provider> {
         let cache_client = self
             .cache_client
@@ -82,6 +96,10 @@ impl ProviderBuilder {
         if persistence_client.collection.is_empty() {
             return Err(anyhow::anyhow!("Persistence collection not set"));
         }
+        // companies and properties have encrypted fields
+        if persistence_client.key_provider().is_none() {
+            return Err(anyhow::anyhow!("Persistence key not set"));
+        }
 
         Ok(Provider {
             cache_client,
@@ -101,6 +119,9 @@ mod test_provider {
 
     #[tokio::test]
     async fn provider_create_and_build_is_ok() {
+        let key_path = std::env::temp_dir().join(format!("provider-{}.key", std::process::id()));
+        LocalKeyFile::create(&key_path).unwrap();
+
         let rp = Provider::create()
             .cache_uri(CACHE_URI)
             .await
@@ -112,8 +133,11 @@ mod test_provider {
             .unwrap()
             .persistence_collection(PERSISTENCE_COLLECTION)
             .unwrap()
+            .persistence_key_file(&key_path)
+            .unwrap()
             .build();
 
         assert!(rp.is_ok());
+        std::fs::remove_file(key_path).unwrap();
     }
 }